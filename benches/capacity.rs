@@ -0,0 +1,91 @@
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use libevent::{Base, Event, EventFlags};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::os::unix::io::AsRawFd;
+use std::os::unix::net::UnixStream;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+const NUM_FDS: usize = 512;
+
+/// Wraps the system allocator to count allocations, so that the benchmark
+/// can compare the churn of registering fds on a cold and a warmed base.
+/// libevent's own allocations bypass it.
+struct CountingAlloc;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+/// Registers a read event on each of `fds` and drops them again, returning
+/// the number of allocations this took.
+fn register_all(base: &mut Base, fds: &[UnixStream]) -> usize {
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    let events: Vec<_> = fds
+        .iter()
+        .map(|fd| {
+            base.spawn_local(
+                Event::from_fd(fd, EventFlags::READ, None),
+                |_ev, _fd, _flags| {},
+            )
+            .expect("Failed to spawn event")
+        })
+        .collect();
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - before;
+
+    drop(events);
+    allocations
+}
+
+/// Registers `NUM_FDS` fds on a fresh base, once created via `Base::new` and
+/// once via `Base::new_with_capacity`, after checking that warming up spares
+/// the latter some allocations.
+fn fd_registration(c: &mut Criterion) {
+    let (fd, _peer) = UnixStream::pair().expect("Failed to create socket pair");
+    let fds: Vec<_> = (0..NUM_FDS)
+        .map(|_| fd.try_clone().expect("Failed to clone socket"))
+        .collect();
+    let capacity = fds.iter().map(|fd| fd.as_raw_fd()).max().unwrap() as usize + 1;
+
+    let cold = register_all(&mut Base::new().expect("Failed to create base"), &fds);
+    let warm = register_all(
+        &mut Base::new_with_capacity(capacity).expect("Failed to create base"),
+        &fds,
+    );
+    println!(
+        "allocations for {} fds: cold {}, warm {}",
+        NUM_FDS, cold, warm
+    );
+    assert!(warm < cold, "Warming up saved no allocations");
+
+    // Like any base created via `Base::new`, those of the iterations keep
+    // their backend's fds open, so the runs are kept short to stay within
+    // the fd limit.
+    let mut group = c.benchmark_group("capacity");
+    group.sample_size(10);
+    group.warm_up_time(Duration::from_millis(100));
+    group.measurement_time(Duration::from_millis(500));
+    group.throughput(Throughput::Elements(NUM_FDS as u64));
+    group.bench_function("register_512_cold", |b| {
+        b.iter(|| register_all(&mut Base::new().unwrap(), &fds))
+    });
+    group.bench_function("register_512_warm", |b| {
+        b.iter(|| register_all(&mut Base::new_with_capacity(capacity).unwrap(), &fds))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, fd_registration);
+criterion_main!(benches);
@@ -0,0 +1,70 @@
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use libevent::{Base, TimerSet};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::Duration;
+
+const NUM_TIMERS: usize = 100;
+
+/// Wraps the system allocator to count allocations, so that the benchmark
+/// can check that firing timers does not allocate.
+struct CountingAlloc;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+/// Fires `NUM_TIMERS` timers of a `TimerSet` per turn, after checking that
+/// doing so allocates nothing.
+fn timer_set_dispatch(c: &mut Criterion) {
+    let mut base = Base::new().expect("Failed to create base");
+    let fired = Rc::new(Cell::new(0usize));
+
+    // A short interval makes every timer fire on every turn after sleeping
+    // a bit; libevent does not re-arm zero-interval ones.
+    let mut timers = TimerSet::with_capacity(&mut base, NUM_TIMERS);
+    for _ in 0..NUM_TIMERS {
+        let fired = fired.clone();
+        timers
+            .add(Duration::from_millis(1), move || fired.set(fired.get() + 1))
+            .expect("Failed to add timer");
+    }
+
+    // Warm up, so that libevent has sized its internal queues.
+    thread::sleep(Duration::from_millis(20));
+    base.turn();
+
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    for _ in 0..10 {
+        thread::sleep(Duration::from_millis(20));
+        base.turn();
+    }
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - before;
+    assert_eq!(allocations, 0, "Firing timers allocated");
+
+    let mut group = c.benchmark_group("timer_set");
+    group.throughput(Throughput::Elements(NUM_TIMERS as u64));
+    group.bench_function("fire_100", |b| b.iter(|| base.turn()));
+    group.finish();
+
+    drop(timers);
+    assert!(fired.get() >= 11 * NUM_TIMERS);
+}
+
+criterion_group!(benches, timer_set_dispatch);
+criterion_main!(benches);
@@ -0,0 +1,40 @@
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use libevent::{Base, Oneshot};
+use std::cell::Cell;
+use std::rc::Rc;
+use std::time::Duration;
+
+const NUM_TIMERS: usize = 1_000_000;
+
+/// Registers `NUM_TIMERS` zero-timeout oneshots and runs the base until they
+/// have all fired, which mostly measures per-event spawn + dispatch overhead.
+fn oneshot_dispatch(c: &mut Criterion) {
+    let mut group = c.benchmark_group("oneshot");
+    group.sample_size(10);
+    group.throughput(Throughput::Elements(NUM_TIMERS as u64));
+
+    group.bench_function("spawn_and_fire_1m", |b| {
+        b.iter(|| {
+            let mut base = Base::new().expect("Failed to create base");
+            let fired = Rc::new(Cell::new(0usize));
+
+            for _ in 0..NUM_TIMERS {
+                let fired = fired.clone();
+                base.spawn(Oneshot::new(Duration::ZERO), move |ev| {
+                    fired.set(fired.get() + 1);
+                    // Hand the event back for cleanup once it has fired.
+                    ev.stop().expect("Failed to stop oneshot");
+                })
+                .expect("Failed to spawn oneshot");
+            }
+
+            base.run();
+            assert_eq!(fired.get(), NUM_TIMERS);
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, oneshot_dispatch);
+criterion_main!(benches);
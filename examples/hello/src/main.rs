@@ -31,7 +31,8 @@ fn main() {
         .event_new(None, libevent::EventFlags::PERSIST, hello_callback, None)
         .expect("Faled to allocate event");
 
-    base.event_add(ev, Some(Duration::from_secs(2)));
+    base.event_add(ev, Some(Duration::from_secs(2)))
+        .expect("Failed to add event");
 
     let mut b: usize = 0;
     let ev = Interval::new(Duration::from_secs(2));
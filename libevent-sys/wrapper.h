@@ -1,4 +1,7 @@
 #include <event.h>
+#include <event2/listener.h>
+#include <event2/http.h>
+#include <event2/keyvalq_struct.h>
 
 #ifdef EVENT__HAVE_OPENSSL
 #include <event2/bufferevent_ssl.h>
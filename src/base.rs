@@ -1,13 +1,19 @@
 #![allow(dead_code)]
 
 use bitflags::bitflags;
+use std::cell::{Cell, RefCell};
 use std::io;
 use std::os::raw::{c_int, c_short, c_void};
 use std::ptr::NonNull;
-use std::time::Duration;
+use std::sync::atomic::Ordering;
+use std::time::{Duration, Instant};
 
 use super::event::*;
-use crate::EventCallbackWrapper;
+use crate::dispatch::{lock, Dispatch, Registration};
+use crate::{
+    blocking, clock, notifier, signal, unwind, AsRawEvent, EventCallbackWrapper, EventConfig,
+    LibeventError, WrapperHead,
+};
 
 /// A file descriptor in libevent.
 pub type EvutilSocket = c_int;
@@ -21,18 +27,67 @@ pub type EventCallbackCtx = *mut c_void;
 /// The event callback's raw flags type.
 pub type EventCallbackFlags = c_short;
 
+/// A closure run by `Base::on_prepare`.
+type PrepareHook = Box<dyn FnMut() + Send>;
+
+/// A closure run by `Base::on_loop_exit`.
+type ExitHook = Box<dyn FnMut(ExitReason) + Send>;
+
+/// A closure run by `Base::on_shutdown`.
+type ShutdownHook = Box<dyn FnOnce() + Send>;
+
 /// Convenience function for mapping Rust's `Duration` to libevent's `timeval`.
-fn to_timeval(duration: Duration) -> libevent_sys::timeval {
+pub(crate) fn to_timeval(duration: Duration) -> libevent_sys::timeval {
     libevent_sys::timeval {
         tv_sec: duration.as_secs() as _,
         tv_usec: duration.subsec_micros() as _,
     }
 }
 
+/// Checks that `timeout` fits into libevent's `timeval`, which timeouts are
+/// passed to libevent as, e.g. by `Base::event_add` and `Base::spawn*`.
+///
+/// A zero timeout is valid, and makes an event fire on the next iteration of
+/// the loop, since libevent considers it expired by the time it next checks
+/// its timers. Rust's `Duration` cannot be negative, so there is no need to
+/// check for that.
+pub(crate) fn validate_timeout(timeout: Duration) -> io::Result<Duration> {
+    if libc::time_t::try_from(timeout.as_secs()).is_err() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Timeout too large for timeval",
+        ));
+    }
+    Ok(timeout)
+}
+
 /// Wrapper for libevent's `event_base` which is responsible for executing
 /// associated events.
 pub struct Base {
     base: NonNull<libevent_sys::event_base>,
+    prepare_hooks: RefCell<Vec<PrepareHook>>,
+    exit_hooks: RefCell<Vec<ExitHook>>,
+    /// The earliest deadline scheduled via `loopexit` which has not yet made
+    /// the loop exit.
+    loopexit_deadline: Cell<Option<Instant>>,
+    /// The number of calls into the loop so far.
+    iterations: Cell<u64>,
+    shutdown_hooks: Vec<ShutdownHook>,
+    /// Whether the base was created with `EVENT_BASE_FLAG_PRECISE_TIMER`.
+    pub(crate) precise_timers: bool,
+    /// The window which timeouts of spawned events are rounded up to.
+    timer_coalescing: Option<Duration>,
+    /// The priority given to pure timers spawned on the base.
+    timer_priority: Option<c_int>,
+    /// Whether oneshot timers spawned from callbacks inherit their priority.
+    inherit_priority: bool,
+    pub(crate) virtual_clock: Option<clock::VirtualClock>,
+    pub(crate) signals: std::collections::HashMap<c_int, signal::SignalDispatch>,
+    pub(crate) remote: Option<notifier::Remote>,
+    pub(crate) blocking_pool: Option<blocking::BlockingPool>,
+    pub(crate) dispatch: std::sync::Arc<Dispatch>,
+    /// Whether the `event_base` is freed once the `Base` is dropped.
+    owned: bool,
 }
 
 /// The handle that abstracts over libevent's API in Rust.
@@ -51,6 +106,25 @@ impl Base {
         }
     }
 
+    /// Creates a new instance of `Base` for a loop expecting to watch about
+    /// `expected_fds` fds, as a shorthand for `EventConfig::expected_fds`,
+    /// whose docs describe the heuristic.
+    pub fn new_with_capacity(expected_fds: usize) -> Result<Self, io::Error> {
+        Self::with_config(EventConfig::new().expected_fds(expected_fds))
+    }
+
+    /// Creates a new instance of `Base` with `EVENT_BASE_FLAG_PRECISE_TIMER`
+    /// set, for timers with sub-millisecond precision.
+    ///
+    /// How precise timers get depends on the backend: with `epoll`, libevent
+    /// then waits on a `timerfd` instead of the millisecond-granular
+    /// `epoll_wait` timeout, while `kqueue` is precise either way. Backends
+    /// like `poll` stay at millisecond precision. The flag may cost some
+    /// performance, which is why libevent does not set it by default.
+    pub fn new_precise_timers() -> Result<Self, io::Error> {
+        Self::with_config(EventConfig::new().precise_timers(true))
+    }
+
     /// Creates a new instance of `Base` using a raw, non-null `event_base`
     /// pointer.
     ///
@@ -60,7 +134,38 @@ impl Base {
     /// internally. Thus the caller is responsible for checking the
     /// `event_base` validity.
     pub unsafe fn from_raw(base: NonNull<libevent_sys::event_base>) -> Self {
-        Base { base }
+        Self::from_raw_base(base, false)
+    }
+
+    /// Creates a new instance of `Base` driving an `event_base` created
+    /// elsewhere, e.g. by another library or by C code. If `owned` is set,
+    /// the `event_base` is freed once the `Base` is dropped; otherwise, its
+    /// creator remains responsible for freeing it.
+    ///
+    /// # Safety
+    ///
+    /// The caller is responsible for checking the `event_base` validity. If
+    /// `owned` is set, nothing else may free the `event_base`, and all events
+    /// on it must be dropped before the `Base` is.
+    pub unsafe fn from_raw_base(base: NonNull<libevent_sys::event_base>, owned: bool) -> Self {
+        Base {
+            base,
+            prepare_hooks: Default::default(),
+            exit_hooks: Default::default(),
+            loopexit_deadline: Cell::new(None),
+            iterations: Cell::new(0),
+            shutdown_hooks: Vec::new(),
+            precise_timers: false,
+            timer_coalescing: None,
+            timer_priority: None,
+            inherit_priority: false,
+            virtual_clock: None,
+            signals: Default::default(),
+            remote: None,
+            blocking_pool: None,
+            dispatch: Default::default(),
+            owned,
+        }
     }
 
     /// Exposes the raw, non-null `event_base` pointer.
@@ -74,20 +179,167 @@ impl Base {
         self.base
     }
 
+    /// Registers a hook which runs before each batch of callbacks the loop
+    /// dispatches, regardless of whether any events are ready by then. This
+    /// suits frame-based systems which need to do some work every turn.
+    ///
+    /// libevent 2.1 has no notion of a "prepare" watcher, so while any hooks
+    /// are registered, `loop_` steps through the iterations itself via
+    /// `EVLOOP_ONCE`, running the hooks before each. Such a step polls as
+    /// often as it takes for at least one callback to run, so the hooks do
+    /// not run before every poll: a `turn` runs them once, and a `run` once
+    /// per batch of callbacks. Hooks do not run once the loop finds no events
+    /// left. A `loopbreak` or `loopexit` from within a hook or callback ends
+    /// the loop after the current step. Hooks must be `Send`, like the `Base`
+    /// itself.
+    pub fn on_prepare(&mut self, cb: impl FnMut() + Send + 'static) {
+        self.prepare_hooks.get_mut().push(Box::new(cb));
+    }
+
+    /// Runs the prepare hooks, taking them out while they run, so that one
+    /// may run the loop itself without tripping over the borrow.
+    fn run_prepare_hooks(&self) {
+        let mut hooks = std::mem::take(&mut *self.prepare_hooks.borrow_mut());
+        for hook in hooks.iter_mut() {
+            hook();
+        }
+
+        let mut slot = self.prepare_hooks.borrow_mut();
+        hooks.append(&mut slot);
+        *slot = hooks;
+    }
+
+    /// Runs the loop as given by `flags` one iteration at a time, running
+    /// the prepare hooks before each, and returns the result of the last
+    /// `event_base_loop` call. `detached` is left out of the check whether
+    /// any events are left, as for `loop_detached`.
+    fn loop_stepwise(
+        &self,
+        flags: LoopFlags,
+        detached: Option<NonNull<libevent_sys::event>>,
+    ) -> c_int {
+        let base = self.base.as_ptr();
+        let counted = (libevent_sys::EVENT_BASE_COUNT_ACTIVE
+            | libevent_sys::EVENT_BASE_COUNT_VIRTUAL
+            | libevent_sys::EVENT_BASE_COUNT_ADDED) as c_int;
+        let all_pending = (libevent_sys::EV_TIMEOUT
+            | libevent_sys::EV_READ
+            | libevent_sys::EV_WRITE
+            | libevent_sys::EV_SIGNAL) as c_short;
+
+        loop {
+            // The check libevent makes before each iteration, which would
+            // otherwise come after the hooks, and could not leave out
+            // `detached`.
+            let detached = detached.map_or(false, |event| unsafe {
+                libevent_sys::event_pending(event.as_ptr(), all_pending, std::ptr::null_mut()) != 0
+            });
+            let count = unsafe { libevent_sys::event_base_get_num_events(base, counted) };
+            let empty = count <= detached as c_int;
+            if empty && !flags.contains(LoopFlags::NO_EXIT_ON_EMPTY) {
+                return 1;
+            }
+
+            // libevent clears these when entering the loop, which would lose
+            // a break or exit requested from within a hook. Those still set
+            // from an earlier call into the loop are told apart.
+            let requested = || unsafe {
+                (
+                    libevent_sys::event_base_got_break(base) != 0,
+                    libevent_sys::event_base_got_exit(base) != 0,
+                )
+            };
+            let before = requested();
+            self.run_prepare_hooks();
+            let after = requested();
+            if (after.0 && !before.0) || (after.1 && !before.1) {
+                return 0;
+            }
+
+            let exit_code = unsafe {
+                libevent_sys::event_base_loop(base, (flags | LoopFlags::ONCE).bits() as c_int)
+            };
+
+            let done = exit_code != 0
+                || flags.intersects(LoopFlags::ONCE | LoopFlags::NONBLOCK)
+                || unsafe { libevent_sys::event_base_got_exit(base) } != 0
+                || unsafe { libevent_sys::event_base_got_break(base) } != 0;
+            if done {
+                return exit_code;
+            }
+        }
+    }
+
+    /// Registers a closure to be run each time a call into the loop returns,
+    /// with the reason it returned, e.g. for logging or restart logic. This
+    /// runs once per `turn` or `run`.
+    ///
+    /// The reason is the one given by `loop_`, before helpers like
+    /// `run_until_cancelled` map it to their own. Hooks must be `Send`, like
+    /// the `Base` itself.
+    pub fn on_loop_exit(&mut self, cb: impl FnMut(ExitReason) + Send + 'static) {
+        self.exit_hooks.get_mut().push(Box::new(cb));
+    }
+
+    /// Runs the exit hooks, taking them out while they run, so that one may
+    /// run the loop itself without tripping over the borrow.
+    fn run_exit_hooks(&self, reason: ExitReason) {
+        let mut hooks = std::mem::take(&mut *self.exit_hooks.borrow_mut());
+        for hook in hooks.iter_mut() {
+            hook(reason);
+        }
+
+        let mut slot = self.exit_hooks.borrow_mut();
+        hooks.append(&mut slot);
+        *slot = hooks;
+    }
+
+    /// Registers a closure to be run once the `Base` is dropped, e.g. to
+    /// close fds or flush logs tied to the loop's lifetime. Closures run in
+    /// reverse order of registration.
+    ///
+    /// Note that dropping a `Base` does not free the underlying `event_base`,
+    /// which may be borrowed via `from_raw`, unless it was wrapped as owned
+    /// via `from_raw_base`. Hooks must be `Send`, since the `Base` may be
+    /// dropped on another thread than the one registering them.
+    pub fn on_shutdown(&mut self, cb: impl FnOnce() + Send + 'static) {
+        self.shutdown_hooks.push(Box::new(cb));
+    }
+
     /// Wrapper for libevent's `event_base_loop`, which runs the event loop in
     /// a manner defined by the `LoopFlags` input.
     pub fn loop_(&self, flags: LoopFlags) -> ExitReason {
-        let exit_code = unsafe {
-            libevent_sys::event_base_loop(self.base.as_ptr(), flags.bits() as i32) as i32
-        };
+        self.loop_detached(flags, None)
+    }
+
+    /// Like `loop_`, but `detached`, an event of one of the crate's helpers,
+    /// does not keep the loop from exiting with `NoPendingEvents`, just like
+    /// libevent's internal events.
+    pub(crate) fn loop_detached(
+        &self,
+        flags: LoopFlags,
+        detached: Option<NonNull<libevent_sys::event>>,
+    ) -> ExitReason {
+        self.iterations.set(self.iterations.get() + 1);
+        if let Some(limit) = lock(&self.dispatch.turn_limit).as_ref() {
+            limit.reset();
+        }
 
-        match exit_code {
+        let exit_code = if self.prepare_hooks.borrow().is_empty() && detached.is_none() {
+            unsafe { libevent_sys::event_base_loop(self.base.as_ptr(), flags.bits() as i32) as i32 }
+        } else {
+            self.loop_stepwise(flags, detached)
+        };
+        let reason = match exit_code {
+            // A callback panicked and broke the loop.
+            _ if self.claim_panic() => ExitReason::Panicked,
             0 => {
                 unsafe {
                     // Technically mutually-exclusive from `got_break`, but
                     // the check in `event_base_loop` comes first, so the logic
                     // here matches.
                     if libevent_sys::event_base_got_exit(self.base.as_ptr()) != 0i32 {
+                        self.loopexit_deadline.set(None);
                         ExitReason::GotExit
                     } else if libevent_sys::event_base_got_break(self.base.as_ptr()) != 0i32 {
                         ExitReason::GotBreak
@@ -100,29 +352,444 @@ impl Base {
             -1 => ExitReason::Error,
             1 => ExitReason::NoPendingEvents,
             _ => ExitReason::Unknown { flags, exit_code },
-        }
+        };
+
+        self.run_exit_hooks(reason);
+
+        reason
+    }
+
+    /// Returns how often the loop was entered via `loop_` (and thus `turn`
+    /// or `run`) on this `Base`, e.g. for tests asserting how many turns
+    /// occurred. This counts calls, not libevent's internal iterations.
+    pub fn iteration_count(&self) -> u64 {
+        self.iterations.get()
     }
 
     /// Wrapper for libevent's `event_base_loopexit`, which tells the running
-    /// event loop to exit after a specified `Duration`.
-    pub fn loopexit(&self, timeout: Duration) -> i32 {
+    /// event loop to exit after a specified `Duration`. A timeout which does
+    /// not fit into a `timeval` fails like libevent does, with -1.
+    pub fn loopexit(&self, timeout: Duration) -> Result<(), LibeventError> {
+        if validate_timeout(timeout).is_err() {
+            return Err(LibeventError::LoopExit(-1));
+        }
+
         let tv = to_timeval(timeout);
-        unsafe {
+        let code = unsafe {
             let tv_cast = &tv as *const libevent_sys::timeval;
-            libevent_sys::event_base_loopexit(self.as_raw().as_ptr(), tv_cast) as i32
+            libevent_sys::event_base_loopexit(self.as_raw().as_ptr(), tv_cast)
+        };
+        LibeventError::check(code, LibeventError::LoopExit)?;
+
+        // A deadline too far out to represent is as good as none.
+        let deadline = match Instant::now().checked_add(timeout) {
+            Some(deadline) => deadline,
+            None => return Ok(()),
+        };
+        let earliest = match self.loopexit_deadline.get() {
+            Some(current) => current.min(deadline),
+            None => deadline,
+        };
+        self.loopexit_deadline.set(Some(earliest));
+
+        Ok(())
+    }
+
+    /// Schedules the loop to exit after `timeout` like `loopexit`, unless an
+    /// exit scheduled via `loopexit` is already pending at or before then, so
+    /// the loop exits after the soonest of several deadlines.
+    ///
+    /// libevent keeps every exit scheduled via `loopexit` pending until it
+    /// fires, so an exit which lost out to an earlier one may still cut a
+    /// later run short. Skipping redundant ones avoids piling those up.
+    pub fn loopexit_min(&self, timeout: Duration) -> Result<(), LibeventError> {
+        // A deadline too far out to represent is later than any pending one.
+        let deadline = Instant::now().checked_add(timeout);
+
+        match (self.loopexit_deadline.get(), deadline) {
+            (Some(_), None) => Ok(()),
+            (Some(current), Some(deadline)) if current <= deadline => Ok(()),
+            _ => self.loopexit(timeout),
+        }
+    }
+
+    /// Returns the time left until the loop exits as scheduled via
+    /// `loopexit`, or `None` if no exit is pending, or only too far out to
+    /// represent as an `Instant`. Once the deadline has passed, this is zero
+    /// until the loop actually exits.
+    ///
+    /// If several exits were scheduled, this refers to the earliest one, and
+    /// no more are tracked once the loop has exited.
+    pub fn loopexit_remaining(&self) -> Option<Duration> {
+        self.loopexit_deadline
+            .get()
+            .map(|deadline| deadline.saturating_duration_since(Instant::now()))
+    }
+
+    /// Returns the raw `event`s added to or active on the base for `fd`, found
+    /// via libevent's `event_base_foreach_event`, e.g. to find all watchers
+    /// before closing a socket. Events of the crate's helpers, e.g. of
+    /// `add_signal`, are left out, but libevent's own internal ones, such as
+    /// its signal pipe, are not.
+    ///
+    /// libevent does not know about the Rust handles of the events, so match
+    /// these against `AsRawEvent::as_raw` of the handles at hand.
+    pub fn events_for_fd(&self, fd: EvutilSocket) -> Vec<NonNull<libevent_sys::event>> {
+        extern "C" fn collect(
+            _base: *const libevent_sys::event_base,
+            event: *const libevent_sys::event,
+            ctx: *mut c_void,
+        ) -> c_int {
+            let (fd, events) =
+                unsafe { &mut *(ctx as *mut (EvutilSocket, Vec<NonNull<libevent_sys::event>>)) };
+
+            if unsafe { libevent_sys::event_get_fd(event) } == *fd {
+                events.extend(NonNull::new(event as *mut libevent_sys::event));
+            }
+            0
+        }
+
+        let mut ctx = (fd, Vec::new());
+        unsafe {
+            libevent_sys::event_base_foreach_event(
+                self.base.as_ptr(),
+                Some(collect),
+                &mut ctx as *mut _ as *mut c_void,
+            )
+        };
+
+        let helpers = self.dispatch.pending_helpers();
+        ctx.1.retain(|event| !helpers.contains(event));
+        ctx.1
+    }
+
+    /// Panics with a list of the events still added to or active on the base,
+    /// if any, e.g. at the end of a test to catch handles which were never
+    /// cleaned up. Events of the crate's helpers, e.g. of `add_signal`, are
+    /// left out, but libevent's own internal ones, such as its signal pipe,
+    /// are not.
+    ///
+    /// Events are described by their fd (or signal) and flags, since libevent
+    /// does not know about the Rust handles or their labels.
+    pub fn assert_no_leaked_events(&self) {
+        extern "C" fn collect(
+            _base: *const libevent_sys::event_base,
+            event: *const libevent_sys::event,
+            ctx: *mut c_void,
+        ) -> c_int {
+            let (helpers, events) =
+                unsafe { &mut *(ctx as *mut (Vec<NonNull<libevent_sys::event>>, Vec<String>)) };
+            if helpers
+                .iter()
+                .any(|helper| std::ptr::eq(helper.as_ptr(), event))
+            {
+                return 0;
+            }
+            let (fd, flags) = unsafe {
+                (
+                    libevent_sys::event_get_fd(event),
+                    libevent_sys::event_get_events(event),
+                )
+            };
+            let flags = EventFlags::from_bits_truncate(flags as u32);
+            events.push(format!("{:p} (fd {}, {:?})", event, fd, flags));
+            0
+        }
+
+        let mut ctx: (_, Vec<String>) = (self.dispatch.pending_helpers(), Vec::new());
+        unsafe {
+            libevent_sys::event_base_foreach_event(
+                self.base.as_ptr(),
+                Some(collect),
+                &mut ctx as *mut _ as *mut c_void,
+            )
+        };
+
+        let leaked = ctx.1;
+
+        if !leaked.is_empty() {
+            panic!(
+                "{} event(s) still registered on the base: {}",
+                leaked.len(),
+                leaked.join(", ")
+            );
+        }
+    }
+
+    /// Removes every event spawned via `Base::spawn*` via `event_del`, so
+    /// that no more callbacks fire, e.g. to reset state between tests. Unlike
+    /// dropping the base, this leaves it usable.
+    ///
+    /// The closures of events added via `spawn` are dropped right away. Those
+    /// added via `spawn_local` are dropped along with their handles, as
+    /// usual, as is that of the event whose callback calls this. Timers on
+    /// the virtual clock are removed too. Events of listeners, bufferevents
+    /// and the crate's own helpers, such as `add_signal` or `remote`, are
+    /// left alone, as are raw events.
+    pub fn clear_all_events(&mut self) {
+        // The closure of a callback calling this is still in use.
+        let running = self.running_event();
+
+        let events = self.dispatch.user_events();
+        for &event in &events {
+            // Reclaiming a closure may have freed other events it held.
+            let registration = match self.dispatch.registration(event) {
+                Some(registration) => registration,
+                None => continue,
+            };
+
+            unsafe {
+                if registration.owning && event.as_ptr() != running {
+                    free_spawned(event);
+                } else {
+                    libevent_sys::event_del(event.as_ptr());
+                }
+            }
+        }
+
+        // These were never added to libevent in the first place.
+        if let Some(clock) = self.virtual_clock.as_mut() {
+            clock.remove(&events);
+        }
+    }
+
+    /// Returns the event whose callback is running, or null if not called from
+    /// within one.
+    fn running_event(&self) -> *mut libevent_sys::event {
+        // libevent 2.1 dereferences the current callback unchecked, so only
+        // ask while one is running.
+        if self.dispatch.running.load(Ordering::Relaxed) == 0 {
+            return std::ptr::null_mut();
+        }
+        unsafe { libevent_sys::event_base_get_running_event(self.base.as_ptr()) }
+    }
+
+    /// Wrapper for libevent's `event_base_dump_events`, which describes all
+    /// events added to or active on the base, e.g. to find out why a loop
+    /// does not exit. The exact format is up to libevent.
+    pub fn dump_events(&self) -> io::Result<String> {
+        // libevent writes to a `FILE`, so go through an anonymous temp file.
+        let file = unsafe { libc::tmpfile() };
+        if file.is_null() {
+            return Err(io::Error::last_os_error());
+        }
+
+        let mut dump = Vec::new();
+        let ok = unsafe {
+            libevent_sys::event_base_dump_events(self.base.as_ptr(), file as *mut _);
+
+            let mut ok = libc::fflush(file) == 0;
+            libc::rewind(file);
+
+            let mut chunk = [0u8; 4096];
+            while ok {
+                let read = libc::fread(chunk.as_mut_ptr() as *mut c_void, 1, chunk.len(), file);
+                dump.extend_from_slice(&chunk[..read]);
+                if read < chunk.len() {
+                    ok = libc::ferror(file) == 0;
+                    break;
+                }
+            }
+            ok
+        };
+        let error = io::Error::last_os_error();
+        unsafe { libc::fclose(file) };
+
+        if ok {
+            Ok(String::from_utf8_lossy(&dump).into_owned())
+        } else {
+            Err(error)
         }
     }
 
     /// Wrapper for libevent's `event_base_loopbreak`, which tells the running
     /// event loop to break immediately.
-    pub fn loopbreak(&self) -> i32 {
-        unsafe { libevent_sys::event_base_loopbreak(self.as_raw().as_ptr()) as i32 }
+    pub fn loopbreak(&self) -> Result<(), LibeventError> {
+        let code = unsafe { libevent_sys::event_base_loopbreak(self.as_raw().as_ptr()) };
+        LibeventError::check(code, LibeventError::LoopBreak)
     }
 
     /// Wrapper for libevent's `event_base_loopcontinue`, which tells the
     /// running event loop to resume searching for active events.
-    pub fn loopcontinue(&self) -> i32 {
-        unsafe { libevent_sys::event_base_loopcontinue(self.as_raw().as_ptr()) as i32 }
+    pub fn loopcontinue(&self) -> Result<(), LibeventError> {
+        let code = unsafe { libevent_sys::event_base_loopcontinue(self.as_raw().as_ptr()) };
+        LibeventError::check(code, LibeventError::LoopContinue)
+    }
+
+    /// Wrapper for libevent's `event_base_priority_init`, which sets the
+    /// number of priority levels available to events on this base.
+    ///
+    /// This must be called before any events are activated.
+    ///
+    /// Within a priority level, each loop iteration runs all active events in
+    /// the order they became active, so equal-priority events which are
+    /// always ready take turns rather than starving each other; no rotation
+    /// is needed on top. Lower levels only run once higher ones are idle,
+    /// though, so a busy high-priority event can starve everything below it.
+    pub fn priority_init(&mut self, npriorities: c_int) -> Result<(), LibeventError> {
+        let code =
+            unsafe { libevent_sys::event_base_priority_init(self.base.as_ptr(), npriorities) };
+        LibeventError::check(code, LibeventError::PriorityInit)
+    }
+
+    /// Wrapper for libevent's `event_base_get_method`, which returns the name
+    /// of the backend in use, e.g. "epoll" or "kqueue".
+    pub fn method(&self) -> String {
+        let method = unsafe { libevent_sys::event_base_get_method(self.base.as_ptr()) };
+        unsafe { std::ffi::CStr::from_ptr(method) }
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    /// Returns a best-effort estimate of the granularity of timers on this
+    /// base, based on the known characteristics of its backend, to help
+    /// decide whether sub-millisecond timers are viable.
+    ///
+    /// This is the resolution of the timeout the backend waits with; the OS
+    /// scheduler may still add latency on top.
+    pub fn timer_resolution(&self) -> Duration {
+        const MILLI: Duration = Duration::from_millis(1);
+        const MICRO: Duration = Duration::from_micros(1);
+
+        match self.method().as_str() {
+            // With precise timers, epoll waits on a timerfd instead.
+            "epoll" if self.precise_timers => MICRO,
+            // These take their timeout in whole milliseconds.
+            "epoll" | "poll" | "devpoll" | "win32" => MILLI,
+            // `select` takes a `timeval`, `kqueue` and `evport` a `timespec`,
+            // but libevent keeps time in microseconds.
+            _ => MICRO,
+        }
+    }
+
+    /// Returns a best-effort description of how libevent delivers signals to
+    /// this base, derived from its backend, e.g. to debug signal handling.
+    ///
+    /// `kqueue` watches signals natively via `EVFILT_SIGNAL`. The other known
+    /// backends install a signal handler which writes to an internal socket
+    /// pair, reported as "socketpair". Returns `None` for unknown backends.
+    pub fn signal_method(&self) -> Option<String> {
+        let method = match self.method().as_str() {
+            "kqueue" => "kqueue",
+            "epoll" | "poll" | "select" | "devpoll" | "evport" | "win32" => "socketpair",
+            _ => return None,
+        };
+        Some(method.to_owned())
+    }
+
+    /// Makes events spawned via `Base::spawn*` from now on round their
+    /// timeouts up to the nearest multiple of `window`, e.g. 10ms, so that
+    /// timers scheduled close together fire in the same loop iteration and
+    /// wake up the process less often. `None` turns this off again.
+    ///
+    /// This trades timer accuracy for efficiency, and does not affect events
+    /// which were already spawned.
+    pub fn set_timer_coalescing(&mut self, window: Option<Duration>) {
+        self.timer_coalescing = window.filter(|window| *window > Duration::from_secs(0));
+    }
+
+    /// Makes pure timers spawned via `Base::spawn*` from now on run at
+    /// `priority`, e.g. 0 to keep time-critical timers from being starved by
+    /// busy sockets at lower priorities. `None` leaves them at libevent's
+    /// default, the middle priority.
+    ///
+    /// libevent services each timer as an event of its own, so this is
+    /// applied per event rather than to timers as a whole. The priority must
+    /// be below `num_priorities`, so `priority_init` comes first; events
+    /// which were already spawned are not affected.
+    pub fn set_timer_priority(&mut self, priority: Option<c_int>) -> io::Result<()> {
+        if let Some(priority) = priority {
+            if priority < 0 || priority >= self.num_priorities() {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "Timer priority out of range",
+                ));
+            }
+        }
+
+        self.timer_priority = priority;
+        Ok(())
+    }
+
+    /// Applies the timer priority, if any, to the freshly assigned `raw_ev`
+    /// if `ev` is a pure timer.
+    fn apply_timer_priority<T>(
+        &self,
+        ev: &Event<Inactive<T>>,
+        raw_ev: NonNull<libevent_sys::event>,
+    ) {
+        if let Some(priority) = self.timer_priority {
+            if ev.inactive_fd().is_none() && ev.inactive_timeout().is_some() {
+                unsafe { libevent_sys::event_priority_set(raw_ev.as_ptr(), priority) };
+            }
+        }
+    }
+
+    /// Makes oneshot timers spawned via `Base::spawn*` from within a callback,
+    /// such as work scheduled via `defer`, run at the priority of the event
+    /// whose callback spawned them, rather than at the default one. This
+    /// keeps follow-ups of high-priority events from being starved by ready
+    /// events of lower priority.
+    ///
+    /// Inheriting takes precedence over `set_timer_priority`. Oneshots which
+    /// are spawned outside of callbacks are not affected.
+    pub fn set_priority_inheritance(&mut self, enabled: bool) {
+        self.inherit_priority = enabled;
+    }
+
+    /// Gives the freshly assigned `raw_ev` the priority of the running event,
+    /// if priority inheritance is on and `ev` is a oneshot timer spawned from
+    /// within a callback.
+    fn apply_inherited_priority<T>(
+        &self,
+        ev: &Event<Inactive<T>>,
+        raw_ev: NonNull<libevent_sys::event>,
+    ) {
+        let oneshot = ev.inactive_fd().is_none()
+            && ev.inactive_timeout().is_some()
+            && !ev.inactive_flags().contains(EventFlags::PERSIST);
+        if !self.inherit_priority || !oneshot {
+            return;
+        }
+
+        let running = self.running_event();
+        if !running.is_null() {
+            unsafe {
+                let priority = libevent_sys::event_get_priority(running);
+                libevent_sys::event_priority_set(raw_ev.as_ptr(), priority);
+            }
+        }
+    }
+
+    /// Rounds `timeout` up to the timer coalescing window, if any.
+    fn coalesce_timeout(&self, timeout: Option<Duration>) -> Option<Duration> {
+        match (timeout, self.timer_coalescing) {
+            (Some(timeout), Some(window)) => {
+                let window_nanos = window.as_nanos();
+                let windows = (timeout.as_nanos() + window_nanos - 1) / window_nanos;
+                let nanos = (windows * window_nanos).min(u128::from(u64::MAX));
+                Some(Duration::from_nanos(nanos as u64))
+            }
+            _ => timeout,
+        }
+    }
+
+    /// Wrapper for libevent's `event_base_get_npriorities`, which returns the
+    /// number of priority levels of this base (1, unless changed via
+    /// `priority_init`).
+    pub fn num_priorities(&self) -> c_int {
+        unsafe { libevent_sys::event_base_get_npriorities(self.base.as_ptr()) }
+    }
+
+    /// Wrapper for libevent's `event_base_get_num_events`, which returns the
+    /// number of events currently added to the base, counting neither
+    /// libevent's internal ones nor those of the crate's helpers, e.g. of
+    /// `add_signal`.
+    pub fn event_count(&self) -> usize {
+        let flags = libevent_sys::EVENT_BASE_COUNT_ADDED as c_int;
+        let count =
+            unsafe { libevent_sys::event_base_get_num_events(self.base.as_ptr(), flags) as usize };
+        count.saturating_sub(self.dispatch.pending_helpers().len())
     }
 
     /// Wrapper for libevent's `event_new`, which allocates and initializes a
@@ -134,13 +801,8 @@ impl Base {
         callback: EventCallbackFn,
         callback_ctx: Option<EventCallbackCtx>,
     ) -> Option<NonNull<libevent_sys::event>> {
-        let fd: EvutilSocket = if let Some(fd) = fd {
-            // Actual fd
-            fd
-        } else {
-            // Timer
-            -1
-        };
+        // Timers have no fd
+        let fd: EvutilSocket = fd.unwrap_or(-1);
 
         let callback_ctx = if let Some(ctx) = callback_ctx {
             ctx
@@ -170,14 +832,9 @@ impl Base {
         flags: EventFlags,
         callback: EventCallbackFn,
         callback_ctx: Option<EventCallbackCtx>,
-    ) -> c_int {
-        let fd: EvutilSocket = if let Some(fd) = fd {
-            // Actual fd
-            fd
-        } else {
-            // Timer
-            -1
-        };
+    ) -> Result<(), LibeventError> {
+        // Timers have no fd
+        let fd: EvutilSocket = fd.unwrap_or(-1);
 
         let callback_ctx = if let Some(ctx) = callback_ctx {
             ctx
@@ -185,7 +842,7 @@ impl Base {
             std::ptr::null::<c_void>() as *mut std::ffi::c_void
         };
 
-        unsafe {
+        let code = unsafe {
             libevent_sys::event_assign(
                 ev.as_ptr(),
                 self.as_raw().as_ptr(),
@@ -194,17 +851,26 @@ impl Base {
                 Some(callback),
                 callback_ctx,
             )
-        }
+        };
+        LibeventError::check(code, LibeventError::EventAssign)
     }
 
     /// Wrapper for libevent's `event_add`, which activates an initialized
     /// `event` for a pre-defined `Base` and a given timeout interval.
+    ///
+    /// Raw events added this way are suspended by `suspend_io` like spawned
+    /// ones, but otherwise left alone, e.g. by `clear_all_events`. A timeout
+    /// which does not fit into a `timeval` fails like libevent does, with -1.
     pub fn event_add(
         &self,
         event: NonNull<libevent_sys::event>,
         timeout: Option<Duration>,
-    ) -> c_int {
-        unsafe {
+    ) -> Result<(), LibeventError> {
+        if timeout.map_or(false, |timeout| validate_timeout(timeout).is_err()) {
+            return Err(LibeventError::EventAdd(-1));
+        }
+
+        let code = unsafe {
             let p = event.as_ptr();
             if let Some(tv) = timeout {
                 libevent_sys::event_add(p, &to_timeval(tv))
@@ -212,18 +878,42 @@ impl Base {
                 // null timeout means no timeout to libevent
                 libevent_sys::event_add(p, std::ptr::null())
             }
-        }
+        };
+        LibeventError::check(code, LibeventError::EventAdd)?;
+
+        self.dispatch.added(event, timeout);
+        Ok(())
     }
 }
 
 unsafe impl Send for Base {}
 
+impl Drop for Base {
+    fn drop(&mut self) {
+        while let Some(hook) = self.shutdown_hooks.pop() {
+            hook();
+        }
+
+        // Delete the signal events right away, so that libevent restores the
+        // previous dispositions instead of leaving its handlers installed.
+        self.signals.clear();
+
+        if self.owned {
+            // Free the events on the base before the base itself.
+            self.remote = None;
+            unsafe { libevent_sys::event_base_free(self.base.as_ptr()) };
+        }
+    }
+}
+
 impl<S, T: Exec<S, F>, F> EventCallbackWrapper<S, T, F> {
-    pub fn new(inner: F, event: Event<S>) -> Box<Self> {
+    pub fn new(inner: F, event: Event<S>, dispatch: std::sync::Arc<Dispatch>) -> Box<Self> {
         Box::new(Self {
+            reclaim: None,
+            dispatch,
             inner,
             event: Some(event),
-            _phantom: std::marker::PhantomData::default(),
+            _phantom: std::marker::PhantomData,
         })
     }
 }
@@ -248,31 +938,98 @@ pub(crate) unsafe extern "C" fn finalize_wrapped_callback<S, T, F>(
     (*event).ev_evcallback.evcb_arg = null_ctx;
 }
 
+/// Deletes and frees an event spawned via `Base::spawn`, along with its
+/// closure, e.g. one of the crate's helper events once it is done with it.
+///
+/// # Safety
+///
+/// `event` must have been spawned via `Base::spawn` and not be freed yet,
+/// i.e. its closure must not stop it, and its callback must not be running.
+pub(crate) unsafe fn free_spawned(event: NonNull<libevent_sys::event>) {
+    let ctx = libevent_sys::event_get_callback_arg(event.as_ptr());
+    libevent_sys::event_del(event.as_ptr());
+
+    // This frees the event, too.
+    if let Some(reclaim) = (*(ctx as *const WrapperHead)).reclaim {
+        reclaim(ctx);
+    }
+}
+
+/// Drops the event handle owned by a callback wrapper, which in turn frees
+/// the event and the wrapper itself, as when the event is stopped from within
+/// its callback.
+unsafe fn reclaim_wrapped_callback<S, T, F>(ctx: EventCallbackCtx) {
+    let cb = &mut *(ctx as *mut EventCallbackWrapper<S, T, F>);
+    drop(cb.event.take());
+}
+
 /// Acts as a C-compatible trampoline for the user-defined callback closure.
+///
+/// This sits on the hot path of every dispatched event, so the wrapper is
+/// borrowed and the raw event taken exactly once, the optional
+/// instrumentation is skipped behind a single check, and the `Exec`
+/// implementations are inlined, which lets timer event-types discard the
+/// unused fd and flags entirely.
 pub(crate) extern "C" fn handle_wrapped_callback<S, T, F>(
     fd: EvutilSocket,
     event: std::os::raw::c_short,
     ctx: EventCallbackCtx,
 ) where
     T: Exec<S, F>,
+    Event<S>: AsRawEvent,
 {
-    let cb_ref = unsafe {
-        let cb: *mut EventCallbackWrapper<S, T, F> = ctx as *mut EventCallbackWrapper<S, T, F>;
-        let _cb_ref: &mut EventCallbackWrapper<S, T, F> = &mut *cb;
-        _cb_ref
-    };
+    let EventCallbackWrapper {
+        inner,
+        event: ev_slot,
+        dispatch,
+        ..
+    } = unsafe { &mut *(ctx as *mut EventCallbackWrapper<S, T, F>) };
 
+    let ev = ev_slot.as_mut().expect("Missing event for callback");
     let flags = EventFlags::from_bits_truncate(event as u32);
-    let ev = cb_ref.event.as_mut().expect("Missing event for callback");
 
+    let raw = unsafe { ev.as_raw() };
+    let instrumented = dispatch.instrumented();
+
+    ev.extras.record_fire();
+    dispatch.count();
+    if instrumented {
+        dispatch.record(raw, fd, flags);
+        dispatch.record_latency(raw);
+    }
     ev.set_in_callback(true);
-    <T as Exec<S, F>>::exec(ev, fd, flags, &mut cb_ref.inner);
+    dispatch.callback_started(instrumented);
+    let busy = instrumented && dispatch.tracks_busy();
+    let exec_limit = ev.extras.exec_limit();
+    let started = (busy || exec_limit.is_some()).then(Instant::now);
+    let base = unsafe { libevent_sys::event_get_base(raw.as_ptr()) };
+    unwind::catch(base, || {
+        <T as Exec<S, F>>::exec(ev, fd, flags, inner);
+    });
+    if let Some(started) = started {
+        let took = started.elapsed();
+        if busy {
+            dispatch.record_busy(took);
+        }
+        if exec_limit.map_or(false, |max| took > max) {
+            unwind::catch(base, || {
+                if ev.extras.exec_time_exceeded(took) {
+                    unsafe { libevent_sys::event_del(raw.as_ptr()) };
+                    ev.set_stopped(true);
+                }
+            });
+        }
+    }
+    unwind::catch(base, || ev.extras.notify(flags));
+    dispatch.callback_finished(instrumented);
     ev.set_in_callback(false);
+    if instrumented {
+        dispatch.record_turn(raw);
+    }
 
     // row, row, row your boat..
     if ev.stopped() {
-        let event = cb_ref.event.take().expect("Missing event for drop");
-        drop(event)
+        drop(ev_slot.take());
     }
 }
 
@@ -281,7 +1038,10 @@ impl Base {
     fn new_event_raw<S, T: Exec<S, F>, F>(
         &mut self,
         ev: &Event<Inactive<T>>,
-    ) -> Option<NonNull<libevent_sys::event>> {
+    ) -> Option<NonNull<libevent_sys::event>>
+    where
+        Event<S>: AsRawEvent,
+    {
         self.event_new(
             ev.inactive_fd(),
             ev.inactive_flags(),
@@ -296,9 +1056,20 @@ impl Base {
         ev: &Event<Inactive<T>>,
         raw_ev: NonNull<libevent_sys::event>,
         cb_wrapped: Box<EventCallbackWrapper<S, T, F>>,
-    ) -> c_int {
+    ) -> Result<(), LibeventError>
+    where
+        Event<S>: AsRawEvent,
+    {
         // Leak the callback wrapper so we can store it as ctx.
         let ctx_ptr = NonNull::from(Box::leak(cb_wrapped));
+        let owning = unsafe { ctx_ptr.as_ref() }.reclaim.is_some();
+        self.dispatch.register(
+            raw_ev,
+            Registration {
+                owning,
+                ..Default::default()
+            },
+        );
 
         self.event_assign(
             raw_ev,
@@ -319,31 +1090,61 @@ impl Base {
         ev: Event<Inactive<T>>,
         cb: F,
     ) -> io::Result<()> {
+        self.spawn_raw(ev, cb, false).map(drop)
+    }
+
+    /// Like `spawn`, but for the crate's own helpers, such as the dispatch
+    /// event of a signal, which free the returned event via `free_spawned`
+    /// once done with it. `clear_all_events` and `suspend_io` leave it alone.
+    pub(crate) fn spawn_helper<T: Exec<Internal<T>, F>, F>(
+        &mut self,
+        ev: Event<Inactive<T>>,
+        cb: F,
+    ) -> io::Result<NonNull<libevent_sys::event>> {
+        self.spawn_raw(ev, cb, true)
+    }
+
+    fn spawn_raw<T: Exec<Internal<T>, F>, F>(
+        &mut self,
+        ev: Event<Inactive<T>>,
+        cb: F,
+        internal: bool,
+    ) -> io::Result<NonNull<libevent_sys::event>> {
+        let timeout = self
+            .coalesce_timeout(ev.inactive_timeout())
+            .map(validate_timeout)
+            .transpose()?;
+
         // First allocate the event with no context, then apply the reference
         // to the closure (and itself) later on.
         let raw_ev = self
             .new_event_raw::<Internal<T>, T, F>(&ev)
             .ok_or_else(|| io::Error::new(io::ErrorKind::Other, "Failed to allocate event"))?;
 
-        let event: Event<Internal<T>> =
-            EventInner::from_raw(raw_ev, Some(finalize_wrapped_callback::<Internal<T>, T, F>))
-                .into();
+        let event: Event<Internal<T>> = EventInner::from_raw(
+            raw_ev,
+            Some(finalize_wrapped_callback::<Internal<T>, T, F>),
+            timeout,
+            self.dispatch.clone(),
+        )
+        .into();
+        let stopped = std::sync::Arc::downgrade(&event.stopped);
 
-        let cb_wrapped = EventCallbackWrapper::new(cb, event);
+        let mut cb_wrapped = EventCallbackWrapper::new(cb, event, self.dispatch.clone());
+        cb_wrapped.reclaim = Some(reclaim_wrapped_callback::<Internal<T>, T, F>);
 
         // Now we can apply the closure + handle to self.
-        if self.assign_event_raw(&ev, raw_ev, cb_wrapped) != 0 {
-            return Err(io::Error::new(
-                io::ErrorKind::Other,
-                "Failed to assign event",
-            ));
+        self.assign_event_raw(&ev, raw_ev, cb_wrapped)?;
+        if internal {
+            self.dispatch.set_internal(raw_ev);
         }
-
-        if self.event_add(raw_ev, ev.inactive_timeout()) != 0 {
-            return Err(io::Error::new(io::ErrorKind::Other, "Failed to add event"));
+        self.apply_timer_priority(&ev, raw_ev);
+        self.apply_inherited_priority(&ev, raw_ev);
+        if !self.schedule_virtual(&ev, raw_ev, stopped, timeout) {
+            self.event_add(raw_ev, timeout)?;
         }
 
-        Ok(())
+        Ok(raw_ev)
     }
 
     /// Activates a given inactive `Event` with thread-local sharing.
@@ -356,6 +1157,30 @@ impl Base {
         ev: Event<Inactive<T>>,
         cb: F,
     ) -> io::Result<Event<Local<T>>> {
+        self.spawn_local_as(ev, cb, false)
+    }
+
+    /// Like `spawn_local`, but for events of the crate's own helpers, which
+    /// are tracked as internal, so that e.g. `event_count` leaves them out.
+    pub(crate) fn spawn_local_internal<T: Exec<LocalWeak<T>, F>, F>(
+        &mut self,
+        ev: Event<Inactive<T>>,
+        cb: F,
+    ) -> io::Result<Event<Local<T>>> {
+        self.spawn_local_as(ev, cb, true)
+    }
+
+    fn spawn_local_as<T: Exec<LocalWeak<T>, F>, F>(
+        &mut self,
+        ev: Event<Inactive<T>>,
+        cb: F,
+        internal: bool,
+    ) -> io::Result<Event<Local<T>>> {
+        let timeout = self
+            .coalesce_timeout(ev.inactive_timeout())
+            .map(validate_timeout)
+            .transpose()?;
+
         // First allocate the event with no context, then apply the reference
         // to the closure (and itself) later on.
         let raw_ev = self
@@ -365,22 +1190,24 @@ impl Base {
         let event: Event<Local<T>> = EventInner::from_raw(
             raw_ev,
             Some(finalize_wrapped_callback::<LocalWeak<T>, T, F>),
+            timeout,
+            self.dispatch.clone(),
         )
         .into();
         let closure_event = event.downgrade();
+        let stopped = std::sync::Arc::downgrade(&event.stopped);
 
-        let cb_wrapped = EventCallbackWrapper::new(cb, closure_event);
+        let cb_wrapped = EventCallbackWrapper::new(cb, closure_event, self.dispatch.clone());
 
         // Now we can apply the closure + handle to self.
-        if self.assign_event_raw(&ev, raw_ev, cb_wrapped) != 0 {
-            return Err(io::Error::new(
-                io::ErrorKind::Other,
-                "Failed to assign event",
-            ));
+        self.assign_event_raw(&ev, raw_ev, cb_wrapped)?;
+        if internal {
+            self.dispatch.set_internal(raw_ev);
         }
-
-        if self.event_add(raw_ev, ev.inactive_timeout()) != 0 {
-            return Err(io::Error::new(io::ErrorKind::Other, "Failed to add event"));
+        self.apply_timer_priority(&ev, raw_ev);
+        self.apply_inherited_priority(&ev, raw_ev);
+        if !self.schedule_virtual(&ev, raw_ev, stopped, timeout) {
+            self.event_add(raw_ev, timeout)?;
         }
 
         Ok(event)
@@ -389,14 +1216,38 @@ impl Base {
 
 /// Enumerates all possible reasons that the event loop may have stopped
 /// running.
+///
+/// `Panicked` means a callback panicked, whose payload `Base::take_panic`
+/// returns.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum ExitReason {
     GotExit,
     GotBreak,
     Error,
     NoPendingEvents,
+    Cancelled,
+    Panicked,
     Unknown { flags: LoopFlags, exit_code: i32 },
 }
 
+impl ExitReason {
+    /// Maps `Error` and `Panicked` to an `io::Error` and every other reason
+    /// to `Ok`, e.g. for `base.run().into_result()?` in functions returning
+    /// `io::Result`.
+    pub fn into_result(self) -> io::Result<()> {
+        match self {
+            ExitReason::Error => Err(io::Error::new(
+                io::ErrorKind::Other,
+                "Event loop exited with an error",
+            )),
+            ExitReason::Panicked => {
+                Err(io::Error::new(io::ErrorKind::Other, "A callback panicked"))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
 bitflags! {
     /// Flags given to the event loop to alter its behavior.
     pub struct LoopFlags: u32 {
@@ -421,3 +1272,35 @@ bitflags! {
         const CLOSED = libevent_sys::EV_CLOSED;
     }
 }
+
+impl EventFlags {
+    /// Whether the event activated because its fd became readable.
+    #[inline]
+    pub fn is_read(&self) -> bool {
+        self.contains(EventFlags::READ)
+    }
+
+    /// Whether the event activated because its fd became writable.
+    #[inline]
+    pub fn is_write(&self) -> bool {
+        self.contains(EventFlags::WRITE)
+    }
+
+    /// Whether the event activated because its timeout expired.
+    #[inline]
+    pub fn is_timeout(&self) -> bool {
+        self.contains(EventFlags::TIMEOUT)
+    }
+
+    /// Whether the event activated because its signal was raised.
+    #[inline]
+    pub fn is_signal(&self) -> bool {
+        self.contains(EventFlags::SIGNAL)
+    }
+
+    /// Whether the event activated because its fd was closed by the peer.
+    #[inline]
+    pub fn is_closed(&self) -> bool {
+        self.contains(EventFlags::CLOSED)
+    }
+}
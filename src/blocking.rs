@@ -0,0 +1,61 @@
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
+
+use crate::Base;
+
+/// A unit of work run by a `BlockingPool`.
+type Job = Box<dyn FnOnce() + Send>;
+
+/// The fixed-size thread pool behind `Base::spawn_blocking`.
+pub(crate) struct BlockingPool {
+    jobs: Sender<Job>,
+}
+
+impl BlockingPool {
+    /// Starts `threads` worker threads, which exit once the pool is dropped
+    /// and the queued jobs are done.
+    pub(crate) fn new(threads: usize) -> Self {
+        let (jobs, rx) = mpsc::channel::<Job>();
+        let rx = Arc::new(Mutex::new(rx));
+
+        for _ in 0..threads.max(1) {
+            let rx = rx.clone();
+            std::thread::spawn(move || loop {
+                let job = match rx.lock().unwrap().recv() {
+                    Ok(job) => job,
+                    Err(_) => return,
+                };
+                // A panicking job must not take the worker down with it.
+                let _ = panic::catch_unwind(AssertUnwindSafe(job));
+            });
+        }
+
+        BlockingPool { jobs }
+    }
+
+    /// Creates a pool with one thread per CPU.
+    pub(crate) fn with_default_size() -> Self {
+        let cpus = unsafe { libc::sysconf(libc::_SC_NPROCESSORS_ONLN) };
+        Self::new(if cpus > 0 { cpus as usize } else { 1 })
+    }
+
+    /// Queues `job` to be run by the next idle worker.
+    pub(crate) fn execute(&self, job: impl FnOnce() + Send + 'static) {
+        // The workers only exit once `jobs` is dropped, so this cannot fail.
+        let _ = self.jobs.send(Box::new(job));
+    }
+}
+
+impl Base {
+    /// Sets the number of threads which run closures passed to
+    /// `spawn_blocking`, and thus how much blocking work may run at once.
+    /// Closures beyond that wait for a thread to become free. Defaults to
+    /// the number of CPUs; 0 is treated as 1.
+    ///
+    /// The threads are started right away. Closures queued on the previous
+    /// threads still run there.
+    pub fn set_blocking_threads(&mut self, n: usize) {
+        self.blocking_pool = Some(BlockingPool::new(n));
+    }
+}
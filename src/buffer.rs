@@ -0,0 +1,347 @@
+use std::fmt;
+use std::fs::File;
+use std::io;
+use std::os::raw::c_void;
+use std::os::unix::io::{FromRawFd, IntoRawFd};
+use std::ptr::NonNull;
+use std::sync::Arc;
+
+/// End-of-line styles understood by `EvBuffer::readln`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EolStyle {
+    /// Any sequence of `\r` and `\n` characters. Fast, but ambiguous for
+    /// protocols where blank lines are meaningful.
+    Any,
+    /// `\r\n` or a lone `\n`, as most text protocols accept in practice.
+    Crlf,
+    /// Strictly `\r\n`.
+    CrlfStrict,
+    /// Strictly `\n`.
+    Lf,
+    /// A NUL byte.
+    Nul,
+}
+
+impl EolStyle {
+    fn as_raw(self) -> libevent_sys::evbuffer_eol_style {
+        match self {
+            EolStyle::Any => libevent_sys::evbuffer_eol_style_EVBUFFER_EOL_ANY,
+            EolStyle::Crlf => libevent_sys::evbuffer_eol_style_EVBUFFER_EOL_CRLF,
+            EolStyle::CrlfStrict => libevent_sys::evbuffer_eol_style_EVBUFFER_EOL_CRLF_STRICT,
+            EolStyle::Lf => libevent_sys::evbuffer_eol_style_EVBUFFER_EOL_LF,
+            EolStyle::Nul => libevent_sys::evbuffer_eol_style_EVBUFFER_EOL_NUL,
+        }
+    }
+}
+
+/// Wrapper for libevent's `evbuffer`, a byte queue optimized for network I/O
+/// which backs bufferevents and the HTTP layer.
+pub struct EvBuffer {
+    inner: NonNull<libevent_sys::evbuffer>,
+    owned: bool,
+}
+
+impl EvBuffer {
+    /// Creates a new, empty `EvBuffer`.
+    pub fn new() -> io::Result<Self> {
+        let inner = unsafe { libevent_sys::evbuffer_new() };
+
+        if let Some(inner) = NonNull::new(inner) {
+            Ok(unsafe { Self::from_raw(inner, true) })
+        } else {
+            Err(io::Error::new(
+                io::ErrorKind::Other,
+                "Failed to create evbuffer",
+            ))
+        }
+    }
+
+    /// Creates a new instance of `EvBuffer` using a raw, non-null `evbuffer`
+    /// pointer. If `owned`, the `evbuffer` is freed on drop.
+    ///
+    /// # Safety
+    ///
+    /// This function expects a valid, non-null pointer, which must outlive
+    /// the returned `EvBuffer` if not `owned`.
+    pub unsafe fn from_raw(inner: NonNull<libevent_sys::evbuffer>, owned: bool) -> Self {
+        EvBuffer { inner, owned }
+    }
+
+    /// Exposes the raw, non-null `evbuffer` pointer.
+    ///
+    /// # Safety
+    ///
+    /// This function returns a valid, non-null `evbuffer` pointer which by
+    /// itself is safe. However, this function serves as an escape hatch to do
+    /// unsafe things.
+    pub unsafe fn as_raw(&self) -> NonNull<libevent_sys::evbuffer> {
+        self.inner
+    }
+
+    /// Wrapper for libevent's `evbuffer_get_length`, which returns the number
+    /// of bytes stored in the buffer.
+    pub fn len(&self) -> usize {
+        unsafe { libevent_sys::evbuffer_get_length(self.inner.as_ptr()) }
+    }
+
+    /// Returns whether the buffer holds no bytes.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Wrapper for libevent's `evbuffer_add`, which appends a copy of `data`
+    /// to the end of the buffer.
+    pub fn add(&mut self, data: &[u8]) -> io::Result<()> {
+        let ret = unsafe {
+            libevent_sys::evbuffer_add(
+                self.inner.as_ptr(),
+                data.as_ptr() as *const c_void,
+                data.len(),
+            )
+        };
+
+        if ret == 0 {
+            Ok(())
+        } else {
+            Err(io::Error::new(
+                io::ErrorKind::Other,
+                "Failed to add to evbuffer",
+            ))
+        }
+    }
+
+    /// Appends formatted text to the end of the buffer, as produced by
+    /// `format_args!`. The pieces are appended as they are formatted, without
+    /// building an intermediate `String`. See also `evbuffer_add_printf!`.
+    pub fn add_fmt(&mut self, args: fmt::Arguments<'_>) -> io::Result<()> {
+        fmt::Write::write_fmt(self, args)
+            .map_err(|_| io::Error::new(io::ErrorKind::Other, "Failed to add to evbuffer"))
+    }
+
+    /// Wrapper for libevent's `evbuffer_add_reference`, which appends `data`
+    /// to the buffer without copying it. Useful for sending large static or
+    /// shared responses.
+    ///
+    /// The buffer holds on to its clone of the `Arc` until libevent is done
+    /// with the data, i.e. once it has been drained or the buffer is freed.
+    pub fn add_reference(&mut self, data: Arc<[u8]>) -> io::Result<()> {
+        let (ptr, len) = (data.as_ptr(), data.len());
+        let extra = Box::into_raw(Box::new(data));
+
+        let ret = unsafe {
+            libevent_sys::evbuffer_add_reference(
+                self.inner.as_ptr(),
+                ptr as *const c_void,
+                len,
+                Some(release_reference),
+                extra as *mut c_void,
+            )
+        };
+
+        if ret == 0 {
+            Ok(())
+        } else {
+            // libevent never took ownership, so release it here instead.
+            drop(unsafe { Box::from_raw(extra) });
+            Err(io::Error::new(
+                io::ErrorKind::Other,
+                "Failed to add reference to evbuffer",
+            ))
+        }
+    }
+
+    /// Wrapper for libevent's `evbuffer_add_buffer_reference`, which appends
+    /// the contents of `src` to the buffer without copying them, e.g. to
+    /// broadcast one payload to many connections.
+    ///
+    /// `src` keeps its data. The bytes are shared read-only between `src` and
+    /// every buffer referring to them, and are freed once all of them have
+    /// drained them. libevent refuses to share data which itself came from
+    /// `add_buffer_reference` or `add_file`.
+    pub fn add_buffer_reference(&mut self, src: &EvBuffer) -> io::Result<()> {
+        let ret = unsafe {
+            libevent_sys::evbuffer_add_buffer_reference(self.inner.as_ptr(), src.inner.as_ptr())
+        };
+
+        if ret == 0 {
+            Ok(())
+        } else {
+            Err(io::Error::new(
+                io::ErrorKind::Other,
+                "Failed to add buffer reference to evbuffer",
+            ))
+        }
+    }
+
+    /// Wrapper for libevent's `evbuffer_add_file`, which appends `length`
+    /// bytes of `file`, starting at `offset`, to the buffer. Where available,
+    /// libevent uses `sendfile` or `mmap`, so the contents need not pass
+    /// through userspace when the buffer is written to a socket.
+    ///
+    /// The file is closed once its data has been drained from the buffer.
+    pub fn add_file(&mut self, file: File, offset: u64, length: u64) -> io::Result<()> {
+        let too_large = || io::Error::new(io::ErrorKind::InvalidInput, "File range too large");
+        let offset = offset.try_into().map_err(|_| too_large())?;
+        let length = length.try_into().map_err(|_| too_large())?;
+
+        // From here on, libevent owns the fd, if adding it succeeds.
+        let fd = file.into_raw_fd();
+
+        let ret =
+            unsafe { libevent_sys::evbuffer_add_file(self.inner.as_ptr(), fd, offset, length) };
+
+        if ret == 0 {
+            Ok(())
+        } else {
+            // libevent never took ownership, so close it here instead.
+            drop(unsafe { File::from_raw_fd(fd) });
+            Err(io::Error::new(
+                io::ErrorKind::Other,
+                "Failed to add file to evbuffer",
+            ))
+        }
+    }
+
+    /// Wrapper for libevent's `evbuffer_search`, which returns the offset of
+    /// the first occurrence of `needle` in the buffer, if any. Useful for
+    /// finding delimiters in framed protocols.
+    pub fn search(&self, needle: &[u8]) -> Option<usize> {
+        let found = unsafe {
+            libevent_sys::evbuffer_search(
+                self.inner.as_ptr(),
+                needle.as_ptr() as *const _,
+                needle.len(),
+                std::ptr::null(),
+            )
+        };
+
+        // A position of -1 means there was no match.
+        if found.pos < 0 {
+            None
+        } else {
+            Some(found.pos as usize)
+        }
+    }
+
+    /// Wrapper for libevent's `evbuffer_copyout`, which copies up to `len`
+    /// bytes from the front of the buffer without draining them.
+    pub fn peek(&self, len: usize) -> Vec<u8> {
+        let mut data = vec![0u8; len.min(self.len())];
+
+        let copied = unsafe {
+            libevent_sys::evbuffer_copyout(
+                self.inner.as_ptr(),
+                data.as_mut_ptr() as *mut c_void,
+                data.len(),
+            )
+        };
+
+        data.truncate(copied.max(0) as usize);
+        data
+    }
+
+    /// Wrapper for libevent's `evbuffer_peek`, which exposes up to `max` of
+    /// the buffer's internal chunks, from the front, as borrowed slices. This
+    /// lets parsers read the data in place, without copying it out first.
+    ///
+    /// The slices are only valid as long as the buffer is not modified, which
+    /// the borrow ensures.
+    pub fn peek_iovecs(&self, max: usize) -> Vec<&[u8]> {
+        // First ask libevent how many chunks the whole buffer takes, so that
+        // a generous `max` does not allocate room for chunks which are not
+        // there.
+        let needed = unsafe {
+            libevent_sys::evbuffer_peek(
+                self.inner.as_ptr(),
+                -1,
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+                0,
+            )
+        };
+        let n_vec = (needed.max(0) as usize).min(max);
+        let mut vecs: Vec<libevent_sys::evbuffer_iovec> = Vec::with_capacity(n_vec);
+
+        let filled = unsafe {
+            libevent_sys::evbuffer_peek(
+                self.inner.as_ptr(),
+                -1,
+                std::ptr::null_mut(),
+                vecs.as_mut_ptr(),
+                n_vec as _,
+            )
+        };
+
+        // libevent returns how many chunks it would take to cover the whole
+        // buffer, but only fills in as many as there was room for.
+        let filled = (filled.max(0) as usize).min(n_vec);
+        unsafe { vecs.set_len(filled) };
+
+        vecs.iter()
+            .filter(|vec| vec.iov_len > 0)
+            .map(|vec| unsafe {
+                std::slice::from_raw_parts(vec.iov_base as *const u8, vec.iov_len)
+            })
+            .collect()
+    }
+
+    /// Wrapper for libevent's `evbuffer_readln`, which drains a single line
+    /// from the front of the buffer and returns it without its terminator.
+    ///
+    /// Returns `None` if no complete line is buffered yet. Invalid UTF-8 is
+    /// replaced with `U+FFFD`.
+    pub fn readln(&mut self, style: EolStyle) -> Option<String> {
+        let mut len = 0;
+        let line =
+            unsafe { libevent_sys::evbuffer_readln(self.inner.as_ptr(), &mut len, style.as_raw()) };
+
+        if line.is_null() {
+            return None;
+        }
+
+        let bytes = unsafe { std::slice::from_raw_parts(line as *const u8, len) };
+        let line_str = String::from_utf8_lossy(bytes).into_owned();
+
+        // The line was allocated by libevent with `malloc`.
+        unsafe { libc::free(line as *mut libc::c_void) };
+
+        Some(line_str)
+    }
+}
+
+impl fmt::Write for EvBuffer {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.add(s.as_bytes()).map_err(|_| fmt::Error)
+    }
+}
+
+/// Appends formatted text to an `EvBuffer`, in the manner of libevent's
+/// `evbuffer_add_printf` but with Rust's formatting syntax.
+///
+/// ```no_run
+/// # fn main() -> std::io::Result<()> {
+/// let mut buf = libevent::EvBuffer::new()?;
+/// libevent::evbuffer_add_printf!(buf, "Content-Length: {}\r\n", 5)?;
+/// # Ok(())
+/// # }
+/// ```
+#[macro_export]
+macro_rules! evbuffer_add_printf {
+    ($buf:expr, $($arg:tt)*) => {
+        $buf.add_fmt(::std::format_args!($($arg)*))
+    };
+}
+
+/// Cleanup callback for `EvBuffer::add_reference`, which releases the data's
+/// `Arc` once libevent no longer refers to it.
+extern "C" fn release_reference(_data: *const c_void, _len: usize, extra: *mut c_void) {
+    drop(unsafe { Box::from_raw(extra as *mut Arc<[u8]>) });
+}
+
+impl Drop for EvBuffer {
+    fn drop(&mut self) {
+        if self.owned {
+            unsafe { libevent_sys::evbuffer_free(self.inner.as_ptr()) };
+        }
+    }
+}
@@ -0,0 +1,420 @@
+use std::io;
+use std::os::raw::{c_int, c_short, c_void};
+use std::os::unix::io::{IntoRawFd, RawFd};
+use std::ptr::NonNull;
+use std::time::Duration;
+
+use crate::base::to_timeval;
+use crate::{unwind, Base, EvBuffer, EventFlags};
+
+/// How long `BufferEvent::close_after_write` waits for the peer to make
+/// progress on pending writes before giving up on them.
+const CLOSE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How `BufferEvent::flush` flushes, mirroring libevent's
+/// `bufferevent_flush_mode`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FlushMode {
+    /// Processes data as usual, e.g. as during the loop.
+    Normal,
+    /// Forces out as much data as possible, even if filters would rather
+    /// wait for more.
+    Flush,
+    /// Like `Flush`, but also signals that no more data will follow, e.g. to
+    /// let a compression filter write its trailer.
+    Finished,
+}
+
+impl FlushMode {
+    fn as_raw(self) -> libevent_sys::bufferevent_flush_mode {
+        match self {
+            FlushMode::Normal => libevent_sys::bufferevent_flush_mode_BEV_NORMAL,
+            FlushMode::Flush => libevent_sys::bufferevent_flush_mode_BEV_FLUSH,
+            FlushMode::Finished => libevent_sys::bufferevent_flush_mode_BEV_FINISHED,
+        }
+    }
+}
+
+/// What a read callback set via `BufferEvent::set_read_callback` wants to
+/// happen next.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReadAction {
+    /// Keeps reading.
+    Continue,
+    /// Stops reading, which leaves further data in the kernel and applies
+    /// backpressure to the peer, until resumed via `enable(EventFlags::READ)`.
+    Pause,
+    /// Stops reading and writing, and shuts down the socket. The bufferevent
+    /// itself is owned by the `BufferEvent`, and thus freed once dropped.
+    Close,
+}
+
+/// Type of the read callback of a `BufferEvent`.
+type ReadCallback = Box<dyn FnMut(&mut EvBuffer) -> ReadAction>;
+
+/// Wrapper for libevent's socket-based `bufferevent`, which buffers reads
+/// from and writes to a socket from within the event loop of a `Base`.
+pub struct BufferEvent {
+    inner: NonNull<libevent_sys::bufferevent>,
+    input: EvBuffer,
+    output: EvBuffer,
+    /// Boxed once more, so that libevent gets a thin pointer to it.
+    read_cb: Option<Box<ReadCallback>>,
+}
+
+impl BufferEvent {
+    /// Wrapper for libevent's `bufferevent_socket_new`, which takes ownership
+    /// of `socket` and closes it once the `BufferEvent` is dropped.
+    ///
+    /// libevent requires the socket to be non-blocking, since a blocking one
+    /// would stall the whole loop, so it is switched to non-blocking mode
+    /// here. Failing that, an error is returned.
+    pub fn new<S: IntoRawFd>(base: &mut Base, socket: S) -> io::Result<Self> {
+        unsafe { Self::with_raw_base(base.as_raw(), socket) }
+    }
+
+    /// Helper for `new`, for callers which only hold the raw base, such as
+    /// accept callbacks.
+    ///
+    /// # Safety
+    ///
+    /// `base` must be a valid `event_base` which outlives the `BufferEvent`.
+    pub(crate) unsafe fn with_raw_base<S: IntoRawFd>(
+        base: NonNull<libevent_sys::event_base>,
+        socket: S,
+    ) -> io::Result<Self> {
+        let fd = socket.into_raw_fd();
+
+        if unsafe { libevent_sys::evutil_make_socket_nonblocking(fd) } != 0 {
+            let error = io::Error::last_os_error();
+            unsafe { libc::close(fd) };
+            return Err(error);
+        }
+
+        let inner = unsafe {
+            libevent_sys::bufferevent_socket_new(
+                base.as_ptr(),
+                fd,
+                libevent_sys::bufferevent_options_BEV_OPT_CLOSE_ON_FREE as _,
+            )
+        };
+
+        match NonNull::new(inner) {
+            Some(inner) => Ok(unsafe { Self::from_raw(inner) }),
+            None => {
+                unsafe { libc::close(fd) };
+                Err(io::Error::new(
+                    io::ErrorKind::Other,
+                    "Failed to create bufferevent",
+                ))
+            }
+        }
+    }
+
+    /// Creates a new instance of `BufferEvent` taking ownership of a raw,
+    /// non-null `bufferevent` pointer, e.g. a filtering one set up via
+    /// `bufferevent_filter_new`, which is freed on drop.
+    ///
+    /// # Safety
+    ///
+    /// This function expects a valid, non-null pointer which is not owned by
+    /// anything else.
+    pub unsafe fn from_raw(inner: NonNull<libevent_sys::bufferevent>) -> Self {
+        BufferEvent {
+            inner,
+            input: buffer(libevent_sys::bufferevent_get_input(inner.as_ptr())),
+            output: buffer(libevent_sys::bufferevent_get_output(inner.as_ptr())),
+            read_cb: None,
+        }
+    }
+
+    /// Exposes the raw, non-null `bufferevent` pointer.
+    ///
+    /// # Safety
+    ///
+    /// This function returns a valid, non-null `bufferevent` pointer which by
+    /// itself is safe. However, this function serves as an escape hatch to do
+    /// unsafe things.
+    pub unsafe fn as_raw(&self) -> NonNull<libevent_sys::bufferevent> {
+        self.inner
+    }
+
+    /// Returns the underlying socket.
+    pub fn fd(&self) -> RawFd {
+        unsafe { libevent_sys::bufferevent_getfd(self.inner.as_ptr()) }
+    }
+
+    /// Frees the bufferevent without closing its socket, and returns the
+    /// socket, e.g. to hand the connection over to another subsystem. The
+    /// caller becomes responsible for closing it.
+    ///
+    /// Data left in the input and output buffers is discarded, so callers
+    /// should drain them first. The socket stays in non-blocking mode.
+    pub fn take_fd(self) -> RawFd {
+        let fd = self.fd();
+
+        // Detaching the socket first keeps `BEV_OPT_CLOSE_ON_FREE` from
+        // closing it on drop.
+        unsafe { libevent_sys::bufferevent_setfd(self.inner.as_ptr(), -1) };
+
+        fd
+    }
+
+    /// Returns the error behind a `BEV_EVENT_ERROR`, for use from within the
+    /// bufferevent's event callback, e.g. `ECONNREFUSED` for a failed
+    /// connect.
+    ///
+    /// A failed DNS lookup is reported via `bufferevent_socket_get_dns_error`
+    /// first. Otherwise, this is the socket's pending `SO_ERROR`, or failing
+    /// that `errno`, which libevent leaves set when invoking the callback and
+    /// which is only meaningful right then.
+    pub fn socket_error(&self) -> io::Error {
+        // Grab `errno` first, before anything else can clobber it.
+        let errno = io::Error::last_os_error();
+
+        let dns_error =
+            unsafe { libevent_sys::bufferevent_socket_get_dns_error(self.inner.as_ptr()) };
+        if dns_error != 0 {
+            let message =
+                unsafe { std::ffi::CStr::from_ptr(libevent_sys::evutil_gai_strerror(dns_error)) };
+            return io::Error::new(
+                io::ErrorKind::Other,
+                format!("DNS lookup failed: {}", message.to_string_lossy()),
+            );
+        }
+
+        let mut so_error: c_int = 0;
+        let mut len = std::mem::size_of::<c_int>() as libc::socklen_t;
+        let ret = unsafe {
+            libc::getsockopt(
+                self.fd(),
+                libc::SOL_SOCKET,
+                libc::SO_ERROR,
+                &mut so_error as *mut c_int as *mut c_void,
+                &mut len,
+            )
+        };
+        if ret == 0 && so_error != 0 {
+            return io::Error::from_raw_os_error(so_error);
+        }
+
+        errno
+    }
+
+    /// Wrapper for libevent's `bufferevent_enable`, which starts reading
+    /// and/or writing, as given by `READ` and `WRITE` in `flags`.
+    pub fn enable(&self, flags: EventFlags) -> io::Result<()> {
+        let ret = unsafe {
+            libevent_sys::bufferevent_enable(self.inner.as_ptr(), flags.bits() as c_short)
+        };
+
+        if ret == 0 {
+            Ok(())
+        } else {
+            Err(io::Error::new(
+                io::ErrorKind::Other,
+                "Failed to enable bufferevent",
+            ))
+        }
+    }
+
+    /// Wrapper for libevent's `bufferevent_disable`, which stops reading
+    /// and/or writing, as given by `READ` and `WRITE` in `flags`.
+    ///
+    /// Disabling only `READ` keeps pending output flowing while leaving
+    /// incoming data in the kernel, which applies backpressure to the peer.
+    pub fn disable(&self, flags: EventFlags) -> io::Result<()> {
+        let ret = unsafe {
+            libevent_sys::bufferevent_disable(self.inner.as_ptr(), flags.bits() as c_short)
+        };
+
+        if ret == 0 {
+            Ok(())
+        } else {
+            Err(io::Error::new(
+                io::ErrorKind::Other,
+                "Failed to disable bufferevent",
+            ))
+        }
+    }
+
+    /// Wrapper for libevent's `bufferevent_flush`, which forces buffered data
+    /// in the directions given by `READ` and `WRITE` in `iotype` through,
+    /// e.g. to end a message or signal end-of-stream to filters.
+    ///
+    /// Returns, as libevent does, -1 on failure, 0 if there was nothing to
+    /// flush and 1 if data was flushed. Socket-based bufferevents have
+    /// nothing to flush, so this matters for filtering ones.
+    pub fn flush(&self, iotype: EventFlags, mode: FlushMode) -> c_int {
+        unsafe {
+            libevent_sys::bufferevent_flush(
+                self.inner.as_ptr(),
+                iotype.bits() as c_short,
+                mode.as_raw(),
+            )
+        }
+    }
+
+    /// Closes the bufferevent, and thus its socket, once all pending output
+    /// was written, rather than truncating it as dropping would. Reading is
+    /// stopped right away.
+    ///
+    /// If the peer makes no progress on the pending output for 30 seconds,
+    /// or the connection fails, the bufferevent is closed regardless. This
+    /// replaces any callbacks set on the raw bufferevent.
+    pub fn close_after_write(self) {
+        // With nothing pending, dropping `self` closes right away.
+        if self.output.is_empty() {
+            return;
+        }
+
+        let inner = self.inner.as_ptr();
+        let timeout = to_timeval(CLOSE_TIMEOUT);
+        let ctx = Box::into_raw(Box::new(self)) as *mut c_void;
+
+        unsafe {
+            libevent_sys::bufferevent_setwatermark(inner, libevent_sys::EV_WRITE as c_short, 0, 0);
+            libevent_sys::bufferevent_set_timeouts(inner, std::ptr::null(), &timeout);
+            libevent_sys::bufferevent_setcb(
+                inner,
+                None,
+                Some(handle_drained),
+                Some(handle_close_event),
+                ctx,
+            );
+            libevent_sys::bufferevent_disable(inner, libevent_sys::EV_READ as c_short);
+            if libevent_sys::bufferevent_enable(inner, libevent_sys::EV_WRITE as c_short) != 0 {
+                drop(Box::from_raw(ctx as *mut BufferEvent));
+            }
+        }
+    }
+
+    /// Returns the number of bytes read from the socket but not yet consumed,
+    /// e.g. for custom flow control or metrics.
+    pub fn input_len(&self) -> usize {
+        self.input.len()
+    }
+
+    /// Returns the number of bytes queued but not yet written to the socket.
+    pub fn output_len(&self) -> usize {
+        self.output.len()
+    }
+
+    /// Sets `cb` to be invoked with the input buffer whenever data was read,
+    /// and applies the `ReadAction` it returns, for flow control without
+    /// juggling `enable` and `disable` by hand. This replaces any callbacks
+    /// set on the raw bufferevent.
+    ///
+    /// Reading must be enabled via `enable(EventFlags::READ)` for `cb` to
+    /// run; after a `Pause`, that also resumes it.
+    pub fn set_read_callback(&mut self, cb: impl FnMut(&mut EvBuffer) -> ReadAction + 'static) {
+        let read_cb = self.read_cb.insert(Box::new(Box::new(cb)));
+        let ctx = &mut **read_cb as *mut ReadCallback as *mut c_void;
+
+        unsafe {
+            libevent_sys::bufferevent_setcb(self.inner.as_ptr(), Some(handle_read), None, None, ctx)
+        };
+    }
+
+    /// Appends all of `bufs` to the output buffer, in order, as a single
+    /// operation, e.g. a frame's header and body. The buffer is grown once
+    /// for all slices and stays locked throughout, so the slices are never
+    /// interleaved with writes from other threads.
+    ///
+    /// On failure, a prefix of `bufs` may have been appended.
+    pub fn write_vectored(&self, bufs: &[&[u8]]) -> io::Result<()> {
+        let output = unsafe { self.output.as_raw() }.as_ptr();
+        let total = bufs.iter().map(|buf| buf.len()).sum();
+
+        unsafe {
+            libevent_sys::evbuffer_lock(output);
+            let mut ret = libevent_sys::evbuffer_expand(output, total);
+            for buf in bufs {
+                if ret != 0 {
+                    break;
+                }
+                ret = libevent_sys::evbuffer_add(output, buf.as_ptr() as *const c_void, buf.len());
+            }
+            libevent_sys::evbuffer_unlock(output);
+
+            if ret == 0 {
+                Ok(())
+            } else {
+                Err(io::Error::new(
+                    io::ErrorKind::Other,
+                    "Failed to add to evbuffer",
+                ))
+            }
+        }
+    }
+
+    /// Returns the buffer of data read from the socket.
+    pub fn input(&mut self) -> &mut EvBuffer {
+        &mut self.input
+    }
+
+    /// Returns the buffer of data to be written to the socket.
+    pub fn output(&mut self) -> &mut EvBuffer {
+        &mut self.output
+    }
+}
+
+/// Borrows one of a bufferevent's buffers, which is owned by the bufferevent.
+///
+/// # Safety
+///
+/// `buf` must be a valid, non-null `evbuffer` pointer.
+pub(crate) unsafe fn buffer(buf: *mut libevent_sys::evbuffer) -> EvBuffer {
+    EvBuffer::from_raw(NonNull::new_unchecked(buf), false)
+}
+
+/// Read callback of `BufferEvent::set_read_callback`, which invokes the
+/// closure and applies the action it returns.
+unsafe extern "C" fn handle_read(bev: *mut libevent_sys::bufferevent, ctx: *mut c_void) {
+    let cb = &mut *(ctx as *mut ReadCallback);
+    let mut input = buffer(libevent_sys::bufferevent_get_input(bev));
+
+    // A panicking callback leaves reading enabled, for after the loop.
+    let mut action = ReadAction::Continue;
+    unwind::catch(libevent_sys::bufferevent_get_base(bev), || {
+        action = cb(&mut input)
+    });
+
+    match action {
+        ReadAction::Continue => {}
+        ReadAction::Pause => {
+            libevent_sys::bufferevent_disable(bev, libevent_sys::EV_READ as c_short);
+        }
+        ReadAction::Close => {
+            let both = (libevent_sys::EV_READ | libevent_sys::EV_WRITE) as c_short;
+            libevent_sys::bufferevent_disable(bev, both);
+            libc::shutdown(libevent_sys::bufferevent_getfd(bev), libc::SHUT_RDWR);
+        }
+    }
+}
+
+/// Write callback of `BufferEvent::close_after_write`, which frees the
+/// bufferevent once the output buffer was drained.
+unsafe extern "C" fn handle_drained(_bev: *mut libevent_sys::bufferevent, ctx: *mut c_void) {
+    let bev = ctx as *mut BufferEvent;
+    if (*bev).output.is_empty() {
+        drop(Box::from_raw(bev));
+    }
+}
+
+/// Event callback of `BufferEvent::close_after_write`, which frees the
+/// bufferevent on errors or the write timeout.
+unsafe extern "C" fn handle_close_event(
+    _bev: *mut libevent_sys::bufferevent,
+    _what: c_short,
+    ctx: *mut c_void,
+) {
+    drop(Box::from_raw(ctx as *mut BufferEvent));
+}
+
+impl Drop for BufferEvent {
+    fn drop(&mut self) {
+        // The buffers are not owned, so they are left alone after this.
+        unsafe { libevent_sys::bufferevent_free(self.inner.as_ptr()) };
+    }
+}
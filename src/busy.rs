@@ -0,0 +1,55 @@
+use std::cell::Cell;
+use std::time::{Duration, Instant};
+
+use crate::dispatch::lock;
+use crate::Base;
+
+/// The time a base spent in callbacks since the start of its current sample.
+#[derive(Debug)]
+pub(crate) struct BusyTracker {
+    busy: Cell<Duration>,
+    since: Cell<Instant>,
+}
+
+impl Base {
+    /// Starts tracking the time spent in callbacks, for `busy_fraction`. Only
+    /// callbacks of events spawned via `Base::spawn*` are counted.
+    pub fn enable_busy_tracking(&mut self) {
+        lock(&self.dispatch.busy).get_or_insert_with(|| BusyTracker {
+            busy: Cell::new(Duration::ZERO),
+            since: Cell::new(Instant::now()),
+        });
+        self.dispatch.update_instrumented();
+    }
+
+    /// Returns the fraction of wall-clock time spent in callbacks since the
+    /// previous call, or since `enable_busy_tracking` for the first one, as
+    /// a lightweight load signal between 0 and 1. Returns 0 if tracking was
+    /// never enabled.
+    ///
+    /// Every call starts a new sample, so polling this periodically yields
+    /// the load of each period.
+    pub fn busy_fraction(&self) -> f64 {
+        let busy = lock(&self.dispatch.busy);
+        let tracker = match busy.as_ref() {
+            Some(tracker) => tracker,
+            None => return 0.0,
+        };
+
+        let now = Instant::now();
+        let elapsed = now.duration_since(tracker.since.replace(now));
+        let busy = tracker.busy.replace(Duration::ZERO);
+
+        if elapsed.is_zero() {
+            return 0.0;
+        }
+        (busy.as_secs_f64() / elapsed.as_secs_f64()).min(1.0)
+    }
+}
+
+impl BusyTracker {
+    /// Adds `took`, the time a callback took, to the current sample.
+    pub(crate) fn record(&self, took: Duration) {
+        self.busy.set(self.busy.get() + took);
+    }
+}
@@ -0,0 +1,72 @@
+use std::io::Write;
+use std::os::unix::net::UnixStream;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// A cloneable, thread-safe token which can be used to stop a running event
+/// loop from any thread, via `Base::run_until_cancelled`.
+///
+/// Once cancelled, a token stays cancelled.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken {
+    inner: Arc<TokenInner>,
+}
+
+#[derive(Debug, Default)]
+struct TokenInner {
+    cancelled: AtomicBool,
+    /// Write ends of the self-pipes of the loops currently waiting on the
+    /// token.
+    wakers: Mutex<Vec<Arc<UnixStream>>>,
+}
+
+impl CancellationToken {
+    /// Creates a new, uncancelled token.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Cancels the token, waking up any loops waiting on it.
+    pub fn cancel(&self) {
+        self.inner.cancelled.store(true, Ordering::SeqCst);
+
+        for waker in self.inner.wakers.lock().unwrap().iter() {
+            // A full pipe already has a wakeup pending, so errors are moot.
+            let _ = (&**waker).write(&[1]);
+        }
+    }
+
+    /// Returns whether the token has been cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Registers the write end of a self-pipe to be written to on cancel,
+    /// for as long as the returned `Registration` lives.
+    pub(crate) fn register(&self, waker: UnixStream) -> Registration {
+        let waker = Arc::new(waker);
+        self.inner.wakers.lock().unwrap().push(waker.clone());
+
+        Registration {
+            token: self.clone(),
+            waker,
+        }
+    }
+}
+
+/// Keeps a self-pipe registered with a `CancellationToken`.
+pub(crate) struct Registration {
+    token: CancellationToken,
+    waker: Arc<UnixStream>,
+}
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        self.token
+            .inner
+            .wakers
+            .lock()
+            .unwrap()
+            .retain(|waker| !Arc::ptr_eq(waker, &self.waker));
+    }
+}
@@ -0,0 +1,50 @@
+use std::io;
+use std::os::unix::io::RawFd;
+use std::sync::mpsc::{self, Receiver, TrySendError};
+
+use crate::event::Local;
+use crate::{Base, Event, EventFlags, Fd};
+
+/// What `Base::event_channel` does with an activation while the channel is
+/// full, i.e. while the receiver lags behind.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Backpressure {
+    /// Drops the activation, keeping the loop responsive.
+    Drop,
+    /// Blocks the whole loop until the receiver makes room.
+    Block,
+}
+
+impl Base {
+    /// Registers a persistent event for `fd` and `flags`, and pushes the flags
+    /// of each activation into a channel of the given `capacity`, so that
+    /// activations can be consumed from another thread.
+    ///
+    /// Once the receiver is dropped, the event is stopped. Dropping the
+    /// returned handle removes the event, which ends the channel.
+    pub fn event_channel(
+        &mut self,
+        fd: RawFd,
+        flags: EventFlags,
+        capacity: usize,
+        policy: Backpressure,
+    ) -> io::Result<(Event<Local<Fd>>, Receiver<EventFlags>)> {
+        let (tx, rx) = mpsc::sync_channel(capacity);
+
+        let ev = Event::new(fd, flags | EventFlags::PERSIST, None);
+        let handle = self.spawn_local(ev, move |ev, _fd, flags| {
+            let disconnected = match policy {
+                Backpressure::Drop => {
+                    matches!(tx.try_send(flags), Err(TrySendError::Disconnected(_)))
+                }
+                Backpressure::Block => tx.send(flags).is_err(),
+            };
+
+            if disconnected {
+                let _ = ev.stop();
+            }
+        })?;
+
+        Ok((handle, rx))
+    }
+}
@@ -0,0 +1,167 @@
+use std::ptr::NonNull;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Weak;
+use std::time::Duration;
+
+use crate::dispatch::lock;
+use crate::event::Inactive;
+use crate::{Base, Event, EventFlags, LoopFlags};
+
+/// The manually driven time of a `Base` with `enable_virtual_clock`, along
+/// with the timers scheduled on it.
+#[derive(Default)]
+pub(crate) struct VirtualClock {
+    /// The virtual time elapsed since the clock was enabled.
+    now: Duration,
+    timers: Vec<VirtualTimer>,
+}
+
+/// A timer event which is activated by `Base::advance_time` rather than by
+/// libevent's timer dispatch.
+struct VirtualTimer {
+    event: NonNull<libevent_sys::event>,
+    /// The stop flag of the event's handles, which is gone once the event
+    /// was freed.
+    stopped: Weak<AtomicBool>,
+    deadline: Duration,
+    /// The interval of persistent timers, which are re-armed after firing.
+    period: Option<Duration>,
+}
+
+impl VirtualTimer {
+    fn is_live(&self) -> bool {
+        match self.stopped.upgrade() {
+            Some(stopped) => !stopped.load(Ordering::Relaxed),
+            None => false,
+        }
+    }
+}
+
+impl VirtualClock {
+    /// Forgets the timers of `events`, which `Base::clear_all_events`
+    /// removed.
+    pub(crate) fn remove(&mut self, events: &[NonNull<libevent_sys::event>]) {
+        self.timers.retain(|timer| !events.contains(&timer.event));
+    }
+}
+
+impl Base {
+    /// Switches timers spawned via `Base::spawn*` from now on to a virtual
+    /// clock, which only moves forward via `advance_time`, for deterministic
+    /// tests of timer-heavy code. The virtual clock starts at zero.
+    ///
+    /// Only pure timers, i.e. `Interval` and `Oneshot` events, are affected;
+    /// timeouts of fd events keep using the wall clock. Timers spawned before
+    /// this call are left alone too, as are zero timeouts, which are due
+    /// right away on either clock, e.g. those of `defer`, and the timers of
+    /// the crate's helpers, e.g. of `add_virtual_event`.
+    pub fn enable_virtual_clock(&mut self) {
+        if self.virtual_clock.is_none() {
+            self.virtual_clock = Some(VirtualClock::default());
+        }
+    }
+
+    /// Moves the virtual clock forward by `by`, dispatching the callbacks of
+    /// all timers whose deadline passes, in deadline order. Does nothing
+    /// unless `enable_virtual_clock` was called.
+    ///
+    /// Each timer is dispatched via a non-blocking pass of libevent's loop,
+    /// which also runs any other events that are ready by then. These passes
+    /// are not calls into the loop via `loop_`: they neither count toward
+    /// `iteration_count` nor run the prepare or exit hooks, and the cap set
+    /// via `set_max_events_per_turn` applies to the whole call. If a callback
+    /// panics, the clock stops short of `by`, and the panic is left for
+    /// `take_panic`.
+    pub fn advance_time(&mut self, by: Duration) {
+        let target = match &self.virtual_clock {
+            Some(clock) => clock.now.saturating_add(by),
+            None => return,
+        };
+        if let Some(limit) = lock(&self.dispatch.turn_limit).as_ref() {
+            limit.reset();
+        }
+
+        loop {
+            let clock = self.virtual_clock.as_mut().unwrap();
+            clock.timers.retain(VirtualTimer::is_live);
+
+            let next = clock
+                .timers
+                .iter()
+                .enumerate()
+                .filter(|(_, timer)| timer.deadline <= target)
+                .min_by_key(|(_, timer)| timer.deadline)
+                .map(|(i, _)| i);
+
+            let i = match next {
+                Some(i) => i,
+                None => {
+                    clock.now = target;
+                    return;
+                }
+            };
+
+            let timer = &mut clock.timers[i];
+            clock.now = timer.deadline;
+            let event = timer.event;
+
+            // A deadline beyond what a `Duration` holds never comes.
+            let period = timer
+                .period
+                .map(|period| period.max(Duration::from_nanos(1)));
+            match period.and_then(|period| timer.deadline.checked_add(period)) {
+                Some(deadline) => timer.deadline = deadline,
+                None => drop(clock.timers.swap_remove(i)),
+            }
+
+            unsafe {
+                let base = self.as_raw().as_ptr();
+                libevent_sys::event_active(event.as_ptr(), libevent_sys::EV_TIMEOUT as _, 0);
+                libevent_sys::event_base_loop(base, LoopFlags::NONBLOCK.bits() as _);
+            }
+            if self.claim_panic() {
+                return;
+            }
+        }
+    }
+
+    /// Schedules the freshly assigned `raw_ev` on the virtual clock instead
+    /// of adding it to libevent, if the clock is enabled and `ev` is a pure
+    /// timer. Returns whether it did.
+    pub(crate) fn schedule_virtual<T>(
+        &mut self,
+        ev: &Event<Inactive<T>>,
+        raw_ev: NonNull<libevent_sys::event>,
+        stopped: Weak<AtomicBool>,
+        timeout: Option<Duration>,
+    ) -> bool {
+        let clock = match self.virtual_clock.as_mut() {
+            Some(clock) => clock,
+            None => return false,
+        };
+        let timeout = match timeout {
+            Some(timeout) if ev.inactive_fd().is_none() && !timeout.is_zero() => timeout,
+            _ => return false,
+        };
+        let helper = self
+            .dispatch
+            .registration(raw_ev)
+            .map_or(false, |registration| registration.internal);
+        if helper {
+            return false;
+        }
+
+        let persistent = ev.inactive_flags().contains(EventFlags::PERSIST);
+        // A deadline beyond what a `Duration` holds never comes.
+        if let Some(deadline) = clock.now.checked_add(timeout) {
+            clock.timers.push(VirtualTimer {
+                event: raw_ev,
+                stopped,
+                deadline,
+                period: if persistent { Some(timeout) } else { None },
+            });
+        }
+
+        true
+    }
+}
@@ -0,0 +1,123 @@
+use std::io;
+use std::os::raw::c_int;
+use std::ptr::NonNull;
+
+use crate::dispatch::lock;
+use crate::Base;
+
+/// Settings for a new `Base`, after libevent's `event_config`, for
+/// `Base::with_config`.
+#[derive(Clone, Debug, Default)]
+pub struct EventConfig {
+    precise_timers: bool,
+    expected_fds: usize,
+}
+
+impl EventConfig {
+    /// Creates the settings of a plain `Base::new`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets `EVENT_BASE_FLAG_PRECISE_TIMER`, as `Base::new_precise_timers`
+    /// does.
+    pub fn precise_timers(mut self, enabled: bool) -> Self {
+        self.precise_timers = enabled;
+        self
+    }
+
+    /// Sizes the base's internal structures for about `n` fds up front, to
+    /// spare a server expecting many connections the churn of growing them
+    /// one registration at a time.
+    ///
+    /// This is a heuristic: libevent has no capacity hint, so the new base
+    /// registers and immediately deletes a dummy event on an fd numbered
+    /// `n - 1` or above, which grows libevent's fd map to fit every fd below
+    /// it. Whether the backend pre-sizes anything as well depends on the
+    /// backend. Nothing is warmed if no such fd can be opened, e.g. since `n`
+    /// exceeds `RLIMIT_NOFILE`.
+    pub fn expected_fds(mut self, n: usize) -> Self {
+        self.expected_fds = n;
+        self
+    }
+}
+
+impl Base {
+    /// Creates a new instance of `Base` with the given settings.
+    pub fn with_config(config: EventConfig) -> Result<Self, io::Error> {
+        let raw = unsafe { libevent_sys::event_config_new() };
+        if raw.is_null() {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                "Failed to create libevent config",
+            ));
+        }
+
+        let base = unsafe {
+            if config.precise_timers {
+                libevent_sys::event_config_set_flag(
+                    raw,
+                    libevent_sys::event_base_config_flag_EVENT_BASE_FLAG_PRECISE_TIMER as _,
+                );
+            }
+            let base = libevent_sys::event_base_new_with_config(raw);
+            libevent_sys::event_config_free(raw);
+            base
+        };
+
+        let base = match NonNull::new(base) {
+            Some(base) => base,
+            None => {
+                return Err(io::Error::new(
+                    io::ErrorKind::Other,
+                    "Failed to create libevent base",
+                ))
+            }
+        };
+
+        let mut base = unsafe { Self::from_raw(base) };
+        base.precise_timers = config.precise_timers;
+        if config.expected_fds > 0 {
+            base.warm_up(config.expected_fds);
+        }
+        Ok(base)
+    }
+
+    /// Grows the structures which track fds to fit `expected_fds` of them,
+    /// for `EventConfig::expected_fds`.
+    fn warm_up(&self, expected_fds: usize) {
+        let mut fds: [c_int; 2] = [-1; 2];
+        if unsafe { libc::pipe(fds.as_mut_ptr()) } != 0 {
+            return;
+        }
+
+        // The lowest free fd numbered `expected_fds - 1` or above.
+        let min = c_int::try_from(expected_fds - 1).unwrap_or(c_int::MAX);
+        let high = unsafe { libc::fcntl(fds[0], libc::F_DUPFD_CLOEXEC, min) };
+        if high >= 0 {
+            // Within the fd limit, so the reservation is bounded, too.
+            lock(&self.dispatch.events).reserve(expected_fds);
+
+            unsafe {
+                let ev = libevent_sys::event_new(
+                    self.as_raw().as_ptr(),
+                    high,
+                    libevent_sys::EV_READ as _,
+                    None,
+                    std::ptr::null_mut(),
+                );
+                if !ev.is_null() {
+                    libevent_sys::event_add(ev, std::ptr::null());
+                    libevent_sys::event_del(ev);
+                    libevent_sys::event_free(ev);
+                }
+                libc::close(high);
+            }
+        }
+
+        unsafe {
+            libc::close(fds[0]);
+            libc::close(fds[1]);
+        }
+    }
+}
@@ -0,0 +1,88 @@
+use std::io;
+use std::net::SocketAddr;
+use std::os::raw::{c_int, c_void};
+use std::os::unix::io::RawFd;
+use std::time::Duration;
+
+use crate::util::raw_socket_addr;
+use crate::{Base, ConnectError, Event, EventFlags};
+
+impl Base {
+    /// Starts a non-blocking connect of the socket `fd` to `addr`, and
+    /// reports via `cb` whether it succeeded, failed, or did not finish
+    /// within `timeout`. The socket is switched to non-blocking mode.
+    ///
+    /// `cb` is always invoked from within the loop, even if the outcome is
+    /// known right away. The socket is left open either way.
+    pub fn connect_with_timeout<F>(
+        &mut self,
+        fd: RawFd,
+        addr: SocketAddr,
+        timeout: Duration,
+        cb: F,
+    ) -> io::Result<()>
+    where
+        F: FnOnce(Result<(), ConnectError>) + 'static,
+    {
+        if unsafe { libevent_sys::evutil_make_socket_nonblocking(fd) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let (sockaddr, socklen) = raw_socket_addr(&addr);
+        let ret = unsafe {
+            libc::connect(
+                fd,
+                &sockaddr as *const libc::sockaddr_storage as *const libc::sockaddr,
+                socklen,
+            )
+        };
+
+        if ret == 0 {
+            return self.defer(move || cb(Ok(())));
+        }
+        let error = io::Error::last_os_error();
+        if error.raw_os_error() != Some(libc::EINPROGRESS) {
+            return self.defer(move || cb(Err(ConnectError::Io(error))));
+        }
+
+        // The socket becomes writable once the connect finishes either way.
+        let mut cb = Some(cb);
+        let ev = Event::new(fd, EventFlags::WRITE, Some(timeout));
+        self.spawn(ev, move |ev, fd, flags| {
+            let result = if flags.contains(EventFlags::TIMEOUT) {
+                Err(ConnectError::Timeout)
+            } else {
+                socket_error(fd).map_err(ConnectError::Io)
+            };
+
+            if let Some(cb) = cb.take() {
+                cb(result);
+            }
+            let _ = ev.stop();
+        })
+    }
+}
+
+/// Returns the pending error of a socket, via `SO_ERROR`.
+fn socket_error(fd: RawFd) -> io::Result<()> {
+    let mut error: c_int = 0;
+    let mut len = std::mem::size_of::<c_int>() as libc::socklen_t;
+
+    let ret = unsafe {
+        libc::getsockopt(
+            fd,
+            libc::SOL_SOCKET,
+            libc::SO_ERROR,
+            &mut error as *mut c_int as *mut c_void,
+            &mut len,
+        )
+    };
+
+    if ret != 0 {
+        Err(io::Error::last_os_error())
+    } else if error != 0 {
+        Err(io::Error::from_raw_os_error(error))
+    } else {
+        Ok(())
+    }
+}
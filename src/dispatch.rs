@@ -0,0 +1,279 @@
+use std::any::Any;
+use std::collections::HashMap;
+use std::os::raw::c_short;
+use std::os::unix::io::RawFd;
+use std::ptr::NonNull;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant};
+
+use crate::busy::BusyTracker;
+use crate::turn_limit::TurnLimit;
+use crate::watchdog::Watchdog;
+use crate::{Activation, EventFlags, LatencyHistogram};
+
+/// How an event created via `Base::spawn*`, or added via `Base::event_add`,
+/// is tracked by its base.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct Registration {
+    /// The timeout the event was last added with, which `resume_io` adds it
+    /// with again.
+    pub(crate) timeout: Option<Duration>,
+    /// The callback of a raw event added via `Base::event_add`, which may be
+    /// freed behind the base's back, to tell it apart from a later event at
+    /// the same address. `None` for events created via `Base::spawn*`.
+    pub(crate) raw_callback: Option<usize>,
+    /// Whether the callback wrapper owns the event's only handle, as for
+    /// `Base::spawn`, so that reclaiming the wrapper frees the event.
+    pub(crate) owning: bool,
+    /// Whether the event belongs to one of the crate's helpers, e.g. the
+    /// dispatch event of a signal, rather than to the user.
+    pub(crate) internal: bool,
+}
+
+/// The per-base state needed on the dispatch path of events spawned via
+/// `Base::spawn*`, e.g. the log of a `RecordingBase`.
+///
+/// It is shared between the `Base` and the callback wrappers of its events,
+/// which reach it directly rather than looking it up by base, and lives on
+/// for as long as any of them do. Since the `Base` may be sent to another
+/// thread while handles to its events stay behind, it is synchronized.
+#[derive(Debug, Default)]
+pub(crate) struct Dispatch {
+    /// The number of callbacks dispatched so far, which `Base::drain_ready`
+    /// uses to count per run.
+    pub(crate) dispatched: AtomicUsize,
+    /// The activations logged so far, while recording.
+    pub(crate) log: Mutex<Option<Vec<Activation>>>,
+    /// The watchdog monitoring callbacks, if one was set.
+    pub(crate) watchdog: Mutex<Option<Arc<Watchdog>>>,
+    /// The delays of callbacks, while latency stats are enabled.
+    pub(crate) latency: Mutex<Option<LatencyHistogram>>,
+    /// The time spent in callbacks, while busy tracking is enabled.
+    pub(crate) busy: Mutex<Option<BusyTracker>>,
+    /// The cap on callbacks per turn, if one was set.
+    pub(crate) turn_limit: Mutex<Option<TurnLimit>>,
+    /// The payload of a panic in a callback, until taken via
+    /// `Base::take_panic`.
+    pub(crate) panic: Mutex<Option<Box<dyn Any + Send>>>,
+    /// Whether any of the optional instrumentation above is enabled, as a
+    /// single cheap check on the dispatch path.
+    pub(crate) instrumented: AtomicBool,
+    /// The number of callbacks currently running, nested ones included.
+    pub(crate) running: AtomicUsize,
+    /// The events created via `Base::spawn*` which are not yet freed, and
+    /// the raw ones added via `Base::event_add`, by address.
+    pub(crate) events: Mutex<HashMap<usize, Registration>>,
+    /// The events removed by `Base::suspend_io`, by address.
+    pub(crate) suspended: Mutex<Vec<usize>>,
+}
+
+// `Base` is `Send`, so the state it shares with its events must be, too.
+const _: fn() = || {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<Dispatch>();
+};
+
+impl Dispatch {
+    /// Counts a dispatched callback.
+    #[inline]
+    pub(crate) fn count(&self) {
+        self.dispatched.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Logs an activation of `event`, if the base is recording.
+    #[inline]
+    pub(crate) fn record(&self, event: NonNull<libevent_sys::event>, fd: RawFd, flags: EventFlags) {
+        if let Some(log) = lock(&self.log).as_mut() {
+            log.push(Activation {
+                event,
+                fd,
+                flags,
+                time: Instant::now(),
+            });
+        }
+    }
+
+    /// Returns whether any instrumentation is enabled, i.e. the activation
+    /// log, the watchdog, latency stats, busy tracking or the turn limit.
+    #[inline]
+    pub(crate) fn instrumented(&self) -> bool {
+        self.instrumented.load(Ordering::Relaxed)
+    }
+
+    /// Refreshes `instrumented` after any of the instrumentation changed. It
+    /// locks each of them, so none may be locked by the caller.
+    pub(crate) fn update_instrumented(&self) {
+        let instrumented = lock(&self.log).is_some()
+            || lock(&self.watchdog).is_some()
+            || lock(&self.latency).is_some()
+            || lock(&self.busy).is_some()
+            || lock(&self.turn_limit).is_some();
+        self.instrumented.store(instrumented, Ordering::Relaxed);
+    }
+
+    /// Tracks `event`, which was just created via `Base::spawn*`.
+    pub(crate) fn register(&self, event: NonNull<libevent_sys::event>, registration: Registration) {
+        let key = event.as_ptr() as usize;
+        lock(&self.events).insert(key, registration);
+    }
+
+    /// Tracks `event`, which was just added with `timeout`. Events which are
+    /// not tracked yet are raw ones.
+    pub(crate) fn added(&self, event: NonNull<libevent_sys::event>, timeout: Option<Duration>) {
+        let key = event.as_ptr() as usize;
+        let callback = unsafe { libevent_sys::event_get_callback(event.as_ptr()) };
+        let callback = callback.map_or(0, |callback| callback as usize);
+
+        let mut events = lock(&self.events);
+        let registration = events.entry(key).or_insert(Registration {
+            raw_callback: Some(callback),
+            ..Default::default()
+        });
+        if registration.raw_callback.is_some() {
+            registration.raw_callback = Some(callback);
+        }
+        registration.timeout = timeout;
+    }
+
+    /// Forgets `event`, which is about to be freed, so that `resume_io` does
+    /// not add it again.
+    pub(crate) fn unregister(&self, event: NonNull<libevent_sys::event>) {
+        let key = event.as_ptr() as usize;
+        lock(&self.events).remove(&key);
+        lock(&self.suspended).retain(|suspended| *suspended != key);
+    }
+
+    /// Flags the tracked `event` as one of the crate's helpers.
+    pub(crate) fn set_internal(&self, event: NonNull<libevent_sys::event>) {
+        let key = event.as_ptr() as usize;
+        if let Some(registration) = lock(&self.events).get_mut(&key) {
+            registration.internal = true;
+        }
+    }
+
+    /// Returns how `event` is tracked, or `None` if it is not (anymore).
+    ///
+    /// A raw `event` must still be allocated, since its callback is compared
+    /// to the one it was tracked with.
+    pub(crate) fn registration(&self, event: NonNull<libevent_sys::event>) -> Option<Registration> {
+        let key = event.as_ptr() as usize;
+        let registration = lock(&self.events).get(&key).copied()?;
+
+        match registration.raw_callback {
+            Some(callback) => {
+                let current = unsafe { libevent_sys::event_get_callback(event.as_ptr()) };
+                let current = current.map_or(0, |current| current as usize);
+                (current == callback).then(|| registration)
+            }
+            None => Some(registration),
+        }
+    }
+
+    /// Forgets the raw events which are neither in `live` nor suspended, and
+    /// thus may have been freed since.
+    pub(crate) fn prune_raw(&self, live: &[usize]) {
+        let suspended = lock(&self.suspended);
+        lock(&self.events).retain(|key, registration| {
+            registration.raw_callback.is_none() || live.contains(key) || suspended.contains(key)
+        });
+    }
+
+    /// Returns the events created via `Base::spawn*` which belong to the
+    /// user.
+    pub(crate) fn user_events(&self) -> Vec<NonNull<libevent_sys::event>> {
+        lock(&self.events)
+            .iter()
+            .filter(|(_, registration)| {
+                !registration.internal && registration.raw_callback.is_none()
+            })
+            .filter_map(|(key, _)| NonNull::new(*key as *mut libevent_sys::event))
+            .collect()
+    }
+
+    /// Returns the events created via `Base::spawn*` for the crate's helpers
+    /// which are added to or active on the base, to leave them out like
+    /// libevent leaves out its internal events.
+    pub(crate) fn pending_helpers(&self) -> Vec<NonNull<libevent_sys::event>> {
+        let all_pending = (libevent_sys::EV_TIMEOUT
+            | libevent_sys::EV_READ
+            | libevent_sys::EV_WRITE
+            | libevent_sys::EV_SIGNAL) as c_short;
+
+        lock(&self.events)
+            .iter()
+            .filter(|(_, registration)| {
+                registration.internal && registration.raw_callback.is_none()
+            })
+            .filter_map(|(key, _)| NonNull::new(*key as *mut libevent_sys::event))
+            .filter(|event| unsafe {
+                libevent_sys::event_pending(event.as_ptr(), all_pending, std::ptr::null_mut()) != 0
+            })
+            .collect()
+    }
+
+    /// Records the delay of the callback of `event`, if latency stats are
+    /// enabled.
+    #[inline]
+    pub(crate) fn record_latency(&self, event: NonNull<libevent_sys::event>) {
+        if let Some(histogram) = lock(&self.latency).as_mut() {
+            histogram.record_callback(event);
+        }
+    }
+
+    /// Returns whether busy tracking is enabled, as a cheap check before
+    /// timing a callback for `record_busy`.
+    #[inline]
+    pub(crate) fn tracks_busy(&self) -> bool {
+        lock(&self.busy).is_some()
+    }
+
+    /// Adds `took`, the time a callback took, to the busy time, if busy
+    /// tracking is enabled.
+    #[inline]
+    pub(crate) fn record_busy(&self, took: Duration) {
+        if let Some(tracker) = lock(&self.busy).as_ref() {
+            tracker.record(took);
+        }
+    }
+
+    /// Counts the callback of `event` toward the cap on callbacks per turn,
+    /// if any.
+    #[inline]
+    pub(crate) fn record_turn(&self, event: NonNull<libevent_sys::event>) {
+        if let Some(limit) = lock(&self.turn_limit).as_ref() {
+            limit.record(event);
+        }
+    }
+
+    /// Marks the start of a callback, also for the watchdog, if any and
+    /// `instrumented`.
+    #[inline]
+    pub(crate) fn callback_started(&self, instrumented: bool) {
+        self.running.fetch_add(1, Ordering::Relaxed);
+        if !instrumented {
+            return;
+        }
+        if let Some(watchdog) = lock(&self.watchdog).as_ref() {
+            watchdog.callback_started();
+        }
+    }
+
+    /// Marks the end of a callback, also for the watchdog, if any and
+    /// `instrumented`.
+    #[inline]
+    pub(crate) fn callback_finished(&self, instrumented: bool) {
+        if instrumented {
+            if let Some(watchdog) = lock(&self.watchdog).as_ref() {
+                watchdog.callback_finished();
+            }
+        }
+        self.running.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Locks `mutex`, ignoring poisoning, since a panic in a user closure is
+/// caught before it could leave the guarded state half-updated.
+pub(crate) fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
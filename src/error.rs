@@ -0,0 +1,104 @@
+use std::fmt;
+use std::io;
+use std::os::raw::c_int;
+
+/// Errors reported by the libevent API, which mostly signals failure through
+/// a bare non-zero return code. The code is preserved for debugging.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LibeventError {
+    /// `event_base_loopexit` failed.
+    LoopExit(c_int),
+    /// `event_base_loopbreak` failed.
+    LoopBreak(c_int),
+    /// `event_base_loopcontinue` failed.
+    LoopContinue(c_int),
+    /// `event_base_priority_init` failed, e.g. due to an out-of-range count
+    /// or events already being active.
+    PriorityInit(c_int),
+    /// `event_assign` failed, e.g. due to conflicting flags.
+    EventAssign(c_int),
+    /// `event_add` failed, e.g. due to an invalid fd.
+    EventAdd(c_int),
+}
+
+impl LibeventError {
+    /// Maps a libevent return code to `Ok` if zero, and to the error
+    /// constructed by `variant` otherwise.
+    pub(crate) fn check(code: c_int, variant: fn(c_int) -> Self) -> Result<(), Self> {
+        if code == 0 {
+            Ok(())
+        } else {
+            Err(variant(code))
+        }
+    }
+
+    /// Returns the raw return code given by libevent.
+    pub fn code(&self) -> c_int {
+        match *self {
+            LibeventError::LoopExit(code)
+            | LibeventError::LoopBreak(code)
+            | LibeventError::LoopContinue(code)
+            | LibeventError::PriorityInit(code)
+            | LibeventError::EventAssign(code)
+            | LibeventError::EventAdd(code) => code,
+        }
+    }
+}
+
+impl fmt::Display for LibeventError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let what = match self {
+            LibeventError::LoopExit(_) => "Failed to schedule loop exit",
+            LibeventError::LoopBreak(_) => "Failed to break loop",
+            LibeventError::LoopContinue(_) => "Failed to continue loop",
+            LibeventError::PriorityInit(_) => "Failed to initialize priorities",
+            LibeventError::EventAssign(_) => "Failed to assign event",
+            LibeventError::EventAdd(_) => "Failed to add event",
+        };
+        write!(f, "{} (code {})", what, self.code())
+    }
+}
+
+impl std::error::Error for LibeventError {}
+
+impl From<LibeventError> for io::Error {
+    fn from(e: LibeventError) -> Self {
+        io::Error::new(io::ErrorKind::Other, e)
+    }
+}
+
+/// The ways a connect started by `Base::connect_with_timeout` can fail.
+#[derive(Debug)]
+pub enum ConnectError {
+    /// The connect did not finish within the timeout.
+    Timeout,
+    /// The connect failed, e.g. with `ECONNREFUSED`.
+    Io(io::Error),
+}
+
+impl fmt::Display for ConnectError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConnectError::Timeout => write!(f, "Connect timed out"),
+            ConnectError::Io(e) => write!(f, "Failed to connect: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ConnectError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ConnectError::Timeout => None,
+            ConnectError::Io(e) => Some(e),
+        }
+    }
+}
+
+impl From<ConnectError> for io::Error {
+    fn from(e: ConnectError) -> Self {
+        match e {
+            ConnectError::Timeout => io::Error::new(io::ErrorKind::TimedOut, e),
+            ConnectError::Io(e) => e,
+        }
+    }
+}
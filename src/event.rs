@@ -1,13 +1,20 @@
-use crate::EventFlags;
+use crate::base::{
+    finalize_wrapped_callback, handle_wrapped_callback, to_timeval, EventCallbackCtx,
+    EventCallbackFn,
+};
+use crate::dispatch::{lock, Dispatch};
+use crate::{EventCallbackWrapper, EventFlags};
 use std::cell::RefCell;
+use std::hash::{Hash, Hasher};
 use std::io;
 use std::marker::PhantomData;
-use std::os::unix::io::RawFd;
+use std::os::raw::{c_int, c_short};
+use std::os::unix::io::{AsRawFd, RawFd};
 use std::ptr::NonNull;
 use std::rc::Rc;
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
-use std::time::Duration;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 /// The primitive event-type which is created with [Event::new] using a
 /// a non-negative `RawFd`.
@@ -29,9 +36,37 @@ impl Interval {
     pub fn new(interval: Duration) -> Event<Inactive<Interval>> {
         Inactive::new(None, EventFlags::PERSIST, Some(interval))
     }
+
+    /// Creates an interval timer like `new`, with `extra` flags ORed into
+    /// `PERSIST`, e.g. `FINALIZE`.
+    ///
+    /// Flags which only make sense for an fd or a signal, i.e. `READ`,
+    /// `WRITE`, `SIGNAL` and `CLOSED`, are rejected, since a timer has
+    /// neither.
+    pub fn with_flags(
+        interval: Duration,
+        extra: EventFlags,
+    ) -> io::Result<Event<Inactive<Interval>>> {
+        let needs_fd =
+            EventFlags::READ | EventFlags::WRITE | EventFlags::SIGNAL | EventFlags::CLOSED;
+        if extra.intersects(needs_fd) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Interval flags require an fd",
+            ));
+        }
+
+        Ok(Inactive::new(
+            None,
+            EventFlags::PERSIST | extra,
+            Some(interval),
+        ))
+    }
 }
 
 impl Oneshot {
+    /// Creates a oneshot timer firing after `timeout`. A zero `timeout` fires
+    /// on the next iteration of the loop.
     pub fn new(timeout: Duration) -> Event<Inactive<Oneshot>> {
         Inactive::new(None, EventFlags::empty(), Some(timeout))
     }
@@ -42,20 +77,29 @@ impl Oneshot {
 pub(crate) struct EventInner<T> {
     inner: NonNull<libevent_sys::event>,
     finalizer: libevent_sys::event_finalize_callback_fn,
+    timeout: Option<Duration>,
+    /// The dispatch state of the event's base, for callback wrappers
+    /// created later on.
+    dispatch: Arc<Dispatch>,
     _phantom: PhantomData<T>,
 }
 
 impl<T> EventInner<T> {
-    /// Creates a new `EventInner` given a raw `event`, and a "finalizer"
-    /// function which helps in `Drop` teardown.
+    /// Creates a new `EventInner` given a raw `event`, a "finalizer"
+    /// function which helps in `Drop` teardown, the timeout the event was
+    /// added with, and the dispatch state of its base.
     // TODO: unsafe?
     pub(crate) fn from_raw(
         inner: NonNull<libevent_sys::event>,
         finalizer: libevent_sys::event_finalize_callback_fn,
+        timeout: Option<Duration>,
+        dispatch: Arc<Dispatch>,
     ) -> Self {
         EventInner {
             inner,
             finalizer,
+            timeout,
+            dispatch,
             _phantom: Default::default(),
         }
     }
@@ -79,6 +123,66 @@ impl<T> EventInner<T> {
         }
     }
 
+    /// Re-assigns the event with a new callback and context, keeping its
+    /// base, fd, flags and priority. If the event was pending, it is added
+    /// again with its original timeout.
+    ///
+    /// The previous context is dropped via the previous finalizer, and
+    /// `finalizer` takes its place for the new context.
+    pub(crate) fn reassign(
+        &mut self,
+        callback: EventCallbackFn,
+        ctx: EventCallbackCtx,
+        finalizer: libevent_sys::event_finalize_callback_fn,
+    ) -> io::Result<()> {
+        let ptr = self.inner.as_ptr();
+        let all_pending = (libevent_sys::EV_TIMEOUT
+            | libevent_sys::EV_READ
+            | libevent_sys::EV_WRITE
+            | libevent_sys::EV_SIGNAL) as c_short;
+
+        let (pending, base, fd, events, priority) = unsafe {
+            (
+                libevent_sys::event_pending(ptr, all_pending, std::ptr::null_mut()) != 0,
+                libevent_sys::event_get_base(ptr),
+                libevent_sys::event_get_fd(ptr),
+                libevent_sys::event_get_events(ptr),
+                libevent_sys::event_get_priority(ptr),
+            )
+        };
+
+        // `event_assign` must never be called on a pending event.
+        self.stop()?;
+        self.drop_context();
+        self.finalizer = finalizer;
+
+        let assigned =
+            unsafe { libevent_sys::event_assign(ptr, base, fd, events, Some(callback), ctx) };
+        if assigned != 0 {
+            unsafe { (finalizer.unwrap())(ptr, ctx) };
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                "Failed to assign event",
+            ));
+        }
+
+        unsafe { libevent_sys::event_priority_set(ptr, priority) };
+
+        if pending {
+            let added = unsafe {
+                match self.timeout {
+                    Some(timeout) => libevent_sys::event_add(ptr, &to_timeval(timeout)),
+                    None => libevent_sys::event_add(ptr, std::ptr::null()),
+                }
+            };
+            if added != 0 {
+                return Err(io::Error::new(io::ErrorKind::Other, "Failed to add event"));
+            }
+        }
+
+        Ok(())
+    }
+
     fn __drop_context(
         event: NonNull<libevent_sys::event>,
         finalizer: libevent_sys::event_finalize_callback_fn,
@@ -86,6 +190,11 @@ impl<T> EventInner<T> {
         let ptr = event.as_ptr();
         let ctx = unsafe { libevent_sys::event_get_callback_arg(ptr) };
 
+        // Already dropped (e.g. by a failed `reassign`).
+        if ctx.is_null() {
+            return;
+        }
+
         unsafe { (finalizer.unwrap())(event.as_ptr(), ctx) };
     }
 
@@ -116,10 +225,17 @@ pub struct Inactive<T> {
 pub struct Internal<T>(pub(crate) EventInner<T>);
 
 /// Thread-local synchronization type used by `Base::spawn_local`.
-#[derive(Clone, Debug)]
+#[derive(Debug)]
 #[doc(hidden)]
 pub struct Local<T>(pub(crate) Rc<RefCell<EventInner<T>>>);
 
+// Not derived, since the event-type `T` need not be `Clone` itself.
+impl<T> Clone for Local<T> {
+    fn clone(&self) -> Self {
+        Local(self.0.clone())
+    }
+}
+
 /// Downgraded version of `Local` which does not count toward ownership.
 #[derive(Debug)]
 #[doc(hidden)]
@@ -133,6 +249,125 @@ pub struct Event<S> {
     pub(crate) inner: S,
     pub(crate) in_callback: Arc<AtomicBool>,
     pub(crate) stopped: Arc<AtomicBool>,
+    pub(crate) extras: Arc<Extras>,
+}
+
+/// A closure added via `Event::add_observer`.
+type Observer = Box<dyn FnMut(EventFlags) + Send>;
+
+/// A closure set via `Event::on_exec_time_exceeded`.
+type ExceededCallback = Box<dyn FnMut(Duration) -> bool + Send>;
+
+/// The state this crate layers on top of an event, such as its label and
+/// observers, shared by all handles to the event. It is synchronized like
+/// the event's other shared flags, so it keeps handles `Send` where the
+/// synchronization type allows it.
+#[derive(Default)]
+pub(crate) struct Extras {
+    /// Whether stats are enabled via `Event::enable_stats`, checked before
+    /// locking `stats` on every invocation.
+    stats_enabled: AtomicBool,
+    stats: Mutex<EventStats>,
+    label: Mutex<Option<String>>,
+    observers: Mutex<Vec<Observer>>,
+    /// The execution-time cap in nanoseconds, where 0 means none.
+    exec_limit: AtomicU64,
+    on_exec_time_exceeded: Mutex<Option<ExceededCallback>>,
+}
+
+impl Extras {
+    /// Records an invocation of the event's callback, if stats are enabled.
+    #[inline]
+    pub(crate) fn record_fire(&self) {
+        if self.stats_enabled.load(Ordering::Relaxed) {
+            let mut stats = lock(&self.stats);
+            stats.fire_count += 1;
+            stats.last_fired = Some(Instant::now());
+        }
+    }
+
+    /// Invokes all observers in registration order. Observers added along
+    /// the way are kept, but only invoked from the next activation on.
+    #[inline]
+    pub(crate) fn notify(&self, flags: EventFlags) {
+        // Take the observers out while they run, so they may add more.
+        let observers = std::mem::take(&mut *lock(&self.observers));
+        if observers.is_empty() {
+            return;
+        }
+
+        // Put them back even if one panics, so they survive a caught panic.
+        struct Restore<'a>(&'a Mutex<Vec<Observer>>, Vec<Observer>);
+        impl Drop for Restore<'_> {
+            fn drop(&mut self) {
+                let mut slot = lock(self.0);
+                let mut observers = std::mem::take(&mut self.1);
+                observers.append(&mut slot);
+                *slot = observers;
+            }
+        }
+
+        let mut restore = Restore(&self.observers, observers);
+        for observer in restore.1.iter_mut() {
+            observer(flags);
+        }
+    }
+
+    /// Returns the execution-time cap, if any, as a cheap check before timing
+    /// a callback.
+    #[inline]
+    pub(crate) fn exec_limit(&self) -> Option<Duration> {
+        match self.exec_limit.load(Ordering::Relaxed) {
+            0 => None,
+            nanos => Some(Duration::from_nanos(nanos)),
+        }
+    }
+
+    /// Reports that a callback took `took`, past the cap, and returns whether
+    /// the event should be stopped.
+    pub(crate) fn exec_time_exceeded(&self, took: Duration) -> bool {
+        // Take the closure out while it runs, so it may replace itself.
+        let cb = match lock(&self.on_exec_time_exceeded).take() {
+            Some(cb) => cb,
+            None => return false,
+        };
+
+        // Put it back even if it panics, so it survives a caught panic.
+        struct Restore<'a>(
+            &'a Mutex<Option<ExceededCallback>>,
+            Option<ExceededCallback>,
+        );
+        impl Drop for Restore<'_> {
+            fn drop(&mut self) {
+                if let Some(cb) = self.1.take() {
+                    lock(self.0).get_or_insert(cb);
+                }
+            }
+        }
+
+        let mut restore = Restore(&self.on_exec_time_exceeded, Some(cb));
+        (restore.1.as_mut().expect("Missing exceeded callback"))(took)
+    }
+}
+
+impl std::fmt::Debug for Extras {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Extras")
+            .field("label", &*lock(&self.label))
+            .field("stats", &*lock(&self.stats))
+            .field("observers", &lock(&self.observers).len())
+            .field("exec_limit", &self.exec_limit())
+            .finish()
+    }
+}
+
+/// Activation statistics of an event, as returned by `Event::stats`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct EventStats {
+    /// The number of times the event's callback has been invoked.
+    pub fire_count: u64,
+    /// When the event's callback was last invoked, if ever.
+    pub last_fired: Option<Instant>,
 }
 
 impl<S> Event<S> {
@@ -156,6 +391,61 @@ impl<S> Event<S> {
     pub(crate) fn set_stopped(&self, stopped: bool) {
         self.stopped.store(stopped, Ordering::Relaxed);
     }
+
+    /// Starts counting the invocations of the event's callback, for `stats`.
+    /// This is opt-in, since it reads the clock on every invocation.
+    pub fn enable_stats(&self) {
+        self.extras.stats_enabled.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns how often, and when last, the event's callback was invoked
+    /// since `enable_stats`. Without it, nothing is counted.
+    pub fn stats(&self) -> EventStats {
+        *lock(&self.extras.stats)
+    }
+
+    /// Attaches a label to the event, e.g. "heartbeat", which shows up in its
+    /// `Debug` output to tell events apart when logging. The label is shared
+    /// by all handles to the event, and is never passed to libevent.
+    pub fn set_label(&self, label: impl Into<String>) {
+        *lock(&self.extras.label) = Some(label.into());
+    }
+
+    /// Adds a closure to be invoked with the activation flags each time the
+    /// event activates, after the event's own callback and any previously
+    /// added observers. This lets several subsystems react to the same
+    /// readiness without registering the fd with libevent again.
+    pub fn add_observer(&self, cb: impl FnMut(EventFlags) + Send + 'static) {
+        lock(&self.extras.observers).push(Box::new(cb));
+    }
+
+    /// Caps how long the event's callback may run, measured around each
+    /// invocation, to detect slow callbacks. Whenever the callback runs past
+    /// `limit`, the closure set via `on_exec_time_exceeded` is invoked.
+    /// `None` lifts the cap.
+    ///
+    /// The callback is not interrupted, so the cap is only checked once it
+    /// has returned.
+    pub fn set_exec_time_limit(&self, limit: Option<Duration>) {
+        // Saturate rather than wrap, and keep a zero cap distinct from none.
+        let nanos = limit.map_or(0, |limit| {
+            u64::try_from(limit.as_nanos()).unwrap_or(u64::MAX).max(1)
+        });
+        self.extras.exec_limit.store(nanos, Ordering::Relaxed);
+    }
+
+    /// Sets `cb` to be invoked with the time the event's callback took,
+    /// whenever that exceeds the cap set via `set_exec_time_limit`, e.g. to
+    /// log a warning. If `cb` returns `true`, the event is stopped, as if
+    /// via `stop`.
+    pub fn on_exec_time_exceeded(&self, cb: impl FnMut(Duration) -> bool + Send + 'static) {
+        *lock(&self.extras.on_exec_time_exceeded) = Some(Box::new(cb));
+    }
+
+    /// Returns the label attached via `set_label`, if any.
+    pub fn label(&self) -> Option<String> {
+        lock(&self.extras.label).clone()
+    }
 }
 
 impl Event<Inactive<Fd>> {
@@ -163,6 +453,20 @@ impl Event<Inactive<Fd>> {
         Inactive::new(Some(fd), flags, timeout)
     }
 
+    /// Creates an event for the fd of `source`, e.g. a `&TcpStream`,
+    /// `&UdpSocket` or `&File`, without calling `as_raw_fd` by hand. Use `new`
+    /// for fds not owned by such a type.
+    ///
+    /// The event does not borrow `source`, which must stay open for as long
+    /// as the event is active.
+    pub fn from_fd<S: AsRawFd + ?Sized>(
+        source: &S,
+        flags: EventFlags,
+        timeout: Option<Duration>,
+    ) -> Self {
+        Self::new(source.as_raw_fd(), flags, timeout)
+    }
+
     /// Shouldn't be allowing Fd's to clone, so this is internal-only.
     pub(crate) fn __clone(&self) -> Self {
         Inactive::new(
@@ -199,6 +503,7 @@ impl<T> Inactive<T> {
             },
             in_callback: Arc::new(AtomicBool::new(false)),
             stopped: Arc::new(AtomicBool::new(false)),
+            extras: Default::default(),
         }
     }
 }
@@ -209,6 +514,7 @@ impl<S: Clone> Clone for Event<S> {
             inner: self.inner.clone(),
             in_callback: self.in_callback.clone(),
             stopped: self.stopped.clone(),
+            extras: self.extras.clone(),
         }
     }
 }
@@ -236,6 +542,7 @@ impl<S: Downgrade> Downgrade for Event<S> {
             inner: self.inner.downgrade(),
             in_callback: self.in_callback.clone(),
             stopped: self.stopped.clone(),
+            extras: self.extras.clone(),
         }
     }
 }
@@ -246,6 +553,7 @@ impl<T> From<EventInner<T>> for Event<Internal<T>> {
             inner: Internal(inner),
             in_callback: Arc::new(AtomicBool::new(false)),
             stopped: Arc::new(AtomicBool::new(false)),
+            extras: Default::default(),
         }
     }
 }
@@ -256,6 +564,7 @@ impl<T> From<EventInner<T>> for Event<Local<T>> {
             inner: Local(Rc::new(RefCell::new(inner))),
             in_callback: Arc::new(AtomicBool::new(false)),
             stopped: Arc::new(AtomicBool::new(false)),
+            extras: Default::default(),
         }
     }
 }
@@ -288,6 +597,37 @@ impl<T> Event<Local<T>> {
         self.set_stopped(true);
         self.with_inner(|inner| inner.stop())
     }
+
+    /// Replaces the closure invoked when the event activates, without
+    /// re-creating the event. The fd, flags, priority and timeout are kept,
+    /// and the previous closure is dropped.
+    ///
+    /// This cannot be done while the event's own callback is running, since
+    /// the previous closure is still borrowed at that point.
+    pub fn set_callback<F>(&mut self, cb: F) -> io::Result<()>
+    where
+        T: Exec<LocalWeak<T>, F>,
+    {
+        if self.in_callback() {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                "Cannot replace callback from within itself",
+            ));
+        }
+
+        let dispatch = self.inner.0.borrow().dispatch.clone();
+        let cb_wrapped =
+            EventCallbackWrapper::<LocalWeak<T>, T, F>::new(cb, self.downgrade(), dispatch);
+        let ctx = Box::into_raw(cb_wrapped) as EventCallbackCtx;
+
+        self.with_inner(|inner| {
+            inner.reassign(
+                handle_wrapped_callback::<LocalWeak<T>, T, F>,
+                ctx,
+                Some(finalize_wrapped_callback::<LocalWeak<T>, T, F>),
+            )
+        })
+    }
 }
 
 impl<T> Event<LocalWeak<T>> {
@@ -306,11 +646,150 @@ impl<T> Event<LocalWeak<T>> {
     }
 }
 
+/// Provides access to the raw `event` behind an event handle, for generic
+/// code which needs to call into libevent directly.
+pub trait AsRawEvent {
+    /// Exposes the raw, non-null `event` pointer.
+    ///
+    /// # Safety
+    ///
+    /// This function returns a valid, non-null `event` pointer which by
+    /// itself is safe. However, this function serves as an escape hatch to do
+    /// unsafe things.
+    unsafe fn as_raw(&self) -> NonNull<libevent_sys::event>;
+}
+
+impl<E: AsRawEvent + ?Sized> AsRawEvent for &E {
+    unsafe fn as_raw(&self) -> NonNull<libevent_sys::event> {
+        (**self).as_raw()
+    }
+}
+
+impl<E: AsRawEvent + ?Sized> AsRawEvent for &mut E {
+    unsafe fn as_raw(&self) -> NonNull<libevent_sys::event> {
+        (**self).as_raw()
+    }
+}
+
+impl<T> AsRawEvent for Event<Internal<T>> {
+    unsafe fn as_raw(&self) -> NonNull<libevent_sys::event> {
+        self.inner.0.as_raw()
+    }
+}
+
+// Only a shared borrow is needed here, so that comparing or hashing handles
+// works even while the event is mutably borrowed elsewhere.
+impl<T> AsRawEvent for Event<Local<T>> {
+    unsafe fn as_raw(&self) -> NonNull<libevent_sys::event> {
+        self.inner.0.borrow().as_raw()
+    }
+}
+
+impl<T> AsRawEvent for Event<LocalWeak<T>> {
+    unsafe fn as_raw(&self) -> NonNull<libevent_sys::event> {
+        let upgraded = self.inner.0.upgrade().unwrap();
+        let raw = upgraded.borrow().as_raw();
+        raw
+    }
+}
+
+impl<S> Event<S>
+where
+    Event<S>: AsRawEvent,
+{
+    /// Returns whether the event was set up with `EV_PERSIST`, i.e. stays
+    /// pending after activating, like an `Interval`, as opposed to a
+    /// `Oneshot`.
+    pub fn is_persistent(&self) -> bool {
+        let events = unsafe { libevent_sys::event_get_events(self.as_raw().as_ptr()) };
+        EventFlags::from_bits_truncate(events as u32).contains(EventFlags::PERSIST)
+    }
+
+    /// Wrapper for libevent's `event_get_priority`, which returns the
+    /// event's current priority, where lower numbers are more urgent.
+    pub fn priority(&self) -> c_int {
+        unsafe { libevent_sys::event_get_priority(self.as_raw().as_ptr()) }
+    }
+
+    /// Returns the time left until the event's pending timeout expires, for
+    /// timers as well as fd events with a timeout, or `None` if it has no
+    /// pending timeout. A friendlier wrapper around `event_pending`.
+    ///
+    /// libevent reports the expiry as wall-clock time, so this is off by as
+    /// much as the clock was stepped since the timeout was scheduled.
+    pub fn time_until_fire(&self) -> Option<Duration> {
+        let mut expiry: libevent_sys::timeval = unsafe { std::mem::zeroed() };
+        let pending = unsafe {
+            libevent_sys::event_pending(
+                self.as_raw().as_ptr(),
+                libevent_sys::EV_TIMEOUT as c_short,
+                &mut expiry,
+            )
+        };
+        if pending == 0 {
+            return None;
+        }
+
+        let mut now: libc::timeval = unsafe { std::mem::zeroed() };
+        unsafe { libc::gettimeofday(&mut now, std::ptr::null_mut()) };
+
+        let micros = |sec: i64, usec: i64| sec * 1_000_000 + usec;
+        let left = micros(expiry.tv_sec as i64, expiry.tv_usec as i64)
+            - micros(now.tv_sec as i64, now.tv_usec as i64);
+
+        Some(Duration::from_micros(left.max(0) as u64))
+    }
+
+    /// Sets the event's priority to `high` while running `f`, e.g. while
+    /// handling a latency-sensitive control command, and restores the
+    /// original priority afterwards. Lower numbers are more urgent.
+    ///
+    /// libevent applies the priority when the event becomes active, so this
+    /// only affects activations happening while `f` runs, e.g. from loop
+    /// turns within it. libevent also refuses to change the priority of an
+    /// event which is currently active, in which case it stays unchanged.
+    pub fn with_priority_boost<R>(&self, high: c_int, f: impl FnOnce() -> R) -> R {
+        let ptr = unsafe { self.as_raw() }.as_ptr();
+        let original = self.priority();
+
+        unsafe { libevent_sys::event_priority_set(ptr, high) };
+        let result = f();
+        unsafe { libevent_sys::event_priority_set(ptr, original) };
+
+        result
+    }
+}
+
+/// Event handles compare by identity rather than behavior: two handles are
+/// equal if they refer to the same underlying `event`, e.g. a `Local` handle
+/// and its clones, regardless of callback or state.
+impl<S> PartialEq for Event<S>
+where
+    Event<S>: AsRawEvent,
+{
+    fn eq(&self, other: &Self) -> bool {
+        unsafe { self.as_raw() == other.as_raw() }
+    }
+}
+
+impl<S> Eq for Event<S> where Event<S>: AsRawEvent {}
+
+/// Hashes the identity of the underlying `event`, consistent with `PartialEq`.
+impl<S> Hash for Event<S>
+where
+    Event<S>: AsRawEvent,
+{
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        unsafe { self.as_raw() }.hash(state)
+    }
+}
+
 impl<T> Drop for EventInner<T> {
     fn drop(&mut self) {
         self.drop_context();
 
         let raw = unsafe { self.as_raw() };
+        self.dispatch.unregister(raw);
 
         unsafe { libevent_sys::event_free(raw.as_ptr()) };
     }
@@ -327,18 +806,21 @@ pub trait Exec<S, F> {
 }
 
 impl<S, F: FnMut(&mut Event<S>, RawFd, EventFlags)> Exec<S, F> for Fd {
+    #[inline]
     fn exec(ev: &mut Event<S>, fd: RawFd, flags: EventFlags, cb: &mut F) {
         cb(ev, fd, flags)
     }
 }
 
 impl<S, F: FnMut(&mut Event<S>)> Exec<S, F> for Interval {
+    #[inline]
     fn exec(ev: &mut Event<S>, _fd: RawFd, _flags: EventFlags, cb: &mut F) {
         cb(ev)
     }
 }
 
 impl<S, F: FnMut(&mut Event<S>)> Exec<S, F> for Oneshot {
+    #[inline]
     fn exec(ev: &mut Event<S>, _fd: RawFd, _flags: EventFlags, cb: &mut F) {
         cb(ev)
     }
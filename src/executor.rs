@@ -0,0 +1,182 @@
+use std::cell::RefCell;
+use std::future::Future;
+use std::io;
+use std::os::unix::io::AsRawFd;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake, Waker};
+use std::time::Duration;
+
+use crate::event::Local;
+use crate::{Base, Event, EventFlags, Fd, LoopFlags, Notifier, Oneshot};
+
+/// Wakes up `Base::block_on` by flagging the future as woken and notifying
+/// the loop, from any thread.
+struct LoopWaker {
+    woken: AtomicBool,
+    notifier: Notifier,
+}
+
+impl Wake for LoopWaker {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.woken.store(true, Ordering::Release);
+        self.notifier.notify();
+    }
+}
+
+/// The state of a `Sleep`, shared with its timer's callback.
+#[derive(Default)]
+struct SleepState {
+    elapsed: bool,
+    waker: Option<Waker>,
+}
+
+/// A future which completes once its timer on a `Base` has fired, created
+/// with `Base::sleep`.
+///
+/// The future only makes progress while the loop runs, e.g. within
+/// `Base::block_on`.
+pub struct Sleep {
+    state: Rc<RefCell<SleepState>>,
+    _event: Event<Local<Oneshot>>,
+}
+
+impl Future for Sleep {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let mut state = self.state.borrow_mut();
+        if state.elapsed {
+            return Poll::Ready(());
+        }
+
+        state.waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+/// The state of a `Ready`, shared with its event's callback.
+#[derive(Default)]
+struct ReadyState {
+    flags: Option<EventFlags>,
+    waker: Option<Waker>,
+}
+
+/// A future which completes with the flags its fd became ready with, created
+/// with `Base::ready`.
+///
+/// Like `Sleep`, the future only makes progress while the loop runs.
+pub struct Ready {
+    state: Rc<RefCell<ReadyState>>,
+    _event: Event<Local<Fd>>,
+}
+
+impl Future for Ready {
+    type Output = EventFlags;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<EventFlags> {
+        let mut state = self.state.borrow_mut();
+        if let Some(flags) = state.flags {
+            return Poll::Ready(flags);
+        }
+
+        state.waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+impl Base {
+    /// Runs `future` to completion on the current thread, using the loop as
+    /// its reactor: the future is polled whenever it was woken, and the loop
+    /// is run in between. Futures tied to this `Base`, such as those from
+    /// `Base::sleep`, make progress while it blocks.
+    ///
+    /// Wakeups are delivered through an internal notifier, so the future may
+    /// be woken from any thread. Other events of the `Base` are dispatched
+    /// as usual while blocking.
+    pub fn block_on<F: Future>(&mut self, future: F) -> io::Result<F::Output> {
+        let (notifier, _event) = self.notifier(|| {})?;
+        let loop_waker = Arc::new(LoopWaker {
+            // Poll once up front, before waiting for any wakeup.
+            woken: AtomicBool::new(true),
+            notifier,
+        });
+        let waker = Waker::from(loop_waker.clone());
+
+        let mut future = Box::pin(future);
+        let mut cx = Context::from_waker(&waker);
+
+        loop {
+            if loop_waker.woken.swap(false, Ordering::Acquire) {
+                if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                    return Ok(output);
+                }
+                continue;
+            }
+
+            // The notifier's event keeps the loop from running out of events.
+            self.loop_(LoopFlags::ONCE).into_result()?;
+        }
+    }
+
+    /// Returns a future which completes once `duration` has passed, driven by
+    /// a oneshot timer on this `Base`, e.g. for use within `block_on`.
+    pub fn sleep(&mut self, duration: Duration) -> io::Result<Sleep> {
+        let state = Rc::new(RefCell::new(SleepState::default()));
+        let shared = state.clone();
+
+        let event = self.spawn_local(Oneshot::new(duration), move |_ev| {
+            let waker = {
+                let mut state = shared.borrow_mut();
+                state.elapsed = true;
+                state.waker.take()
+            };
+            if let Some(waker) = waker {
+                waker.wake();
+            }
+        })?;
+
+        Ok(Sleep {
+            state,
+            _event: event,
+        })
+    }
+
+    /// Returns a future which completes once the fd of `source` is ready for
+    /// `flags`, e.g. `READ` or `WRITE`, yielding the flags it became ready
+    /// with. The readiness is waited for once, by a non-persistent event.
+    ///
+    /// The future does not borrow `source`, which must stay open until it
+    /// completes or is dropped.
+    pub fn ready<S: AsRawFd + ?Sized>(
+        &mut self,
+        source: &S,
+        flags: EventFlags,
+    ) -> io::Result<Ready> {
+        let state = Rc::new(RefCell::new(ReadyState::default()));
+        let shared = state.clone();
+
+        let ev = Event::from_fd(source, flags - EventFlags::PERSIST, None);
+        let event = self.spawn_local(ev, move |_ev, _fd, flags| {
+            let waker = {
+                let mut state = shared.borrow_mut();
+                state.flags = Some(flags);
+                state.waker.take()
+            };
+            if let Some(waker) = waker {
+                waker.wake();
+            }
+        })?;
+
+        Ok(Ready {
+            state,
+            _event: event,
+        })
+    }
+}
@@ -0,0 +1,511 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::ffi::{CStr, CString};
+use std::io;
+use std::marker::PhantomData;
+use std::net::{SocketAddr, TcpListener};
+use std::os::raw::{c_char, c_void};
+use std::os::unix::io::FromRawFd;
+use std::ptr::NonNull;
+
+use crate::{unwind, Base, EvBuffer};
+
+/// The closure invoked for each request to a path registered with
+/// `HttpServer::set_handler`.
+type RequestCallback = Box<dyn FnMut(Request<'_>)>;
+
+/// The HTTP methods understood by libevent's `evhttp`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Method {
+    Get,
+    Post,
+    Head,
+    Put,
+    Delete,
+    Options,
+    Trace,
+    Connect,
+    Patch,
+}
+
+impl Method {
+    fn from_raw(cmd: libevent_sys::evhttp_cmd_type) -> Option<Self> {
+        match cmd {
+            libevent_sys::evhttp_cmd_type_EVHTTP_REQ_GET => Some(Method::Get),
+            libevent_sys::evhttp_cmd_type_EVHTTP_REQ_POST => Some(Method::Post),
+            libevent_sys::evhttp_cmd_type_EVHTTP_REQ_HEAD => Some(Method::Head),
+            libevent_sys::evhttp_cmd_type_EVHTTP_REQ_PUT => Some(Method::Put),
+            libevent_sys::evhttp_cmd_type_EVHTTP_REQ_DELETE => Some(Method::Delete),
+            libevent_sys::evhttp_cmd_type_EVHTTP_REQ_OPTIONS => Some(Method::Options),
+            libevent_sys::evhttp_cmd_type_EVHTTP_REQ_TRACE => Some(Method::Trace),
+            libevent_sys::evhttp_cmd_type_EVHTTP_REQ_CONNECT => Some(Method::Connect),
+            libevent_sys::evhttp_cmd_type_EVHTTP_REQ_PATCH => Some(Method::Patch),
+            _ => None,
+        }
+    }
+}
+
+/// Wrapper for libevent's `evhttp`, a small embedded HTTP server which
+/// serves requests from within the event loop of a `Base`.
+///
+/// Requests to paths without a handler are answered with a 404.
+pub struct HttpServer {
+    inner: NonNull<libevent_sys::evhttp>,
+    socket: NonNull<libevent_sys::evhttp_bound_socket>,
+    handlers: HashMap<String, Box<RefCell<RequestCallback>>>,
+}
+
+impl HttpServer {
+    /// Binds a new HTTP server to `addr`, serving requests on the given
+    /// `Base`.
+    pub fn bind(base: &mut Base, addr: SocketAddr) -> io::Result<Self> {
+        let inner = unsafe { libevent_sys::evhttp_new(base.as_raw().as_ptr()) };
+        let inner = NonNull::new(inner)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::Other, "Failed to create evhttp"))?;
+
+        let ip = CString::new(addr.ip().to_string()).unwrap();
+        let socket = unsafe {
+            libevent_sys::evhttp_bind_socket_with_handle(inner.as_ptr(), ip.as_ptr(), addr.port())
+        };
+
+        match NonNull::new(socket) {
+            Some(socket) => Ok(HttpServer {
+                inner,
+                socket,
+                handlers: HashMap::new(),
+            }),
+            None => {
+                // Grab the error first, before freeing can clobber it.
+                let error = io::Error::last_os_error();
+                unsafe { libevent_sys::evhttp_free(inner.as_ptr()) };
+                Err(error)
+            }
+        }
+    }
+
+    /// Exposes the raw, non-null `evhttp` pointer.
+    ///
+    /// # Safety
+    ///
+    /// This function returns a valid, non-null `evhttp` pointer which by
+    /// itself is safe. However, this function serves as an escape hatch to do
+    /// unsafe things.
+    pub unsafe fn as_raw(&self) -> NonNull<libevent_sys::evhttp> {
+        self.inner
+    }
+
+    /// Returns the address the server is bound to.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        let fd = unsafe { libevent_sys::evhttp_bound_socket_get_fd(self.socket.as_ptr()) };
+
+        // Borrow the socket as a `TcpListener` without taking ownership.
+        let listener = std::mem::ManuallyDrop::new(unsafe { TcpListener::from_raw_fd(fd) });
+        listener.local_addr()
+    }
+
+    /// Wrapper for libevent's `evhttp_set_cb`, which invokes `cb` for every
+    /// request whose path matches `path` exactly. Any handler previously set
+    /// for the same path is replaced.
+    ///
+    /// A request which is dropped by the handler without calling
+    /// `Request::respond` is answered with a 500.
+    pub fn set_handler<F>(&mut self, path: &str, cb: F) -> io::Result<()>
+    where
+        F: FnMut(Request<'_>) + 'static,
+    {
+        let c_path = CString::new(path)
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "Path contains a NUL byte"))?;
+
+        let handler: Box<RefCell<RequestCallback>> = Box::new(RefCell::new(Box::new(cb)));
+        let ctx = &*handler as *const RefCell<RequestCallback> as *mut c_void;
+
+        if self.handlers.contains_key(path) {
+            unsafe { libevent_sys::evhttp_del_cb(self.inner.as_ptr(), c_path.as_ptr()) };
+        }
+
+        let ret = unsafe {
+            libevent_sys::evhttp_set_cb(
+                self.inner.as_ptr(),
+                c_path.as_ptr(),
+                Some(handle_request),
+                ctx,
+            )
+        };
+
+        if ret == 0 {
+            self.handlers.insert(path.to_owned(), handler);
+            Ok(())
+        } else {
+            self.handlers.remove(path);
+            Err(io::Error::new(
+                io::ErrorKind::Other,
+                "Failed to set HTTP handler",
+            ))
+        }
+    }
+}
+
+impl Drop for HttpServer {
+    fn drop(&mut self) {
+        // This also closes the bound socket; the handlers are dropped after.
+        unsafe { libevent_sys::evhttp_free(self.inner.as_ptr()) };
+    }
+}
+
+/// Acts as a C-compatible trampoline for a path's request closure.
+extern "C" fn handle_request(req: *mut libevent_sys::evhttp_request, ctx: *mut c_void) {
+    let handler = unsafe { &*(ctx as *const RefCell<RequestCallback>) };
+
+    if let Some(inner) = NonNull::new(req) {
+        let conn = unsafe { libevent_sys::evhttp_request_get_connection(req) };
+        let base = unsafe { libevent_sys::evhttp_connection_get_base(conn) };
+        unwind::catch(base, || {
+            (handler.borrow_mut())(Request {
+                inner,
+                responded: false,
+                _phantom: PhantomData,
+            })
+        });
+    }
+}
+
+/// An incoming request handed to an `HttpServer` handler.
+///
+/// The request can not outlive the handler invocation; it has to be answered
+/// from within it, via `respond`.
+pub struct Request<'a> {
+    inner: NonNull<libevent_sys::evhttp_request>,
+    responded: bool,
+    _phantom: PhantomData<&'a mut libevent_sys::evhttp_request>,
+}
+
+impl<'a> Request<'a> {
+    /// Exposes the raw, non-null `evhttp_request` pointer.
+    ///
+    /// # Safety
+    ///
+    /// This function returns a valid, non-null `evhttp_request` pointer which
+    /// by itself is safe. However, this function serves as an escape hatch to
+    /// do unsafe things.
+    pub unsafe fn as_raw(&self) -> NonNull<libevent_sys::evhttp_request> {
+        self.inner
+    }
+
+    /// Returns the request's method, or `None` for one unknown to libevent.
+    pub fn method(&self) -> Option<Method> {
+        Method::from_raw(unsafe { libevent_sys::evhttp_request_get_command(self.inner.as_ptr()) })
+    }
+
+    /// Returns the request URI as sent by the client, including any query
+    /// string. Invalid UTF-8 is replaced with `U+FFFD`.
+    pub fn uri(&self) -> String {
+        let uri = unsafe { libevent_sys::evhttp_request_get_uri(self.inner.as_ptr()) };
+        to_string_lossy(uri)
+    }
+
+    /// Returns the request headers as name-value pairs, in the order they
+    /// were received.
+    pub fn headers(&self) -> Vec<(String, String)> {
+        let mut headers = Vec::new();
+
+        unsafe {
+            let keyvalq = libevent_sys::evhttp_request_get_input_headers(self.inner.as_ptr());
+            let mut kv = (*keyvalq).tqh_first;
+            while !kv.is_null() {
+                headers.push((to_string_lossy((*kv).key), to_string_lossy((*kv).value)));
+                kv = (*kv).next.tqe_next;
+            }
+        }
+
+        headers
+    }
+
+    /// Wrapper for libevent's `evhttp_find_header`, which returns the value
+    /// of the first request header called `name`, compared
+    /// case-insensitively.
+    pub fn header(&self, name: &str) -> Option<String> {
+        let name = CString::new(name).ok()?;
+
+        let value = unsafe {
+            let keyvalq = libevent_sys::evhttp_request_get_input_headers(self.inner.as_ptr());
+            libevent_sys::evhttp_find_header(keyvalq, name.as_ptr())
+        };
+
+        if value.is_null() {
+            None
+        } else {
+            Some(to_string_lossy(value))
+        }
+    }
+
+    /// Returns the decoded value of the query parameter `key`, as parsed by
+    /// libevent's `evhttp_parse_query`. If `key` appears more than once, the
+    /// first value is returned.
+    ///
+    /// Returns `None` if the URI has no such parameter, or if its query
+    /// string is malformed.
+    pub fn query_param(&self, key: &str) -> Option<String> {
+        let key = CString::new(key).ok()?;
+        let uri = unsafe { libevent_sys::evhttp_request_get_uri(self.inner.as_ptr()) };
+
+        let mut params = libevent_sys::evkeyvalq {
+            tqh_first: std::ptr::null_mut(),
+            tqh_last: std::ptr::null_mut(),
+        };
+        params.tqh_last = &mut params.tqh_first;
+
+        unsafe {
+            // Even on failure, some parameters may have been added already.
+            let value = if libevent_sys::evhttp_parse_query(uri, &mut params) == 0 {
+                let value = libevent_sys::evhttp_find_header(&params, key.as_ptr());
+                if value.is_null() {
+                    None
+                } else {
+                    Some(to_string_lossy(value))
+                }
+            } else {
+                None
+            };
+            libevent_sys::evhttp_clear_headers(&mut params);
+            value
+        }
+    }
+
+    /// Wrapper for libevent's `evhttp_send_reply`, which answers the request
+    /// with the given status code and body. The reason phrase is derived
+    /// from the status code.
+    pub fn respond(mut self, status: u16, body: &[u8]) -> io::Result<()> {
+        let mut buf = EvBuffer::new()?;
+        buf.add(body)?;
+
+        unsafe {
+            libevent_sys::evhttp_send_reply(
+                self.inner.as_ptr(),
+                status.into(),
+                std::ptr::null(),
+                buf.as_raw().as_ptr(),
+            )
+        };
+        self.responded = true;
+
+        Ok(())
+    }
+}
+
+impl<'a> Drop for Request<'a> {
+    fn drop(&mut self) {
+        // Never leave the client hanging on an unanswered request.
+        if !self.responded {
+            unsafe {
+                libevent_sys::evhttp_send_error(
+                    self.inner.as_ptr(),
+                    libevent_sys::HTTP_INTERNAL as _,
+                    std::ptr::null(),
+                )
+            };
+        }
+    }
+}
+
+/// The closure invoked with the outcome of a request made by `HttpClient`.
+type ResponseCallback = Box<dyn FnOnce(io::Result<Response>)>;
+
+/// The context of a request made by `HttpClient`, which keeps the base
+/// around since a failed request may come without a connection.
+struct ResponseContext {
+    base: NonNull<libevent_sys::event_base>,
+    cb: ResponseCallback,
+}
+
+/// A response received by `HttpClient`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Response {
+    /// The response's status code.
+    pub status: u16,
+    /// The response's body.
+    pub body: Vec<u8>,
+}
+
+/// An HTTP client making requests from within the event loop of a `Base`,
+/// based on libevent's `evhttp_connection`.
+pub struct HttpClient;
+
+impl HttpClient {
+    /// Issues a GET request for `url`, which must be an absolute `http` URL,
+    /// on the given `Base`. Once the response has been received, or the
+    /// request failed, `cb` is invoked from within the event loop.
+    ///
+    /// Each request uses a connection of its own, which libevent frees once
+    /// the request completes. If the base is freed while the request is
+    /// still in flight, `cb` is never invoked.
+    pub fn get<F>(base: &mut Base, url: &str, cb: F) -> io::Result<()>
+    where
+        F: FnOnce(io::Result<Response>) + 'static,
+    {
+        let invalid = || io::Error::new(io::ErrorKind::InvalidInput, "Invalid URL");
+        let c_url = CString::new(url).map_err(|_| invalid())?;
+
+        let uri = NonNull::new(unsafe { libevent_sys::evhttp_uri_parse(c_url.as_ptr()) })
+            .ok_or_else(invalid)?;
+        let target = unsafe { RequestTarget::from_uri(uri) };
+        unsafe { libevent_sys::evhttp_uri_free(uri.as_ptr()) };
+        let target = target.ok_or_else(invalid)?;
+
+        let conn = unsafe {
+            libevent_sys::evhttp_connection_base_new(
+                base.as_raw().as_ptr(),
+                std::ptr::null_mut(),
+                target.host.as_ptr(),
+                target.port,
+            )
+        };
+        let conn = NonNull::new(conn).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::Other, "Failed to create HTTP connection")
+        })?;
+
+        let ctx = Box::into_raw(Box::new(ResponseContext {
+            base: unsafe { base.as_raw() },
+            cb: Box::new(cb),
+        }));
+        let req = unsafe { libevent_sys::evhttp_request_new(Some(handle_response), ctx as _) };
+
+        let ret = match NonNull::new(req) {
+            Some(req) => unsafe {
+                let headers = libevent_sys::evhttp_request_get_output_headers(req.as_ptr());
+                libevent_sys::evhttp_add_header(
+                    headers,
+                    b"Host\0".as_ptr() as *const c_char,
+                    target.host.as_ptr(),
+                );
+
+                // On failure, this frees the request already.
+                libevent_sys::evhttp_make_request(
+                    conn.as_ptr(),
+                    req.as_ptr(),
+                    libevent_sys::evhttp_cmd_type_EVHTTP_REQ_GET,
+                    target.path.as_ptr(),
+                )
+            },
+            None => -1,
+        };
+
+        if ret == 0 {
+            unsafe { libevent_sys::evhttp_connection_free_on_completion(conn.as_ptr()) };
+            Ok(())
+        } else {
+            unsafe {
+                libevent_sys::evhttp_connection_free(conn.as_ptr());
+                drop(Box::from_raw(ctx));
+            }
+            Err(io::Error::new(
+                io::ErrorKind::Other,
+                "Failed to make HTTP request",
+            ))
+        }
+    }
+}
+
+/// The parts of a URL needed to make a request with `evhttp_connection`.
+struct RequestTarget {
+    host: CString,
+    port: u16,
+    path: CString,
+}
+
+impl RequestTarget {
+    /// Extracts host, port and path (including the query) from a parsed
+    /// `http` URL.
+    ///
+    /// # Safety
+    ///
+    /// `uri` must be a valid `evhttp_uri`.
+    unsafe fn from_uri(uri: NonNull<libevent_sys::evhttp_uri>) -> Option<Self> {
+        let uri = uri.as_ptr();
+
+        let scheme = libevent_sys::evhttp_uri_get_scheme(uri);
+        if scheme.is_null()
+            || !CStr::from_ptr(scheme)
+                .to_bytes()
+                .eq_ignore_ascii_case(b"http")
+        {
+            return None;
+        }
+
+        let host = libevent_sys::evhttp_uri_get_host(uri);
+        if host.is_null() {
+            return None;
+        }
+
+        // A port of -1 means none was given.
+        let port = match libevent_sys::evhttp_uri_get_port(uri) {
+            -1 => 80,
+            port => u16::try_from(port).ok()?,
+        };
+
+        let mut path = to_string_lossy(libevent_sys::evhttp_uri_get_path(uri));
+        if path.is_empty() {
+            path.push('/');
+        }
+        let query = libevent_sys::evhttp_uri_get_query(uri);
+        if !query.is_null() {
+            path.push('?');
+            path.push_str(&to_string_lossy(query));
+        }
+
+        Some(RequestTarget {
+            host: CStr::from_ptr(host).to_owned(),
+            port,
+            path: CString::new(path).ok()?,
+        })
+    }
+}
+
+/// Acts as a C-compatible trampoline for `HttpClient`'s response closure,
+/// which is invoked exactly once per request.
+extern "C" fn handle_response(req: *mut libevent_sys::evhttp_request, ctx: *mut c_void) {
+    let ResponseContext { base, cb } = *unsafe { Box::from_raw(ctx as *mut ResponseContext) };
+    unwind::catch(base.as_ptr(), || respond(req, cb));
+}
+
+/// Invokes `cb` with the outcome of `req`, which is null or a valid
+/// `evhttp_request`, for `handle_response`.
+fn respond(req: *mut libevent_sys::evhttp_request, cb: ResponseCallback) {
+    // libevent signals connection failures with either no request at all,
+    // or one without a response code.
+    let status = if req.is_null() {
+        0
+    } else {
+        unsafe { libevent_sys::evhttp_request_get_response_code(req) }
+    };
+
+    if status <= 0 {
+        return cb(Err(io::Error::new(
+            io::ErrorKind::Other,
+            "HTTP request failed",
+        )));
+    }
+
+    let body = unsafe {
+        let input = libevent_sys::evhttp_request_get_input_buffer(req);
+        match NonNull::new(input) {
+            Some(input) => {
+                let buf = EvBuffer::from_raw(input, false);
+                buf.peek(buf.len())
+            }
+            None => Vec::new(),
+        }
+    };
+
+    cb(Ok(Response {
+        status: status as u16,
+        body,
+    }));
+}
+
+/// Copies a C string owned by libevent, treating null as empty.
+fn to_string_lossy(s: *const c_char) -> String {
+    if s.is_null() {
+        return String::new();
+    }
+    unsafe { CStr::from_ptr(s) }.to_string_lossy().into_owned()
+}
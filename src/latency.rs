@@ -0,0 +1,99 @@
+use std::ptr::NonNull;
+use std::time::Duration;
+
+use crate::dispatch::lock;
+use crate::Base;
+
+/// The number of buckets of a `LatencyHistogram`.
+const NUM_BUCKETS: usize = 24;
+
+/// A histogram of the delays between events becoming ready and their
+/// callbacks being invoked, as returned by `Base::latency_stats`.
+///
+/// Bucket `i` counts delays below 2^i microseconds (and at least 2^(i-1)),
+/// with the last bucket also counting everything longer.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LatencyHistogram {
+    buckets: [u64; NUM_BUCKETS],
+    count: u64,
+    total: Duration,
+    max: Duration,
+}
+
+impl LatencyHistogram {
+    /// Returns the number of callbacks recorded.
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Returns the mean delay, or `None` if nothing was recorded.
+    pub fn mean(&self) -> Option<Duration> {
+        if self.count == 0 {
+            return None;
+        }
+        Some(Duration::from_nanos(
+            (self.total.as_nanos() / u128::from(self.count)) as u64,
+        ))
+    }
+
+    /// Returns the longest delay recorded.
+    pub fn max(&self) -> Duration {
+        self.max
+    }
+
+    /// Returns the bucket counts, where bucket `i` counts delays below 2^i
+    /// microseconds.
+    pub fn buckets(&self) -> &[u64] {
+        &self.buckets
+    }
+
+    /// Records the delay of the callback of `event`, which is about to run.
+    pub(crate) fn record_callback(&mut self, event: NonNull<libevent_sys::event>) {
+        let base = unsafe { libevent_sys::event_get_base(event.as_ptr()) };
+        let mut ready: libevent_sys::timeval = unsafe { std::mem::zeroed() };
+        let mut now: libc::timeval = unsafe { std::mem::zeroed() };
+
+        unsafe {
+            libevent_sys::event_base_gettimeofday_cached(base, &mut ready);
+            libc::gettimeofday(&mut now, std::ptr::null_mut());
+        }
+
+        let micros = |sec: i64, usec: i64| sec * 1_000_000 + usec;
+        let delay = micros(now.tv_sec as i64, now.tv_usec as i64)
+            - micros(ready.tv_sec as i64, ready.tv_usec as i64);
+
+        // The clock may have been stepped in between.
+        self.record(Duration::from_micros(delay.max(0) as u64));
+    }
+
+    fn record(&mut self, delay: Duration) {
+        let micros = delay.as_micros().min(u128::from(u64::MAX)) as u64;
+        let bucket = (64 - micros.leading_zeros()) as usize;
+
+        self.buckets[bucket.min(NUM_BUCKETS - 1)] += 1;
+        self.count += 1;
+        self.total += delay;
+        self.max = self.max.max(delay);
+    }
+}
+
+impl Base {
+    /// Starts recording how long each callback was delayed after its event
+    /// became ready, for performance work. Only callbacks of events spawned
+    /// via `Base::spawn*` are recorded.
+    ///
+    /// libevent does not report when the kernel signalled readiness, so this
+    /// is approximated by the cached time taken at the start of each loop
+    /// iteration. Callbacks running late in a busy iteration thus show the
+    /// time spent by the ones before them.
+    pub fn enable_latency_stats(&mut self) {
+        lock(&self.dispatch.latency).get_or_insert_with(LatencyHistogram::default);
+        self.dispatch.update_instrumented();
+    }
+
+    /// Returns the latency stats recorded since `enable_latency_stats`, which
+    /// are empty if it was never called.
+    pub fn latency_stats(&self) -> LatencyHistogram {
+        lock(&self.dispatch.latency).clone().unwrap_or_default()
+    }
+}
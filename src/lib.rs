@@ -2,24 +2,147 @@
 //!
 //! [libevent]: https://libevent.org/
 
+// The API is built on `RawFd`, while libevent takes `SOCKET` handles on
+// Windows; see "Platform Support" in the README.
+#[cfg(not(unix))]
+compile_error!("libevent-rs supports Unix-like systems only; Windows is not supported yet");
+
+use std::os::unix::io::AsRawFd;
+use std::sync::atomic::Ordering;
 use std::time::Duration;
 
 mod event;
-pub use event::{Event, Fd, Interval, Oneshot};
+pub use event::{AsRawEvent, Event, EventStats, Fd, Interval, Oneshot};
 
 mod base;
 pub use base::{
     Base, EventCallbackCtx, EventCallbackFlags, EventFlags, EvutilSocket, ExitReason, LoopFlags,
 };
 
+mod blocking;
+
+mod buffer;
+pub use buffer::{EolStyle, EvBuffer};
+
+mod bufferevent;
+pub use bufferevent::{BufferEvent, FlushMode, ReadAction};
+
+mod busy;
+
+mod cancel;
+pub use cancel::CancellationToken;
+
+mod channel;
+pub use channel::Backpressure;
+
+mod clock;
+
+mod config;
+pub use config::EventConfig;
+
+mod connect;
+
+mod dispatch;
+
+mod error;
+pub use error::{ConnectError, LibeventError};
+
+mod executor;
+pub use executor::{Ready, Sleep};
+
+#[cfg(feature = "http")]
+mod http;
+#[cfg(feature = "http")]
+pub use http::{HttpClient, HttpServer, Method, Request, Response};
+
+mod latency;
+pub use latency::LatencyHistogram;
+
+mod line_server;
+pub use line_server::LineServer;
+
+mod listener;
+#[cfg(feature = "stream")]
+pub use listener::Incoming;
+pub use listener::{Listener, ListenerFlags};
+
+mod notifier;
+pub use notifier::{Notifier, NotifyMechanism, RemoteHandle};
+
+mod pool;
+pub use pool::BasePool;
+
+mod recording;
+pub use recording::{Activation, RecordingBase};
+
+mod signal;
+
+mod source;
+pub use source::Interest;
+
+mod stateful;
+pub use stateful::StateRef;
+
+mod suspend;
+
+mod timer_queue;
+pub use timer_queue::{TimerId, TimerQueue};
+
+mod timer_set;
+pub use timer_set::TimerSet;
+
+mod turn_limit;
+
+mod unwind;
+
+pub mod util;
+
+mod watchdog;
+
+/// Wrapper for libevent's `event_get_supported_methods`, which returns the
+/// names of the backends libevent was compiled with, e.g. "epoll" or
+/// "kqueue", in order of preference, without creating a `Base`.
+///
+/// A listed backend may still turn out to be unusable at runtime, e.g. if
+/// the kernel lacks it; `Base::method` tells which one a base ended up with.
+pub fn available_methods() -> Vec<String> {
+    let mut methods = Vec::new();
+    let mut method = unsafe { libevent_sys::event_get_supported_methods() };
+
+    // The array is static and NULL-terminated.
+    while !method.is_null() && !unsafe { *method }.is_null() {
+        let name = unsafe { std::ffi::CStr::from_ptr(*method) };
+        methods.push(name.to_string_lossy().into_owned());
+        method = unsafe { method.add(1) };
+    }
+
+    methods
+}
+
 /// The context passed into `handle_wrapped_callback`, which handles event-type
 /// specific metadata for trampolining into the user-supplied closure.
+#[repr(C)]
 pub(crate) struct EventCallbackWrapper<S, T, F> {
+    /// Drops the event handle owned by the wrapper, for events whose only
+    /// handle it is. This and `dispatch` must stay the first fields, in line
+    /// with `WrapperHead`, so that they can be reached without knowing the
+    /// type parameters.
+    reclaim: Option<unsafe fn(EventCallbackCtx)>,
+    /// The dispatch state of the event's base.
+    dispatch: std::sync::Arc<dispatch::Dispatch>,
     inner: F,
     event: Option<Event<S>>,
     _phantom: std::marker::PhantomData<T>,
 }
 
+/// The leading fields of every `EventCallbackWrapper`, for reading them from
+/// a callback context without knowing its type parameters.
+#[repr(C)]
+pub(crate) struct WrapperHead {
+    reclaim: Option<unsafe fn(EventCallbackCtx)>,
+    dispatch: std::sync::Arc<dispatch::Dispatch>,
+}
+
 impl Base {
     /// Turns the libevent base once.
     // TODO: any way to show if work was done?
@@ -27,9 +150,61 @@ impl Base {
         self.loop_(LoopFlags::NONBLOCK)
     }
 
-    /// Turns the libevent base until exit or timeout duration reached.
+    /// Turns the libevent base without blocking until no more events are
+    /// ready, and returns the number of callbacks invoked in total.
+    ///
+    /// Events which keep becoming ready, such as a zero-interval `Interval`,
+    /// make this spin forever. Stops early once a callback panicked. Only
+    /// callbacks of events spawned via `Base::spawn*` are counted.
+    pub fn drain_ready(&self) -> usize {
+        let mut total = 0;
+
+        loop {
+            let before = self.dispatch.dispatched.load(Ordering::Relaxed);
+            let reason = self.loop_(LoopFlags::NONBLOCK);
+            let ran = self
+                .dispatch
+                .dispatched
+                .load(Ordering::Relaxed)
+                .wrapping_sub(before);
+
+            total += ran;
+            if ran == 0 || reason == ExitReason::Panicked {
+                return total;
+            }
+        }
+    }
+
+    /// Turns the libevent base without blocking until `pred` returns true,
+    /// sleeping for `poll` whenever a turn invoked no callbacks, e.g. for
+    /// tests waiting on a flag set by some event.
+    ///
+    /// `pred` is checked after every turn, so the base is turned at least
+    /// once. Returns the reason given by the last turn, which is
+    /// `ExitReason::Error` if the loop failed, or `ExitReason::Panicked` if a
+    /// callback panicked. Only callbacks of events spawned via `Base::spawn*`
+    /// count as activity.
+    pub fn run_until(&self, mut pred: impl FnMut() -> bool, poll: Duration) -> ExitReason {
+        loop {
+            let before = self.dispatch.dispatched.load(Ordering::Relaxed);
+            let reason = self.turn();
+
+            if matches!(reason, ExitReason::Error | ExitReason::Panicked) || pred() {
+                return reason;
+            }
+            if self.dispatch.dispatched.load(Ordering::Relaxed) == before {
+                std::thread::sleep(poll);
+            }
+        }
+    }
+
+    /// Turns the libevent base until exit or timeout duration reached. A
+    /// timeout too large for libevent, such as `Duration::MAX`, means none.
     pub fn run_timeout(&self, timeout: Duration) -> ExitReason {
-        if self.loopexit(timeout) != 0 {
+        if base::validate_timeout(timeout).is_err() {
+            return self.run();
+        }
+        if self.loopexit(timeout).is_err() {
             // TODO: This conflates errors, is it ok?
             return ExitReason::Error;
         };
@@ -39,7 +214,7 @@ impl Base {
     /// Turns the libevent base until next active event.
     pub fn run_until_event(&self, timeout: Option<Duration>) -> ExitReason {
         if let Some(timeout) = timeout {
-            if self.loopexit(timeout) != 0 {
+            if self.loopexit(timeout).is_err() {
                 // TODO: This conflates errors, is it ok?
                 return ExitReason::Error;
             }
@@ -51,4 +226,197 @@ impl Base {
     pub fn run(&self) -> ExitReason {
         self.loop_(LoopFlags::empty())
     }
+
+    /// Stops the running loop once the current callback returns, so that
+    /// `run` returns `ExitReason::GotBreak`. Forwards to `loopbreak`.
+    pub fn stop(&self) -> Result<(), LibeventError> {
+        self.loopbreak()
+    }
+
+    /// Stops the running loop after `timeout`, once the callbacks ready by
+    /// then have run, so that `run` returns `ExitReason::GotExit`. Forwards
+    /// to `loopexit`.
+    pub fn stop_after(&self, timeout: Duration) -> Result<(), LibeventError> {
+        self.loopexit(timeout)
+    }
+
+    /// Makes the running loop check for new events once the current callback
+    /// returns, rather than running the remaining ready callbacks first.
+    /// Forwards to `loopcontinue`.
+    pub fn resume(&self) -> Result<(), LibeventError> {
+        self.loopcontinue()
+    }
+
+    /// Turns the libevent base until exit, or until `token` is cancelled, in
+    /// which case `ExitReason::Cancelled` is returned.
+    ///
+    /// The token may be cancelled from any thread; the loop is woken up via
+    /// an internal self-pipe, and breaks once the callbacks of the current
+    /// iteration have run. The self-pipe does not keep the loop alive, so
+    /// `ExitReason::NoPendingEvents` is returned once no other events are
+    /// left.
+    pub fn run_until_cancelled(&mut self, token: &CancellationToken) -> ExitReason {
+        let (rx, tx) = match std::os::unix::net::UnixStream::pair() {
+            Ok(pair) => pair,
+            Err(_) => return ExitReason::Error,
+        };
+        if rx.set_nonblocking(true).is_err() || tx.set_nonblocking(true).is_err() {
+            return ExitReason::Error;
+        }
+
+        // Registering before checking means a concurrent cancel is either
+        // seen here, or wakes the loop through the pipe.
+        let _registration = token.register(tx);
+        if token.is_cancelled() {
+            return ExitReason::Cancelled;
+        }
+
+        let base = unsafe { self.as_raw() };
+        let ev = Event::new(rx.as_raw_fd(), EventFlags::READ, None);
+        let wakeup = match self.spawn_local_internal(ev, move |_ev, _fd, _flags| unsafe {
+            libevent_sys::event_base_loopbreak(base.as_ptr());
+        }) {
+            Ok(ev) => ev,
+            Err(_) => return ExitReason::Error,
+        };
+
+        let wakeup = unsafe { wakeup.as_raw() };
+        match self.loop_detached(LoopFlags::empty(), Some(wakeup)) {
+            ExitReason::GotBreak if token.is_cancelled() => ExitReason::Cancelled,
+            reason => reason,
+        }
+    }
+
+    /// Schedules `cb` to run once, on the next iteration of the loop, e.g.
+    /// to defer work out of the current callback.
+    ///
+    /// This is a zero-timeout `Oneshot`: libevent considers a zero `timeval`
+    /// to have expired by the time it next checks its timers, as opposed to
+    /// a null one, which means no timeout at all. The event is cleaned up
+    /// once `cb` has run. See `set_priority_inheritance` for running it at
+    /// the priority of the current callback's event.
+    pub fn defer(&mut self, cb: impl FnOnce() + 'static) -> std::io::Result<()> {
+        let mut cb = Some(cb);
+
+        self.spawn(Oneshot::new(Duration::ZERO), move |ev| {
+            if let Some(cb) = cb.take() {
+                cb();
+            }
+            // Hand the event back for cleanup once it has fired.
+            let _ = ev.stop();
+        })
+    }
+
+    /// Runs the blocking closure `f` off the loop, on a thread pool sized via
+    /// `set_blocking_threads`, and hands its result to `done` back on the
+    /// loop's thread, so blocking work does not stall other events.
+    ///
+    /// The result is delivered via a self-pipe event, which keeps the loop
+    /// alive until `done` has run. If `f` panics, the panic is contained to
+    /// the pool thread: `done` is never invoked, and the event is removed
+    /// once the loop notices, so it no longer keeps the loop alive.
+    pub fn spawn_blocking<F, T, D>(&mut self, f: F, done: D) -> std::io::Result<()>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+        D: FnOnce(T) + 'static,
+    {
+        use std::io::{Read, Write};
+
+        let (mut rx, tx) = std::os::unix::net::UnixStream::pair()?;
+        rx.set_nonblocking(true)?;
+        let (result_tx, result_rx) = std::sync::mpsc::channel();
+
+        let mut done = Some(done);
+        let ev = Event::new(rx.as_raw_fd(), EventFlags::READ | EventFlags::PERSIST, None);
+        self.spawn(ev, move |ev, _fd, _flags| {
+            // Drain the wakeup; the result itself comes through the channel.
+            let _ = rx.read(&mut [0; 1]);
+
+            match result_rx.try_recv() {
+                Ok(result) => {
+                    if let Some(done) = done.take() {
+                        done(result);
+                    }
+                    let _ = ev.stop();
+                }
+                // `f` panicked, which dropped the sender, and with it the
+                // write end, which now keeps reporting EOF.
+                Err(std::sync::mpsc::TryRecvError::Disconnected) => {
+                    let _ = ev.stop();
+                }
+                Err(std::sync::mpsc::TryRecvError::Empty) => {}
+            }
+        })?;
+
+        let pool = self
+            .blocking_pool
+            .get_or_insert_with(blocking::BlockingPool::with_default_size);
+        pool.execute(move || {
+            let result = f();
+            if result_tx.send(result).is_ok() {
+                let _ = (&tx).write(&[1]);
+            }
+        });
+
+        Ok(())
+    }
+
+    /// Registers a "virtual" event which keeps the loop alive without a real
+    /// fd, so that `run` does not return `ExitReason::NoPendingEvents` while
+    /// the returned handle is held. This is useful when embedding another
+    /// loop which only injects work into this one from time to time.
+    ///
+    /// libevent's own `event_base_add_virtual_` is not part of its public
+    /// API, so this is emulated with a persistent timer whose interval is
+    /// long enough to never matter in practice. The timer is one of the
+    /// crate's helpers, so it stays on the wall clock with
+    /// `enable_virtual_clock`, and `event_count` and `clear_all_events` leave
+    /// it out. Dropping the handle removes the event, after which the loop
+    /// will exit again once it runs dry.
+    ///
+    /// Passing `LoopFlags::NO_EXIT_ON_EMPTY` to `loop_` has a similar effect,
+    /// but only for that one call, and the loop then can only be stopped via
+    /// `loopbreak` or `loopexit`.
+    pub fn add_virtual_event(&mut self) -> std::io::Result<Event<event::Local<Interval>>> {
+        const VIRTUAL_INTERVAL: Duration = Duration::from_secs(60 * 60 * 24 * 365);
+
+        self.spawn_local_internal(Interval::new(VIRTUAL_INTERVAL), |_ev| {})
+    }
+
+    /// Registers `cb` to be invoked with the triggering flags whenever the fd
+    /// of `source`, e.g. a `&TcpStream`, is ready as given by `flags`, e.g.
+    /// `READ | PERSIST`, with no timeout, so the flags never contain
+    /// `TIMEOUT`. See `add_fd_timeout` for an idle timeout.
+    ///
+    /// The event does not borrow `source`, which must stay open for as long
+    /// as the event is active. This takes Unix fds only, since the crate does
+    /// not support Windows and its `SOCKET` handles yet.
+    pub fn add_fd<S: AsRawFd + ?Sized>(
+        &mut self,
+        source: &S,
+        flags: EventFlags,
+        mut cb: impl FnMut(EventFlags) + 'static,
+    ) -> std::io::Result<Event<event::Local<Fd>>> {
+        let ev = Event::from_fd(source, flags, None);
+        self.spawn_local(ev, move |_ev, _fd, flags| cb(flags))
+    }
+
+    /// Like `add_fd`, but also invokes `cb` once the fd of `source` has not
+    /// been ready for `timeout`, e.g. to close idle connections. `cb` tells
+    /// the two apart by `READ` or `WRITE` versus `TIMEOUT` in the flags.
+    ///
+    /// For a persistent event, the timeout is an idle timeout: libevent
+    /// restarts it whenever the event fires, be it due to readiness or the
+    /// timeout itself. A non-persistent event fires once, either way.
+    pub fn add_fd_timeout<S: AsRawFd + ?Sized>(
+        &mut self,
+        source: &S,
+        flags: EventFlags,
+        timeout: Duration,
+        mut cb: impl FnMut(EventFlags) + 'static,
+    ) -> std::io::Result<Event<event::Local<Fd>>> {
+        let ev = Event::from_fd(source, flags, Some(timeout));
+        self.spawn_local(ev, move |_ev, _fd, flags| cb(flags))
+    }
 }
@@ -0,0 +1,124 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io;
+use std::net::SocketAddr;
+use std::os::raw::{c_short, c_void};
+use std::os::unix::io::RawFd;
+use std::rc::Rc;
+
+use crate::bufferevent::buffer;
+use crate::{unwind, Base, BufferEvent, EolStyle, EventFlags, Listener};
+
+/// The closure invoked by a `LineServer` for each received line.
+type LineHandler = Box<dyn FnMut(&str) -> Option<String>>;
+
+/// The state shared between a `LineServer` and its callbacks.
+struct Shared {
+    handler: RefCell<LineHandler>,
+    /// The open connections, by socket.
+    connections: RefCell<HashMap<RawFd, BufferEvent>>,
+}
+
+/// A simple TCP server for line-based protocols, e.g. echo or key-value
+/// services, built on a `Listener` and a `BufferEvent` per connection.
+///
+/// Each line received, without its terminator, is handed to the handler, and
+/// the response it returns, if any, is written back followed by `\n`. Lines
+/// may end in `\n` or `\r\n`. Connections are closed once the peer closes
+/// them or fails, and all of them once the `LineServer` is dropped.
+pub struct LineServer {
+    // Dropped first, so no connections are accepted while tearing down.
+    listener: Listener,
+    shared: Rc<Shared>,
+}
+
+impl LineServer {
+    /// Binds a new listening socket to `addr`, serving connections accepted
+    /// on the given `Base` with `handler`.
+    pub fn bind<F>(base: &mut Base, addr: SocketAddr, handler: F) -> io::Result<Self>
+    where
+        F: FnMut(&str) -> Option<String> + 'static,
+    {
+        let shared = Rc::new(Shared {
+            handler: RefCell::new(Box::new(handler)),
+            connections: RefCell::new(HashMap::new()),
+        });
+
+        let raw_base = unsafe { base.as_raw() };
+        let accepting = Rc::downgrade(&shared);
+        let listener = Listener::bind(base, addr, move |stream, _addr| {
+            let shared = match accepting.upgrade() {
+                Some(shared) => shared,
+                None => return,
+            };
+
+            // Connections which cannot be set up are just dropped.
+            let bev = match unsafe { BufferEvent::with_raw_base(raw_base, stream) } {
+                Ok(bev) => bev,
+                Err(_) => return,
+            };
+
+            unsafe {
+                libevent_sys::bufferevent_setcb(
+                    bev.as_raw().as_ptr(),
+                    Some(handle_read),
+                    None,
+                    Some(handle_event),
+                    Rc::as_ptr(&shared) as *mut c_void,
+                )
+            };
+            if bev.enable(EventFlags::READ | EventFlags::WRITE).is_ok() {
+                shared.connections.borrow_mut().insert(bev.fd(), bev);
+            }
+        })?;
+
+        Ok(LineServer { listener, shared })
+    }
+
+    /// Returns the address of the listening socket.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Returns the number of open connections.
+    pub fn connection_count(&self) -> usize {
+        self.shared.connections.borrow().len()
+    }
+}
+
+/// Read callback of a `LineServer` connection, which handles every complete
+/// line received so far.
+unsafe extern "C" fn handle_read(bev: *mut libevent_sys::bufferevent, ctx: *mut c_void) {
+    let shared = &*(ctx as *const Shared);
+    let mut input = buffer(libevent_sys::bufferevent_get_input(bev));
+    let mut output = buffer(libevent_sys::bufferevent_get_output(bev));
+
+    unwind::catch(libevent_sys::bufferevent_get_base(bev), || {
+        while let Some(line) = input.readln(EolStyle::Crlf) {
+            let response = (shared.handler.borrow_mut())(&line);
+            if let Some(response) = response {
+                let _ = output.add(response.as_bytes());
+                let _ = output.add(b"\n");
+            }
+        }
+    });
+}
+
+/// Event callback of a `LineServer` connection, which closes it on EOF or
+/// errors.
+unsafe extern "C" fn handle_event(
+    bev: *mut libevent_sys::bufferevent,
+    what: c_short,
+    ctx: *mut c_void,
+) {
+    let shared = &*(ctx as *const Shared);
+    let closing = (libevent_sys::BEV_EVENT_EOF | libevent_sys::BEV_EVENT_ERROR) as c_short;
+
+    if what & closing != 0 {
+        unwind::catch(libevent_sys::bufferevent_get_base(bev), || {
+            let fd = libevent_sys::bufferevent_getfd(bev);
+            let bev = shared.connections.borrow_mut().remove(&fd);
+            drop(bev);
+        });
+    }
+}
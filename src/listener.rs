@@ -0,0 +1,554 @@
+use bitflags::bitflags;
+use std::cell::{Cell, RefCell};
+use std::io;
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::os::raw::{c_int, c_uint, c_void};
+use std::os::unix::io::{AsRawFd, FromRawFd, IntoRawFd, RawFd};
+use std::ptr::NonNull;
+#[cfg(target_os = "linux")]
+use std::time::Duration;
+
+use crate::util::{from_raw_socket_addr, raw_socket_addr};
+use crate::{unwind, Base, EvutilSocket};
+
+/// The timeout used by `Listener::set_defer_accept`, after which Linux
+/// accepts a deferred connection even without data.
+#[cfg(target_os = "linux")]
+const DEFAULT_DEFER_ACCEPT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The closure invoked for each connection accepted by a `Listener`.
+type AcceptCallback = Box<dyn FnMut(TcpStream, SocketAddr)>;
+
+/// The closure invoked when accepting a connection fails.
+type ErrorCallback = Box<dyn FnMut(io::Error)>;
+
+/// The context shared by a `Listener`'s callback trampolines.
+struct ListenerCallbacks {
+    accept: RefCell<AcceptCallback>,
+    error: RefCell<Option<ErrorCallback>>,
+    /// The cap set via `Listener::set_max_connections`, if any.
+    max_connections: Cell<Option<usize>>,
+    /// The accepted connections not yet released via
+    /// `Listener::release_connection`.
+    active_connections: Cell<usize>,
+}
+
+impl ListenerCallbacks {
+    fn new(accept: AcceptCallback) -> Box<Self> {
+        Box::new(ListenerCallbacks {
+            accept: RefCell::new(accept),
+            error: RefCell::new(None),
+            max_connections: Cell::new(None),
+            active_connections: Cell::new(0),
+        })
+    }
+}
+
+bitflags! {
+    /// Socket options for `Listener::bind_with_flags`.
+    pub struct ListenerFlags: u32 {
+        /// Sets `SO_REUSEADDR`, so the address can be bound again right after
+        /// the listener is closed.
+        const REUSEABLE = libevent_sys::LEV_OPT_REUSEABLE;
+        /// Sets `SO_REUSEPORT` (Linux only), so that several listeners, e.g.
+        /// on the bases of several threads, can bind the same address, with
+        /// the kernel distributing incoming connections among them.
+        const REUSEABLE_PORT = libevent_sys::LEV_OPT_REUSEABLE_PORT;
+    }
+}
+
+/// Wrapper for libevent's `evconnlistener`, which accepts incoming TCP
+/// connections from within the event loop of a `Base`.
+///
+/// Accepted streams are handed over in non-blocking mode, ready to be used
+/// with other events on the same base.
+pub struct Listener {
+    inner: NonNull<libevent_sys::evconnlistener>,
+    callbacks: Box<ListenerCallbacks>,
+}
+
+impl Listener {
+    /// Binds a new listening socket to `addr`, accepting connections on the
+    /// given `Base` and handing each of them to `cb`.
+    pub fn bind<F>(base: &mut Base, addr: SocketAddr, cb: F) -> io::Result<Self>
+    where
+        F: FnMut(TcpStream, SocketAddr) + 'static,
+    {
+        Self::from_std(base, TcpListener::bind(addr)?, cb)
+    }
+
+    /// Wrapper for libevent's `evconnlistener_new_bind`, which binds a new
+    /// listening socket to `addr` with the given socket options set before
+    /// binding, and otherwise works like `bind`.
+    ///
+    /// For accept scaling across threads, bind one listener per base with
+    /// `ListenerFlags::REUSEABLE_PORT`.
+    pub fn bind_with_flags<F>(
+        base: &mut Base,
+        addr: SocketAddr,
+        flags: ListenerFlags,
+        cb: F,
+    ) -> io::Result<Self>
+    where
+        F: FnMut(TcpStream, SocketAddr) + 'static,
+    {
+        let callbacks = ListenerCallbacks::new(Box::new(cb));
+        let ctx = &*callbacks as *const ListenerCallbacks as *mut c_void;
+        let (sockaddr, socklen) = raw_socket_addr(&addr);
+
+        // A backlog of -1 lets libevent pick a reasonable default.
+        let inner = unsafe {
+            libevent_sys::evconnlistener_new_bind(
+                base.as_raw().as_ptr(),
+                Some(handle_accept),
+                ctx,
+                libevent_sys::LEV_OPT_CLOSE_ON_FREE | flags.bits(),
+                -1,
+                &sockaddr as *const libc::sockaddr_storage as *const libevent_sys::sockaddr,
+                socklen as c_int,
+            )
+        };
+
+        match NonNull::new(inner) {
+            Some(inner) => Ok(Listener { inner, callbacks }),
+            None => Err(io::Error::last_os_error()),
+        }
+    }
+
+    /// Accepts connections on an already-listening socket, taking ownership
+    /// of it.
+    pub fn from_std<F>(base: &mut Base, listener: TcpListener, cb: F) -> io::Result<Self>
+    where
+        F: FnMut(TcpStream, SocketAddr) + 'static,
+    {
+        listener.set_nonblocking(true)?;
+
+        let this = unsafe {
+            Self::from_raw_parts(
+                base,
+                listener.as_raw_fd(),
+                libevent_sys::LEV_OPT_CLOSE_ON_FREE,
+                Box::new(cb),
+            )?
+        };
+
+        // Ownership of the socket now belongs to the `evconnlistener`.
+        let _ = listener.into_raw_fd();
+
+        Ok(this)
+    }
+
+    /// Accepts connections on an already-listening socket, e.g. one passed
+    /// down via systemd socket activation or from a parent process, without
+    /// taking ownership of it.
+    ///
+    /// The socket is switched to non-blocking mode, as libevent requires, but
+    /// is not closed when the `Listener` is dropped. To hand over ownership,
+    /// use `from_std` instead.
+    ///
+    /// # Safety
+    ///
+    /// `fd` must be a valid socket which is already listening, and must stay
+    /// open for as long as the `Listener` lives.
+    pub unsafe fn from_fd<F>(base: &mut Base, fd: RawFd, cb: F) -> io::Result<Self>
+    where
+        F: FnMut(TcpStream, SocketAddr) + 'static,
+    {
+        if libevent_sys::evutil_make_socket_nonblocking(fd) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Self::from_raw_parts(base, fd, 0, Box::new(cb))
+    }
+
+    /// Helper for wrapping an already-listening socket with the given
+    /// `LEV_OPT_*` flags.
+    ///
+    /// # Safety
+    ///
+    /// `fd` must be a valid socket which is already listening.
+    unsafe fn from_raw_parts(
+        base: &mut Base,
+        fd: RawFd,
+        flags: c_uint,
+        cb: AcceptCallback,
+    ) -> io::Result<Self> {
+        let callbacks = ListenerCallbacks::new(cb);
+        let ctx = &*callbacks as *const ListenerCallbacks as *mut c_void;
+
+        // A backlog of 0 tells libevent the socket is already listening.
+        let inner = libevent_sys::evconnlistener_new(
+            base.as_raw().as_ptr(),
+            Some(handle_accept),
+            ctx,
+            flags,
+            0,
+            fd,
+        );
+
+        match NonNull::new(inner) {
+            Some(inner) => Ok(Listener { inner, callbacks }),
+            None => Err(io::Error::new(
+                io::ErrorKind::Other,
+                "Failed to create listener",
+            )),
+        }
+    }
+
+    /// Exposes the raw, non-null `evconnlistener` pointer.
+    ///
+    /// # Safety
+    ///
+    /// This function returns a valid, non-null `evconnlistener` pointer which
+    /// by itself is safe. However, this function serves as an escape hatch to
+    /// do unsafe things.
+    pub unsafe fn as_raw(&self) -> NonNull<libevent_sys::evconnlistener> {
+        self.inner
+    }
+
+    /// Wrapper for libevent's `evconnlistener_enable`, which resumes
+    /// accepting connections after a call to `disable`.
+    pub fn enable(&self) -> io::Result<()> {
+        if unsafe { libevent_sys::evconnlistener_enable(self.inner.as_ptr()) } == 0 {
+            Ok(())
+        } else {
+            Err(io::Error::new(
+                io::ErrorKind::Other,
+                "Failed to enable listener",
+            ))
+        }
+    }
+
+    /// Wrapper for libevent's `evconnlistener_disable`, which temporarily
+    /// stops accepting connections, e.g. to apply backpressure.
+    ///
+    /// While disabled, clients can still connect, but their connections wait
+    /// in the kernel's backlog until the listener is enabled again.
+    pub fn disable(&self) -> io::Result<()> {
+        if unsafe { libevent_sys::evconnlistener_disable(self.inner.as_ptr()) } == 0 {
+            Ok(())
+        } else {
+            Err(io::Error::new(
+                io::ErrorKind::Other,
+                "Failed to disable listener",
+            ))
+        }
+    }
+
+    /// Caps the number of connections open at a time at `n`, to avoid
+    /// running out of fds. Once `n` accepted connections have not been
+    /// released via `release_connection`, the listener is disabled, and it is
+    /// enabled again as soon as one is.
+    ///
+    /// Connections accepted before this call count toward the cap as well.
+    /// Calling `enable` or `disable` by hand overrides the cap until the next
+    /// connection is accepted or released.
+    pub fn set_max_connections(&self, n: usize) {
+        self.callbacks.max_connections.set(Some(n));
+        self.apply_connection_limit();
+    }
+
+    /// Signals that a connection accepted by this listener was closed, for
+    /// `set_max_connections`.
+    pub fn release_connection(&self) {
+        let active = &self.callbacks.active_connections;
+        active.set(active.get().saturating_sub(1));
+        self.apply_connection_limit();
+    }
+
+    /// Enables or disables the listener depending on whether the connection
+    /// cap, if any, is reached.
+    fn apply_connection_limit(&self) {
+        unsafe { apply_connection_limit(self.inner.as_ptr(), &self.callbacks) };
+    }
+
+    /// Returns the listening socket.
+    fn fd(&self) -> RawFd {
+        unsafe { libevent_sys::evconnlistener_get_fd(self.inner.as_ptr()) }
+    }
+
+    /// Returns the address of the listening socket.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        // Borrow the socket as a `TcpListener` without taking ownership.
+        let listener = std::mem::ManuallyDrop::new(unsafe { TcpListener::from_raw_fd(self.fd()) });
+        listener.local_addr()
+    }
+
+    /// Sets `TCP_DEFER_ACCEPT` on the listening socket, so that connections
+    /// are only accepted once the client has actually sent data, rather than
+    /// as soon as the handshake completes. This saves a wakeup for
+    /// connections which turn out to be idle.
+    ///
+    /// Linux still accepts a deferred connection without data once a timeout
+    /// has passed and a few SYN-ACK retransmissions have gone unanswered;
+    /// this uses a timeout of 30 seconds, see `set_defer_accept_timeout` for
+    /// another one. `false` turns deferring off again.
+    #[cfg(target_os = "linux")]
+    pub fn set_defer_accept(&self, enabled: bool) -> io::Result<()> {
+        if enabled {
+            self.set_defer_accept_timeout(DEFAULT_DEFER_ACCEPT_TIMEOUT)
+        } else {
+            self.set_defer_accept_secs(0)
+        }
+    }
+
+    /// Like `set_defer_accept(true)`, but with the given timeout after which
+    /// Linux accepts a connection without data. The option is given in whole
+    /// seconds, so `timeout` is rounded up, to at least one second.
+    #[cfg(target_os = "linux")]
+    pub fn set_defer_accept_timeout(&self, timeout: Duration) -> io::Result<()> {
+        let secs = timeout.as_secs() + u64::from(timeout.subsec_nanos() > 0);
+        self.set_defer_accept_secs(c_int::try_from(secs.max(1)).unwrap_or(c_int::MAX))
+    }
+
+    /// Sets `TCP_DEFER_ACCEPT` to `secs`, where 0 turns it off.
+    #[cfg(target_os = "linux")]
+    fn set_defer_accept_secs(&self, secs: c_int) -> io::Result<()> {
+        let ret = unsafe {
+            libc::setsockopt(
+                self.fd(),
+                libc::IPPROTO_TCP,
+                libc::TCP_DEFER_ACCEPT,
+                &secs as *const c_int as *const c_void,
+                std::mem::size_of::<c_int>() as libc::socklen_t,
+            )
+        };
+
+        if ret == 0 {
+            Ok(())
+        } else {
+            Err(io::Error::last_os_error())
+        }
+    }
+
+    /// Replaces the closure which is handed accepted connections.
+    ///
+    /// Panics if called from within the accept callback itself.
+    fn replace_callback(&self, cb: AcceptCallback) {
+        *self.callbacks.accept.borrow_mut() = cb;
+    }
+
+    /// Sets a closure to be invoked whenever accepting a connection fails,
+    /// e.g. with `EMFILE` once the process runs out of fds. This lets servers
+    /// log the condition or back off, for instance via `disable`.
+    ///
+    /// Without an error callback, libevent only logs such failures as
+    /// warnings. Either way, the listener stays enabled.
+    pub fn set_error_callback(&self, cb: impl FnMut(io::Error) + 'static) {
+        *self.callbacks.error.borrow_mut() = Some(Box::new(cb));
+
+        unsafe {
+            libevent_sys::evconnlistener_set_error_cb(self.inner.as_ptr(), Some(handle_error))
+        };
+    }
+
+    /// Returns a `Stream` of the connections accepted from here on, for use
+    /// from async code, possibly on another thread than the event loop.
+    ///
+    /// This replaces the accept callback given at construction. Dropping the
+    /// `Incoming` stream on the loop's thread disables the listener right
+    /// away, leaving further connections in the kernel backlog. If it is
+    /// dropped on another thread, the listener is only disabled once the
+    /// next connection is accepted, which is then closed.
+    #[cfg(feature = "stream")]
+    pub fn incoming(&self) -> Incoming {
+        let (tx, rx) = std::sync::mpsc::channel();
+        let shared = IncomingShared::default();
+        shared.lock().unwrap().listener = Some(LoopListener::new(self.inner));
+
+        let sender = IncomingSender {
+            tx,
+            shared: shared.clone(),
+        };
+        let inner = self.inner;
+
+        self.replace_callback(Box::new(move |stream, addr| {
+            if sender.tx.send(Ok((stream, addr))).is_err() {
+                // The `Incoming` end is gone, so stop accepting.
+                unsafe { libevent_sys::evconnlistener_disable(inner.as_ptr()) };
+                return;
+            }
+            sender.wake();
+        }));
+
+        Incoming { rx, shared }
+    }
+}
+
+impl Drop for Listener {
+    fn drop(&mut self) {
+        unsafe { libevent_sys::evconnlistener_free(self.inner.as_ptr()) };
+    }
+}
+
+/// Acts as a C-compatible trampoline for the listener's accept closure.
+extern "C" fn handle_accept(
+    listener: *mut libevent_sys::evconnlistener,
+    fd: EvutilSocket,
+    addr: *mut libevent_sys::sockaddr,
+    socklen: c_int,
+    ctx: *mut c_void,
+) {
+    let callbacks = unsafe { &*(ctx as *const ListenerCallbacks) };
+    let stream = unsafe { TcpStream::from_raw_fd(fd) };
+
+    // libevent hands over the peer address as returned by `accept`, which
+    // spares a `getpeername` call.
+    let addr = unsafe { from_raw_socket_addr(addr as *const libc::sockaddr, socklen as _) };
+
+    // Connections which have already gone away by now are just dropped.
+    if let Some(addr) = addr.or_else(|| stream.peer_addr().ok()) {
+        let active = &callbacks.active_connections;
+        active.set(active.get() + 1);
+        unsafe { apply_connection_limit(listener, callbacks) };
+
+        let base = unsafe { libevent_sys::evconnlistener_get_base(listener) };
+        unwind::catch(base, || (callbacks.accept.borrow_mut())(stream, addr));
+    }
+}
+
+/// Enables or disables `listener` depending on whether the connection cap
+/// in `callbacks`, if any, is reached.
+///
+/// # Safety
+///
+/// `listener` must be a valid `evconnlistener`.
+unsafe fn apply_connection_limit(
+    listener: *mut libevent_sys::evconnlistener,
+    callbacks: &ListenerCallbacks,
+) {
+    if let Some(max) = callbacks.max_connections.get() {
+        if callbacks.active_connections.get() >= max {
+            libevent_sys::evconnlistener_disable(listener);
+        } else {
+            libevent_sys::evconnlistener_enable(listener);
+        }
+    }
+}
+
+/// Acts as a C-compatible trampoline for the listener's error closure.
+extern "C" fn handle_error(listener: *mut libevent_sys::evconnlistener, ctx: *mut c_void) {
+    // Grab the error first, before anything else can clobber it.
+    let error = io::Error::last_os_error();
+    let callbacks = unsafe { &*(ctx as *const ListenerCallbacks) };
+
+    if let Some(cb) = callbacks.error.borrow_mut().as_mut() {
+        let base = unsafe { libevent_sys::evconnlistener_get_base(listener) };
+        unwind::catch(base, || cb(error));
+    }
+}
+
+/// The state shared between an `Incoming` stream and the accept callback
+/// feeding it.
+#[cfg(feature = "stream")]
+#[derive(Default)]
+struct IncomingState {
+    /// The waker of the task polling `Incoming`.
+    waker: Option<std::task::Waker>,
+    /// The `evconnlistener` feeding the stream, for as long as its accept
+    /// callback does.
+    listener: Option<LoopListener>,
+}
+
+/// An `evconnlistener` pointer which is only handed out on the thread it was
+/// created on, i.e. the loop's, so that an `Incoming` stream holding it can
+/// still move to other threads.
+#[cfg(feature = "stream")]
+struct LoopListener {
+    inner: NonNull<libevent_sys::evconnlistener>,
+    thread: std::thread::ThreadId,
+}
+
+// The pointer is never handed out on another thread than the loop's.
+#[cfg(feature = "stream")]
+unsafe impl Send for LoopListener {}
+
+#[cfg(feature = "stream")]
+impl LoopListener {
+    fn new(inner: NonNull<libevent_sys::evconnlistener>) -> Self {
+        LoopListener {
+            inner,
+            thread: std::thread::current().id(),
+        }
+    }
+
+    /// Returns the listener if called on the loop's thread.
+    fn get(&self) -> Option<NonNull<libevent_sys::evconnlistener>> {
+        (std::thread::current().id() == self.thread).then(|| self.inner)
+    }
+}
+
+/// Type of the state shared between an `Incoming` stream and its sender.
+#[cfg(feature = "stream")]
+type IncomingShared = std::sync::Arc<std::sync::Mutex<IncomingState>>;
+
+/// The accept callback's end of an `Incoming` stream, which wakes the
+/// polling task for every connection, as well as when the listener is gone.
+#[cfg(feature = "stream")]
+struct IncomingSender {
+    tx: std::sync::mpsc::Sender<io::Result<(TcpStream, SocketAddr)>>,
+    shared: IncomingShared,
+}
+
+#[cfg(feature = "stream")]
+impl IncomingSender {
+    fn wake(&self) {
+        if let Some(waker) = self.shared.lock().unwrap().waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+#[cfg(feature = "stream")]
+impl Drop for IncomingSender {
+    fn drop(&mut self) {
+        self.shared.lock().unwrap().listener = None;
+        self.wake();
+    }
+}
+
+/// A `Stream` of connections accepted by a `Listener`, created with
+/// `Listener::incoming`.
+///
+/// The stream ends once the `Listener` is dropped.
+#[cfg(feature = "stream")]
+pub struct Incoming {
+    rx: std::sync::mpsc::Receiver<io::Result<(TcpStream, SocketAddr)>>,
+    shared: IncomingShared,
+}
+
+#[cfg(feature = "stream")]
+impl futures_core::Stream for Incoming {
+    type Item = io::Result<(TcpStream, SocketAddr)>;
+
+    fn poll_next(
+        self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Option<Self::Item>> {
+        use std::sync::mpsc::TryRecvError;
+        use std::task::Poll;
+
+        // Register before checking, so no wakeup can fall in between.
+        self.shared.lock().unwrap().waker = Some(cx.waker().clone());
+
+        match self.rx.try_recv() {
+            Ok(conn) => Poll::Ready(Some(conn)),
+            Err(TryRecvError::Empty) => Poll::Pending,
+            Err(TryRecvError::Disconnected) => Poll::Ready(None),
+        }
+    }
+}
+
+#[cfg(feature = "stream")]
+impl Drop for Incoming {
+    fn drop(&mut self) {
+        // The listener may be gone already, or feed another stream by now.
+        // libevent is not thread-safe by default, so on another thread than
+        // the loop's, the accept callback has to disable the listener once it
+        // finds the stream gone.
+        let state = self.shared.lock().unwrap();
+        if let Some(listener) = state.listener.as_ref().and_then(LoopListener::get) {
+            unsafe { libevent_sys::evconnlistener_disable(listener.as_ptr()) };
+        }
+    }
+}
@@ -0,0 +1,224 @@
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::os::unix::net::UnixStream;
+use std::ptr::NonNull;
+use std::sync::{Arc, Mutex};
+
+use crate::base::free_spawned;
+use crate::event::Local;
+use crate::{Base, Event, EventFlags, Fd};
+
+/// The kernel mechanism through which a `Notifier` wakes up the loop.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NotifyMechanism {
+    /// An `eventfd`, which needs a single fd. Only available on Linux.
+    EventFd,
+    /// A connected pair of Unix sockets, used as a self-pipe.
+    SocketPair,
+}
+
+/// The writing end of the notification mechanism.
+#[derive(Clone, Debug)]
+enum Waker {
+    EventFd(Arc<File>),
+    SocketPair(Arc<UnixStream>),
+}
+
+/// The reading end of the notification mechanism, owned by the callback of
+/// the notification event.
+enum Wakeups {
+    EventFd(Arc<File>),
+    SocketPair(UnixStream),
+}
+
+impl Wakeups {
+    fn fd(&self) -> RawFd {
+        match self {
+            Wakeups::EventFd(file) => file.as_raw_fd(),
+            Wakeups::SocketPair(rx) => rx.as_raw_fd(),
+        }
+    }
+
+    /// Consumes all pending wakeups, coalescing them into one.
+    fn drain(&mut self) {
+        match self {
+            Wakeups::EventFd(file) => {
+                // Reading resets the counter.
+                let mut buf = [0u8; 8];
+                let _ = (&**file).read(&mut buf);
+            }
+            Wakeups::SocketPair(rx) => {
+                let mut buf = [0u8; 64];
+                while let Ok(n) = rx.read(&mut buf) {
+                    if n < buf.len() {
+                        break;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Creates the two ends of a notification mechanism, preferring an `eventfd`
+/// where available.
+fn wakeup_channel() -> io::Result<(Notifier, Wakeups)> {
+    if let Some(file) = new_eventfd() {
+        let file = Arc::new(file);
+        let waker = Waker::EventFd(file.clone());
+        return Ok((Notifier { waker }, Wakeups::EventFd(file)));
+    }
+
+    let (rx, tx) = UnixStream::pair()?;
+    rx.set_nonblocking(true)?;
+    tx.set_nonblocking(true)?;
+
+    let waker = Waker::SocketPair(Arc::new(tx));
+    Ok((Notifier { waker }, Wakeups::SocketPair(rx)))
+}
+
+/// A cloneable, thread-safe handle which wakes up a `Base`'s loop to run the
+/// callback given to `Base::notifier`, e.g. to process a queue fed by other
+/// threads.
+///
+/// Notifications sent before the loop gets around to the callback are
+/// coalesced, so the callback may run fewer times than `notify` was called,
+/// but always at least once after the last call.
+#[derive(Clone, Debug)]
+pub struct Notifier {
+    waker: Waker,
+}
+
+impl Notifier {
+    /// Wakes up the loop to run the notifier's callback.
+    pub fn notify(&self) {
+        // A full pipe or a saturated counter already has a wakeup pending,
+        // so errors are moot.
+        let _ = match &self.waker {
+            Waker::EventFd(file) => (&**file).write(&1u64.to_ne_bytes()),
+            Waker::SocketPair(tx) => (&**tx).write(&[1]),
+        };
+    }
+
+    /// Returns the mechanism chosen for this notifier, for debugging.
+    pub fn mechanism(&self) -> NotifyMechanism {
+        match self.waker {
+            Waker::EventFd(_) => NotifyMechanism::EventFd,
+            Waker::SocketPair(_) => NotifyMechanism::SocketPair,
+        }
+    }
+}
+
+/// The closures scheduled via a `RemoteHandle`, in order.
+type Queue = Arc<Mutex<Vec<Box<dyn FnOnce() + Send>>>>;
+
+/// A cloneable, thread-safe handle for scheduling work on a `Base`'s loop
+/// without owning the `Base`, as returned by `Base::remote`, e.g. to hand to
+/// another library.
+#[derive(Clone)]
+pub struct RemoteHandle {
+    queue: Queue,
+    notifier: Notifier,
+}
+
+impl RemoteHandle {
+    /// Enqueues `cb` to be run on the loop's thread, and wakes up the loop.
+    /// Closures run in the order they were scheduled.
+    pub fn schedule(&self, cb: impl FnOnce() + Send + 'static) {
+        self.queue.lock().unwrap().push(Box::new(cb));
+        self.notifier.notify();
+    }
+}
+
+impl std::fmt::Debug for RemoteHandle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RemoteHandle")
+            .field("notifier", &self.notifier)
+            .finish()
+    }
+}
+
+/// The state behind `Base::remote`, which is shared by all handles.
+pub(crate) struct Remote {
+    handle: RemoteHandle,
+    event: NonNull<libevent_sys::event>,
+}
+
+impl Drop for Remote {
+    fn drop(&mut self) {
+        // The event is only freed here, and never from within its callback.
+        unsafe { free_spawned(self.event) };
+    }
+}
+
+/// Creates a non-blocking `eventfd`, if the platform supports it.
+#[cfg(target_os = "linux")]
+fn new_eventfd() -> Option<File> {
+    use std::os::unix::io::FromRawFd;
+
+    let fd = unsafe { libc::eventfd(0, libc::EFD_NONBLOCK | libc::EFD_CLOEXEC) };
+    if fd < 0 {
+        // E.g. `ENOSYS` on ancient kernels; fall back to a socket pair.
+        return None;
+    }
+    Some(unsafe { File::from_raw_fd(fd) })
+}
+
+#[cfg(not(target_os = "linux"))]
+fn new_eventfd() -> Option<File> {
+    None
+}
+
+impl Base {
+    /// Creates a `Notifier` which runs `cb` on the loop's thread whenever it
+    /// is notified from any thread. This uses an `eventfd` where available,
+    /// and an internal self-pipe otherwise.
+    ///
+    /// The returned event handle keeps the notification event registered;
+    /// once it is dropped, notifications go nowhere.
+    pub fn notifier(
+        &mut self,
+        mut cb: impl FnMut() + 'static,
+    ) -> io::Result<(Notifier, Event<Local<Fd>>)> {
+        let (notifier, mut wakeups) = wakeup_channel()?;
+        let ev = Event::new(wakeups.fd(), EventFlags::READ | EventFlags::PERSIST, None);
+
+        let handle = self.spawn_local(ev, move |_ev, _fd, _flags| {
+            wakeups.drain();
+            cb();
+        })?;
+
+        Ok((notifier, handle))
+    }
+
+    /// Returns a `RemoteHandle` for scheduling closures on this loop from any
+    /// thread. The first call registers an internal notifier, which all
+    /// handles share and which stays registered for the `Base`'s lifetime.
+    pub fn remote(&mut self) -> io::Result<RemoteHandle> {
+        if let Some(remote) = &self.remote {
+            return Ok(remote.handle.clone());
+        }
+
+        let queue = Queue::default();
+        let pending = queue.clone();
+        let (notifier, mut wakeups) = wakeup_channel()?;
+        let ev = Event::new(wakeups.fd(), EventFlags::READ | EventFlags::PERSIST, None);
+
+        let event = self.spawn_helper(ev, move |_ev, _fd, _flags| {
+            wakeups.drain();
+            // Take the queue first, so that closures may schedule more.
+            let scheduled = std::mem::take(&mut *pending.lock().unwrap());
+            for cb in scheduled {
+                cb();
+            }
+        })?;
+
+        let handle = RemoteHandle { queue, notifier };
+        self.remote = Some(Remote {
+            handle: handle.clone(),
+            event,
+        });
+
+        Ok(handle)
+    }
+}
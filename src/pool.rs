@@ -0,0 +1,71 @@
+use std::io;
+
+use crate::event::{Exec, Inactive, Local, LocalWeak};
+use crate::{Base, Event};
+
+/// Owns a fixed set of independent `Base`s, for sharding events across
+/// several event loops (e.g. one per core).
+pub struct BasePool {
+    bases: Vec<Base>,
+}
+
+impl BasePool {
+    /// Creates a new pool of `size` bases.
+    pub fn new(size: usize) -> io::Result<Self> {
+        if size == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Base pool must not be empty",
+            ));
+        }
+
+        let bases = (0..size)
+            .map(|_| Base::new())
+            .collect::<io::Result<Vec<_>>>()?;
+
+        Ok(BasePool { bases })
+    }
+
+    /// Returns the number of bases in the pool.
+    pub fn len(&self) -> usize {
+        self.bases.len()
+    }
+
+    /// Always `false`, as a pool holds at least one base.
+    pub fn is_empty(&self) -> bool {
+        self.bases.is_empty()
+    }
+
+    /// Exposes the pooled bases, e.g. to run each of them.
+    pub fn bases(&self) -> &[Base] {
+        &self.bases
+    }
+
+    /// Exposes the pooled bases mutably, e.g. to spawn on a specific one.
+    pub fn bases_mut(&mut self) -> &mut [Base] {
+        &mut self.bases
+    }
+
+    /// Gives up the pool, e.g. to move each base onto its own thread.
+    pub fn into_bases(self) -> Vec<Base> {
+        self.bases
+    }
+
+    /// Returns the base with the fewest events added, per `Base::event_count`.
+    pub fn least_loaded(&mut self) -> &mut Base {
+        self.bases
+            .iter_mut()
+            .min_by_key(|base| base.event_count())
+            .expect("Base pool is never empty")
+    }
+
+    /// Activates a given inactive `Event` on the least-loaded base, in the
+    /// manner of `Base::spawn_local`.
+    pub fn spawn_local_least_loaded<T: Exec<LocalWeak<T>, F>, F>(
+        &mut self,
+        ev: Event<Inactive<T>>,
+        cb: F,
+    ) -> io::Result<Event<Local<T>>> {
+        self.least_loaded().spawn_local(ev, cb)
+    }
+}
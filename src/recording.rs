@@ -0,0 +1,102 @@
+use std::io;
+use std::os::unix::io::RawFd;
+use std::ptr::NonNull;
+use std::time::Instant;
+
+use crate::dispatch::lock;
+use crate::{AsRawEvent, Base, EventFlags};
+
+/// A single activation of an event, as logged by `RecordingBase`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Activation {
+    /// The raw `event` which was activated, to tell apart events without an
+    /// fd, such as timers.
+    pub event: NonNull<libevent_sys::event>,
+    /// The fd passed to the callback, or -1 for timers.
+    pub fd: RawFd,
+    /// The flags the event was activated with.
+    pub flags: EventFlags,
+    /// When the event's callback was invoked.
+    pub time: Instant,
+}
+
+// The event is only identified by its address here, never dereferenced.
+unsafe impl Send for Activation {}
+
+impl Activation {
+    /// Returns whether this is an activation of `event`.
+    pub fn is(&self, event: &dyn AsRawEvent) -> bool {
+        self.event == unsafe { event.as_raw() }
+    }
+}
+
+/// A `Base` which logs every event activation dispatched by it, so tests of
+/// event-driven code can check the order and timing of callbacks.
+///
+/// Logging only applies to events spawned via `Base::spawn*`. The log is
+/// kept by the base itself, so other bases do not pay for it.
+pub struct RecordingBase {
+    base: Base,
+}
+
+impl RecordingBase {
+    /// Creates a new `Base` which starts out recording.
+    pub fn new() -> io::Result<Self> {
+        let base = Base::new()?;
+        *lock(&base.dispatch.log) = Some(Vec::new());
+        base.dispatch.update_instrumented();
+
+        Ok(RecordingBase { base })
+    }
+
+    /// Exposes the recording base, e.g. to spawn events on or run it.
+    pub fn base(&self) -> &Base {
+        &self.base
+    }
+
+    /// Exposes the recording base mutably.
+    pub fn base_mut(&mut self) -> &mut Base {
+        &mut self.base
+    }
+
+    /// Returns the activations recorded so far, in the order they were
+    /// dispatched.
+    pub fn activations(&self) -> Vec<Activation> {
+        lock(&self.base.dispatch.log).clone().unwrap_or_default()
+    }
+
+    /// Discards the activations recorded so far.
+    pub fn clear(&self) {
+        if let Some(log) = lock(&self.base.dispatch.log).as_mut() {
+            log.clear();
+        }
+    }
+
+    /// Asserts that the recorded activations are exactly those of `expected`,
+    /// in order.
+    ///
+    /// Panics with the index of the first mismatch otherwise.
+    pub fn assert_order(&self, expected: &[&dyn AsRawEvent]) {
+        let log = self.activations();
+
+        for (i, activation) in log.iter().enumerate() {
+            match expected.get(i) {
+                Some(event) => assert!(
+                    activation.is(*event),
+                    "activation {} is of an unexpected event: {:?}",
+                    i,
+                    activation
+                ),
+                None => panic!("unexpected activation {}: {:?}", i, activation),
+            }
+        }
+
+        assert_eq!(
+            log.len(),
+            expected.len(),
+            "expected {} activations, recorded {}",
+            expected.len(),
+            log.len()
+        );
+    }
+}
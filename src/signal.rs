@@ -0,0 +1,85 @@
+use std::io;
+use std::os::raw::c_int;
+use std::ptr::NonNull;
+use std::sync::{Arc, Mutex};
+
+use crate::base::free_spawned;
+use crate::dispatch::lock;
+use crate::{Base, Event, EventFlags};
+
+type Handler = Box<dyn FnMut() + Send>;
+
+/// The handlers subscribed to one signal, shared with its event's callback.
+type Handlers = Arc<Mutex<Vec<Handler>>>;
+
+/// The handlers of a signal taken out to be run without holding their lock,
+/// so that they may register further handlers. They are put back once run,
+/// ahead of any registered meanwhile, and also if one of them panics.
+struct Running<'a> {
+    handlers: &'a Mutex<Vec<Handler>>,
+    taken: Vec<Handler>,
+}
+
+impl Drop for Running<'_> {
+    fn drop(&mut self) {
+        let mut handlers = lock(self.handlers);
+        let added = std::mem::replace(&mut *handlers, std::mem::take(&mut self.taken));
+        handlers.extend(added);
+    }
+}
+
+/// The single libevent signal event of a signal, fanning out to all handlers
+/// registered for it via `Base::add_signal`.
+pub(crate) struct SignalDispatch {
+    event: NonNull<libevent_sys::event>,
+    handlers: Handlers,
+}
+
+impl Drop for SignalDispatch {
+    fn drop(&mut self) {
+        // The event is only freed here, and never from within its callback.
+        unsafe { free_spawned(self.event) };
+    }
+}
+
+impl Base {
+    /// Registers `cb` to be run on the loop's thread whenever the signal
+    /// `signum` is raised, e.g. `libc::SIGUSR1`.
+    ///
+    /// Every signal is backed by a single libevent signal event per base, no
+    /// matter how many handlers are registered for it; they are run in
+    /// registration order, and stay registered for the `Base`'s lifetime.
+    /// Once the `Base` is dropped, the signal's previous disposition is
+    /// restored. Note that libevent only lets one base at a time handle
+    /// signals. Handlers must be `Send`, like the `Base` itself. Those
+    /// registered from within a handler run from the next signal on.
+    pub fn add_signal(
+        &mut self,
+        signum: c_int,
+        cb: impl FnMut() + Send + 'static,
+    ) -> io::Result<()> {
+        if let Some(dispatch) = self.signals.get(&signum) {
+            lock(&dispatch.handlers).push(Box::new(cb));
+            return Ok(());
+        }
+
+        let handlers: Handlers = Arc::new(Mutex::new(vec![Box::new(cb)]));
+        let ev = Event::new(signum, EventFlags::SIGNAL | EventFlags::PERSIST, None);
+
+        let dispatch_handlers = handlers.clone();
+        let event = self.spawn_helper(ev, move |_ev, _signum, _flags| {
+            let mut running = Running {
+                handlers: &dispatch_handlers,
+                taken: std::mem::take(&mut *lock(&dispatch_handlers)),
+            };
+            for handler in running.taken.iter_mut() {
+                handler();
+            }
+        })?;
+
+        self.signals
+            .insert(signum, SignalDispatch { event, handlers });
+
+        Ok(())
+    }
+}
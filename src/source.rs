@@ -0,0 +1,50 @@
+use bitflags::bitflags;
+use std::io;
+use std::os::unix::io::AsRawFd;
+
+use crate::event::Local;
+use crate::{Base, Event, EventFlags, Fd};
+
+bitflags! {
+    /// The readiness a source registered via `Base::register_source` is
+    /// interested in, akin to mio's `Interest`.
+    pub struct Interest: u32 {
+        const READABLE = libevent_sys::EV_READ;
+        const WRITABLE = libevent_sys::EV_WRITE;
+    }
+}
+
+impl From<Interest> for EventFlags {
+    fn from(interest: Interest) -> Self {
+        EventFlags::from_bits_truncate(interest.bits())
+    }
+}
+
+impl Base {
+    /// Registers `source` for the readiness given by `interest`, invoking
+    /// `cb` with `token` and the triggering flags whenever it is ready, to
+    /// ease porting code written against mio's `Poll`.
+    ///
+    /// Unlike with mio, readiness is level-triggered, and the source is
+    /// deregistered by dropping the returned event handle rather than via
+    /// the token. The source must outlive the registration.
+    pub fn register_source<S: AsRawFd>(
+        &mut self,
+        source: &S,
+        interest: Interest,
+        token: u64,
+        mut cb: impl FnMut(u64, EventFlags) + 'static,
+    ) -> io::Result<Event<Local<Fd>>> {
+        if interest.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Interest must not be empty",
+            ));
+        }
+
+        let flags = EventFlags::from(interest) | EventFlags::PERSIST;
+        let ev = Event::new(source.as_raw_fd(), flags, None);
+
+        self.spawn_local(ev, move |_ev, _fd, flags| cb(token, flags))
+    }
+}
@@ -0,0 +1,67 @@
+use std::cell::{Ref, RefCell, RefMut};
+use std::io;
+use std::rc::Rc;
+use std::time::Duration;
+
+use crate::event::Local;
+use crate::{Base, Event, Interval};
+
+/// A shared handle to the state of an interval added via
+/// `Base::add_stateful_interval`, which lets the owner inspect or modify the
+/// state between fires.
+pub struct StateRef<S>(Rc<RefCell<S>>);
+
+impl<S> StateRef<S> {
+    /// Borrows the state.
+    ///
+    /// Panics if the state is mutably borrowed, e.g. when called from within
+    /// the interval's callback.
+    pub fn borrow(&self) -> Ref<'_, S> {
+        self.0.borrow()
+    }
+
+    /// Mutably borrows the state.
+    ///
+    /// Panics if the state is borrowed, e.g. when called from within the
+    /// interval's callback.
+    pub fn borrow_mut(&self) -> RefMut<'_, S> {
+        self.0.borrow_mut()
+    }
+}
+
+impl<S> Clone for StateRef<S> {
+    fn clone(&self) -> Self {
+        StateRef(self.0.clone())
+    }
+}
+
+impl<S: std::fmt::Debug> std::fmt::Debug for StateRef<S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("StateRef").field(&self.0).finish()
+    }
+}
+
+impl Base {
+    /// Adds an interval timer whose callback is handed `state` mutably on
+    /// every fire, e.g. to accumulate counters in a periodic task, and
+    /// returns a `StateRef` through which the owner can inspect it between
+    /// fires.
+    ///
+    /// Dropping the returned event handle removes the timer; the state lives
+    /// on for as long as a `StateRef` to it does.
+    pub fn add_stateful_interval<S: 'static>(
+        &mut self,
+        interval: Duration,
+        state: S,
+        mut cb: impl FnMut(&mut S) + 'static,
+    ) -> io::Result<(Event<Local<Interval>>, StateRef<S>)> {
+        let state = StateRef(Rc::new(RefCell::new(state)));
+        let shared = state.clone();
+
+        let event = self.spawn_local(Interval::new(interval), move |_ev| {
+            cb(&mut shared.borrow_mut());
+        })?;
+
+        Ok((event, state))
+    }
+}
@@ -0,0 +1,96 @@
+use std::io;
+use std::os::raw::{c_int, c_short, c_void};
+use std::ptr::NonNull;
+
+use crate::dispatch::lock;
+use crate::Base;
+
+impl Base {
+    /// Pauses all I/O processing on the base, e.g. for a maintenance window,
+    /// by removing every pending fd event created via `Base::spawn*` or added
+    /// via `Base::event_add`, while timers keep running. Use `resume_io` to
+    /// add the events again; raw events must not be freed in between.
+    ///
+    /// This includes the fd events behind a `notifier`, but not those of
+    /// listeners, bufferevents, `remote` or libevent itself.
+    pub fn suspend_io(&mut self) -> io::Result<()> {
+        extern "C" fn collect(
+            _base: *const libevent_sys::event_base,
+            event: *const libevent_sys::event,
+            ctx: *mut c_void,
+        ) -> c_int {
+            let events = unsafe { &mut *(ctx as *mut Vec<*mut libevent_sys::event>) };
+            events.push(event as *mut libevent_sys::event);
+            0
+        }
+
+        // The events cannot be removed while libevent iterates over them.
+        let mut events: Vec<*mut libevent_sys::event> = Vec::new();
+        unsafe {
+            libevent_sys::event_base_foreach_event(
+                self.as_raw().as_ptr(),
+                Some(collect),
+                &mut events as *mut _ as *mut c_void,
+            )
+        };
+
+        let io_flags =
+            (libevent_sys::EV_READ | libevent_sys::EV_WRITE | libevent_sys::EV_CLOSED) as c_short;
+        let live: Vec<usize> = events.iter().map(|event| *event as usize).collect();
+        self.dispatch.prune_raw(&live);
+
+        for event in events {
+            let key = event as usize;
+            // Helpers like `remote` must keep working while suspended.
+            let is_user = NonNull::new(event)
+                .and_then(|event| self.dispatch.registration(event))
+                .map_or(false, |registration| !registration.internal);
+            let is_io = unsafe {
+                libevent_sys::event_get_events(event) & io_flags != 0
+                    && libevent_sys::event_pending(event, io_flags, std::ptr::null_mut()) != 0
+            };
+            if !is_user || !is_io {
+                continue;
+            }
+
+            if unsafe { libevent_sys::event_del(event) } != 0 {
+                return Err(io::Error::new(
+                    io::ErrorKind::Other,
+                    "Failed to suspend event",
+                ));
+            }
+            lock(&self.dispatch.suspended).push(key);
+        }
+
+        Ok(())
+    }
+
+    /// Adds the events removed by `suspend_io` again. Events which had a
+    /// timeout start it over.
+    ///
+    /// Every event is tried, even after a failure; those which fail stay
+    /// suspended for another call, and the first error is returned.
+    pub fn resume_io(&mut self) -> io::Result<()> {
+        let events = std::mem::take(&mut *lock(&self.dispatch.suspended));
+        let mut failed = Vec::new();
+        let mut result = Ok(());
+
+        for key in events {
+            let event = match NonNull::new(key as *mut libevent_sys::event) {
+                Some(event) => event,
+                None => continue,
+            };
+            if let Some(registration) = self.dispatch.registration(event) {
+                if let Err(e) = self.event_add(event, registration.timeout) {
+                    failed.push(key);
+                    if result.is_ok() {
+                        result = Err(e.into());
+                    }
+                }
+            }
+        }
+
+        lock(&self.dispatch.suspended).extend(failed);
+        result
+    }
+}
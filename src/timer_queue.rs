@@ -0,0 +1,187 @@
+use std::cell::{Cell, RefCell};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::io;
+use std::os::raw::c_short;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use crate::base::{to_timeval, validate_timeout, EventCallbackCtx, EvutilSocket};
+use crate::{unwind, Base};
+
+/// Identifies a timer of a `TimerQueue`, e.g. to cancel it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TimerId(u64);
+
+/// The state of a `TimerQueue`, shared with its event's callback.
+struct Shared {
+    /// The single libevent timer, which is owned by the queue.
+    event: Cell<*mut libevent_sys::event>,
+    timers: RefCell<Timers>,
+}
+
+/// The pending timers of a `TimerQueue`.
+#[derive(Default)]
+struct Timers {
+    /// The deadlines of the pending timers, soonest first. Cancelled timers
+    /// are only dropped from here once they come up.
+    deadlines: BinaryHeap<Reverse<(Instant, u64)>>,
+    callbacks: HashMap<u64, Box<dyn FnOnce()>>,
+    next_id: u64,
+}
+
+/// A queue of oneshot timers ordered by deadline, which keeps only a single
+/// libevent timer armed, for the soonest deadline. This scales better than
+/// one libevent timer each for thousands of timers.
+///
+/// Timers with equal deadlines fire in the order they were added. Pending
+/// timers are dropped along with the queue.
+pub struct TimerQueue {
+    shared: Rc<Shared>,
+}
+
+impl TimerQueue {
+    /// Creates an empty `TimerQueue` on `base`, which must outlive it.
+    pub fn new(base: &mut Base) -> io::Result<Self> {
+        let shared = Rc::new(Shared {
+            event: Cell::new(std::ptr::null_mut()),
+            timers: Default::default(),
+        });
+        // The event holds a reference of its own, released once freed.
+        let ctx = Rc::into_raw(shared.clone()) as EventCallbackCtx;
+
+        let event = unsafe {
+            libevent_sys::event_new(base.as_raw().as_ptr(), -1, 0, Some(handle_timeout), ctx)
+        };
+        if event.is_null() {
+            drop(unsafe { Rc::from_raw(ctx as *const Shared) });
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                "Failed to allocate event",
+            ));
+        }
+
+        shared.event.set(event);
+        Ok(TimerQueue { shared })
+    }
+
+    /// Adds a timer which invokes `cb` once `delay` has passed. A zero
+    /// `delay` fires on the next iteration of the loop.
+    pub fn add(&self, delay: Duration, cb: impl FnOnce() + 'static) -> io::Result<TimerId> {
+        let delay = validate_timeout(delay)?;
+        let deadline = Instant::now().checked_add(delay).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "Timeout too large for Instant")
+        })?;
+        let id = {
+            let mut timers = self.shared.timers.borrow_mut();
+            let id = timers.next_id;
+            timers.next_id += 1;
+            timers.deadlines.push(Reverse((deadline, id)));
+            timers.callbacks.insert(id, Box::new(cb));
+            id
+        };
+
+        self.shared.arm()?;
+        Ok(TimerId(id))
+    }
+
+    /// Cancels the timer `id`, returning whether it was still pending.
+    pub fn cancel(&self, id: TimerId) -> bool {
+        self.shared
+            .timers
+            .borrow_mut()
+            .callbacks
+            .remove(&id.0)
+            .is_some()
+    }
+
+    /// Returns the number of pending timers.
+    pub fn len(&self) -> usize {
+        self.shared.timers.borrow().callbacks.len()
+    }
+
+    /// Returns whether no timers are pending.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Shared {
+    /// Arms the event for the soonest pending deadline, if any, dropping the
+    /// deadlines of cancelled timers along the way.
+    fn arm(&self) -> io::Result<()> {
+        let event = self.event.get();
+        // The queue was dropped by one of the callbacks.
+        if event.is_null() {
+            return Ok(());
+        }
+        let mut timers = self.timers.borrow_mut();
+
+        while let Some(Reverse((deadline, id))) = timers.deadlines.peek().copied() {
+            if !timers.callbacks.contains_key(&id) {
+                timers.deadlines.pop();
+                continue;
+            }
+
+            // Re-adding a pending event just moves its timeout.
+            let timeout = deadline.saturating_duration_since(Instant::now());
+            if unsafe { libevent_sys::event_add(event, &to_timeval(timeout)) } != 0 {
+                return Err(io::Error::new(io::ErrorKind::Other, "Failed to add event"));
+            }
+            return Ok(());
+        }
+
+        unsafe { libevent_sys::event_del(event) };
+        Ok(())
+    }
+}
+
+/// Fires all timers which are due, in deadline order, then re-arms the
+/// queue's event for the next one.
+extern "C" fn handle_timeout(_fd: EvutilSocket, _flags: c_short, ctx: EventCallbackCtx) {
+    // Hold a reference of our own, since a callback may drop the queue.
+    let shared = unsafe {
+        Rc::increment_strong_count(ctx as *const Shared);
+        Rc::from_raw(ctx as *const Shared)
+    };
+    let now = Instant::now();
+    let base = unsafe { libevent_sys::event_get_base(shared.event.get()) };
+
+    loop {
+        // Release the borrow before invoking, so callbacks may add timers.
+        let cb = {
+            let mut timers = shared.timers.borrow_mut();
+            match timers.deadlines.peek().copied() {
+                Some(Reverse((deadline, id))) if deadline <= now => {
+                    timers.deadlines.pop();
+                    timers.callbacks.remove(&id)
+                }
+                _ => break,
+            }
+        };
+
+        if let Some(cb) = cb {
+            unwind::catch(base, cb);
+        }
+    }
+
+    // Nothing to report to from here; a failure leaves the rest pending.
+    let _ = shared.arm();
+}
+
+impl Drop for TimerQueue {
+    fn drop(&mut self) {
+        let event = self.shared.event.replace(std::ptr::null_mut());
+        unsafe {
+            let ctx = libevent_sys::event_get_callback_arg(event);
+            libevent_sys::event_free(event);
+            drop(Rc::from_raw(ctx as *const Shared));
+        }
+
+        // The state outlives the queue while one of its callbacks drops it,
+        // so the pending timers must not fire anymore. Dropping them may run
+        // arbitrary code, hence outside of the borrow.
+        let pending = std::mem::take(&mut self.shared.timers.borrow_mut().callbacks);
+        drop(pending);
+    }
+}
@@ -0,0 +1,112 @@
+use std::io;
+use std::os::raw::c_short;
+use std::ptr::NonNull;
+use std::time::Duration;
+
+use crate::base::{to_timeval, validate_timeout, EventCallbackCtx, EvutilSocket};
+use crate::{unwind, Base};
+
+/// A slot of a `TimerSet`, holding an event and its closure inline.
+struct Slot<F> {
+    event: libevent_sys::event,
+    cb: F,
+}
+
+/// A fixed-capacity set of interval timers whose events and closures are
+/// stored inline in a single slab, for bases running a small, fixed set of
+/// timers. Unlike `Base::spawn*`, adding a timer allocates nothing beyond
+/// the slab, and firing one allocates nothing at all.
+///
+/// All closures share the type `F`, e.g. `fn()` or a single closure type;
+/// use `Box<dyn FnMut()>` to mix closures, at the cost of a box each. The
+/// timers are removed once the `TimerSet` is dropped. Being registered with
+/// libevent directly, they are not covered by features of the spawn
+/// helpers such as event stats or recording.
+pub struct TimerSet<F> {
+    base: NonNull<libevent_sys::event_base>,
+    /// Never grows beyond its initial capacity, so that the slots, which
+    /// libevent refers to, never move.
+    slots: Vec<Slot<F>>,
+}
+
+impl<F: FnMut() + 'static> TimerSet<F> {
+    /// Creates an empty `TimerSet` for up to `capacity` timers on `base`,
+    /// which must outlive it.
+    pub fn with_capacity(base: &mut Base, capacity: usize) -> Self {
+        TimerSet {
+            base: unsafe { base.as_raw() },
+            slots: Vec::with_capacity(capacity),
+        }
+    }
+
+    /// Adds a timer invoking `cb` every `interval`, and returns its index in
+    /// the set. Fails once the set is full.
+    ///
+    /// libevent does not re-arm persistent timers without a timeout, so a
+    /// zero `interval` fires only once.
+    pub fn add(&mut self, interval: Duration, cb: F) -> io::Result<usize> {
+        let interval = validate_timeout(interval)?;
+        if self.slots.len() == self.slots.capacity() {
+            return Err(io::Error::new(io::ErrorKind::Other, "TimerSet is full"));
+        }
+
+        let index = self.slots.len();
+        self.slots.push(Slot {
+            // Initialized by `event_assign` below.
+            event: unsafe { std::mem::zeroed() },
+            cb,
+        });
+
+        let slot = &mut self.slots[index];
+        let ctx = slot as *mut Slot<F> as EventCallbackCtx;
+        let ev = &mut slot.event as *mut libevent_sys::event;
+
+        let assigned = unsafe {
+            libevent_sys::event_assign(
+                ev,
+                self.base.as_ptr(),
+                -1,
+                libevent_sys::EV_PERSIST as c_short,
+                Some(handle_timer::<F>),
+                ctx,
+            )
+        };
+        if assigned != 0 || unsafe { libevent_sys::event_add(ev, &to_timeval(interval)) } != 0 {
+            self.slots.pop();
+            return Err(io::Error::new(io::ErrorKind::Other, "Failed to add timer"));
+        }
+
+        Ok(index)
+    }
+
+    /// Returns the number of timers in the set.
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// Returns whether the set holds no timers.
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+
+    /// Returns the maximum number of timers in the set.
+    pub fn capacity(&self) -> usize {
+        self.slots.capacity()
+    }
+}
+
+/// The trampoline shared by all timers of a `TimerSet<F>`, whose context is
+/// the timer's slot.
+extern "C" fn handle_timer<F: FnMut()>(_fd: EvutilSocket, _flags: c_short, ctx: EventCallbackCtx) {
+    let slot = unsafe { &mut *(ctx as *mut Slot<F>) };
+    let base = unsafe { libevent_sys::event_get_base(&slot.event) };
+    unwind::catch(base, || (slot.cb)());
+}
+
+impl<F> Drop for TimerSet<F> {
+    fn drop(&mut self) {
+        for slot in self.slots.iter_mut() {
+            unsafe { libevent_sys::event_del(&mut slot.event) };
+        }
+    }
+}
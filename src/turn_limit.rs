@@ -0,0 +1,50 @@
+use std::cell::Cell;
+use std::ptr::NonNull;
+
+use crate::dispatch::lock;
+use crate::Base;
+
+/// The cap of a base, and the callbacks run so far in its current turn.
+#[derive(Debug)]
+pub(crate) struct TurnLimit {
+    max: usize,
+    ran: Cell<usize>,
+}
+
+impl Base {
+    /// Caps the number of callbacks run per call into the loop, e.g. per
+    /// `turn`, at `max`, to bound the time spent in the loop when it shares
+    /// a thread with other work. `None` lifts the cap.
+    ///
+    /// Once the cap is hit, the loop is stopped via `loopbreak`, so `turn`
+    /// returns `ExitReason::GotBreak`, and events which are still ready run
+    /// on the next call. Only callbacks of events spawned via `Base::spawn*`
+    /// are counted. The loop can only be stopped from within a callback, so
+    /// a cap of 0 acts like 1.
+    pub fn set_max_events_per_turn(&mut self, max: Option<usize>) {
+        *lock(&self.dispatch.turn_limit) = max.map(|max| TurnLimit {
+            max,
+            ran: Cell::new(0),
+        });
+        self.dispatch.update_instrumented();
+    }
+}
+
+impl TurnLimit {
+    /// Starts counting callbacks anew, at the start of a turn.
+    pub(crate) fn reset(&self) {
+        self.ran.set(0);
+    }
+
+    /// Counts the callback of `event`, and breaks the loop of its base once
+    /// the cap is hit.
+    pub(crate) fn record(&self, event: NonNull<libevent_sys::event>) {
+        let ran = self.ran.get() + 1;
+        self.ran.set(ran);
+
+        if ran >= self.max {
+            let base = unsafe { libevent_sys::event_get_base(event.as_ptr()) };
+            unsafe { libevent_sys::event_base_loopbreak(base) };
+        }
+    }
+}
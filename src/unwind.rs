@@ -0,0 +1,65 @@
+use std::any::Any;
+use std::cell::RefCell;
+use std::panic::{self, AssertUnwindSafe};
+
+use crate::dispatch::lock;
+use crate::{Base, ExitReason};
+
+thread_local! {
+    /// The payload of a panic caught in a callback on this thread, until the
+    /// loop it broke out of has returned.
+    static PAYLOAD: RefCell<Option<Box<dyn Any + Send>>> = RefCell::new(None);
+}
+
+impl Base {
+    /// Runs the loop like `run`, but returns the payload of a panic in a
+    /// callback as an error, so the caller can decide whether to re-raise it
+    /// via `std::panic::resume_unwind` or just log it.
+    pub fn run_catching(&self) -> Result<ExitReason, Box<dyn Any + Send>> {
+        match self.run() {
+            ExitReason::Panicked => Err(self
+                .take_panic()
+                .expect("Missing payload of panicked callback")),
+            reason => Ok(reason),
+        }
+    }
+
+    /// Takes the payload of the panic which made the loop return
+    /// `ExitReason::Panicked`, if it was not taken yet.
+    ///
+    /// A panicking callback breaks the loop, since unwinding through
+    /// libevent is not an option, and its panic is kept until taken here.
+    /// This covers callbacks of events spawned via `Base::spawn*` as well as
+    /// those of the crate's other types, e.g. `BufferEvent` and `Listener`.
+    pub fn take_panic(&self) -> Option<Box<dyn Any + Send>> {
+        lock(&self.dispatch.panic).take()
+    }
+
+    /// Moves the panic caught by `catch` while the loop ran, if any, to this
+    /// base, for `take_panic`. Returns whether there was one.
+    pub(crate) fn claim_panic(&self) -> bool {
+        match PAYLOAD.with(|slot| slot.borrow_mut().take()) {
+            Some(payload) => {
+                *lock(&self.dispatch.panic) = Some(payload);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// Invokes `f`, a callback dispatched by `base`, catching a panic rather than
+/// letting it unwind into libevent. The panic's payload is kept for the loop
+/// to claim, and the loop of `base` is broken.
+///
+/// Only the first panic is kept if more callbacks panic before the loop has
+/// returned.
+pub(crate) fn catch(base: *mut libevent_sys::event_base, f: impl FnOnce()) {
+    if let Err(payload) = panic::catch_unwind(AssertUnwindSafe(f)) {
+        PAYLOAD.with(|slot| {
+            slot.borrow_mut().get_or_insert(payload);
+        });
+
+        unsafe { libevent_sys::event_base_loopbreak(base) };
+    }
+}
@@ -0,0 +1,98 @@
+//! Helpers around libevent's portable `evutil` functions.
+
+use std::ffi::CString;
+use std::io;
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6};
+use std::os::raw::c_int;
+
+/// Wrapper for libevent's `evutil_parse_sockaddr_port`, which parses a socket
+/// address such as "127.0.0.1:8080", "[::1]:443", or a bare "::1", in which
+/// case the port is 0.
+///
+/// No name resolution is done, so hosts must be given as IP addresses.
+pub fn parse_sockaddr(s: &str) -> io::Result<SocketAddr> {
+    let invalid = || io::Error::new(io::ErrorKind::InvalidInput, "Invalid socket address");
+    let c_str = CString::new(s).map_err(|_| invalid())?;
+
+    let mut storage: libc::sockaddr_storage = unsafe { std::mem::zeroed() };
+    let mut len = std::mem::size_of::<libc::sockaddr_storage>() as c_int;
+
+    let ret = unsafe {
+        libevent_sys::evutil_parse_sockaddr_port(
+            c_str.as_ptr(),
+            &mut storage as *mut libc::sockaddr_storage as *mut libevent_sys::sockaddr,
+            &mut len,
+        )
+    };
+
+    if ret != 0 {
+        return Err(invalid());
+    }
+    let addr = &storage as *const libc::sockaddr_storage as *const libc::sockaddr;
+    unsafe { from_raw_socket_addr(addr, len as libc::socklen_t) }.ok_or_else(invalid)
+}
+
+/// Converts `addr` into a C `sockaddr` for handing it to libevent.
+pub(crate) fn raw_socket_addr(addr: &SocketAddr) -> (libc::sockaddr_storage, libc::socklen_t) {
+    let mut storage: libc::sockaddr_storage = unsafe { std::mem::zeroed() };
+
+    let len = match addr {
+        SocketAddr::V4(addr) => {
+            let sin = unsafe { &mut *(&mut storage as *mut _ as *mut libc::sockaddr_in) };
+            sin.sin_family = libc::AF_INET as libc::sa_family_t;
+            sin.sin_port = addr.port().to_be();
+            sin.sin_addr.s_addr = u32::from(*addr.ip()).to_be();
+            std::mem::size_of::<libc::sockaddr_in>()
+        }
+        SocketAddr::V6(addr) => {
+            let sin6 = unsafe { &mut *(&mut storage as *mut _ as *mut libc::sockaddr_in6) };
+            sin6.sin6_family = libc::AF_INET6 as libc::sa_family_t;
+            sin6.sin6_port = addr.port().to_be();
+            sin6.sin6_addr.s6_addr = addr.ip().octets();
+            sin6.sin6_flowinfo = addr.flowinfo();
+            sin6.sin6_scope_id = addr.scope_id();
+            std::mem::size_of::<libc::sockaddr_in6>()
+        }
+    };
+
+    (storage, len as libc::socklen_t)
+}
+
+/// Converts a C `sockaddr` of `len` bytes, as handed out by libevent, into a
+/// `SocketAddr`. Returns `None` for families other than IPv4 and IPv6, or if
+/// `len` is too short for the family.
+///
+/// # Safety
+///
+/// `addr` must point to at least `len` readable bytes.
+pub(crate) unsafe fn from_raw_socket_addr(
+    addr: *const libc::sockaddr,
+    len: libc::socklen_t,
+) -> Option<SocketAddr> {
+    let len = len as usize;
+    if addr.is_null() || len < std::mem::size_of::<libc::sa_family_t>() {
+        return None;
+    }
+
+    match (*addr).sa_family as c_int {
+        libc::AF_INET if len >= std::mem::size_of::<libc::sockaddr_in>() => {
+            let sin = std::ptr::read_unaligned(addr as *const libc::sockaddr_in);
+            let ip = Ipv4Addr::from(u32::from_be(sin.sin_addr.s_addr));
+            Some(SocketAddrV4::new(ip, u16::from_be(sin.sin_port)).into())
+        }
+        libc::AF_INET6 if len >= std::mem::size_of::<libc::sockaddr_in6>() => {
+            let sin6 = std::ptr::read_unaligned(addr as *const libc::sockaddr_in6);
+            let ip = Ipv6Addr::from(sin6.sin6_addr.s6_addr);
+            Some(
+                SocketAddrV6::new(
+                    ip,
+                    u16::from_be(sin6.sin6_port),
+                    sin6.sin6_flowinfo,
+                    sin6.sin6_scope_id,
+                )
+                .into(),
+            )
+        }
+        _ => None,
+    }
+}
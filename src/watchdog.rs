@@ -0,0 +1,80 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::dispatch::lock;
+use crate::Base;
+
+/// The state shared between a base's loop and its watchdog's monitor thread.
+#[derive(Debug, Default)]
+pub(crate) struct Watchdog {
+    state: Mutex<WatchdogState>,
+}
+
+#[derive(Debug, Default)]
+struct WatchdogState {
+    /// When the callback currently running on the loop was invoked.
+    busy_since: Option<Instant>,
+    /// Whether the current stall was already reported.
+    reported: bool,
+}
+
+impl Base {
+    /// Starts a monitor thread which invokes `on_stall` whenever a callback
+    /// on this base's loop runs for longer than `max_iteration`, which stalls
+    /// every other event. This helps to track down blocking calls which made
+    /// it into callbacks.
+    ///
+    /// `on_stall` runs on the monitor thread, while the stalled callback is
+    /// still running, and at most once per stall. Only callbacks of events
+    /// spawned via `Base::spawn*` are watched. Setting another watchdog
+    /// replaces this one.
+    pub fn set_watchdog(&mut self, max_iteration: Duration, on_stall: impl Fn() + Send + 'static) {
+        let watchdog = Arc::new(Watchdog::default());
+        let weak = Arc::downgrade(&watchdog);
+        let poll_interval = (max_iteration / 4).max(Duration::from_millis(1));
+
+        std::thread::spawn(move || loop {
+            std::thread::sleep(poll_interval);
+
+            // The base, or this watchdog of it, is gone.
+            let watchdog = match weak.upgrade() {
+                Some(watchdog) => watchdog,
+                None => return,
+            };
+
+            let stalled = {
+                let mut state = watchdog.state.lock().unwrap();
+                match state.busy_since {
+                    Some(since) if !state.reported && since.elapsed() > max_iteration => {
+                        state.reported = true;
+                        true
+                    }
+                    _ => false,
+                }
+            };
+
+            if stalled {
+                on_stall();
+            }
+        });
+
+        *lock(&self.dispatch.watchdog) = Some(watchdog);
+        self.dispatch.update_instrumented();
+    }
+}
+
+impl Watchdog {
+    /// Marks the start of a callback.
+    #[inline]
+    pub(crate) fn callback_started(&self) {
+        self.state.lock().unwrap().busy_since = Some(Instant::now());
+    }
+
+    /// Marks the end of a callback.
+    #[inline]
+    pub(crate) fn callback_finished(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.busy_since = None;
+        state.reported = false;
+    }
+}
@@ -0,0 +1,1021 @@
+use libevent::{
+    Base, CancellationToken, EventCallbackCtx, EventCallbackFlags, EventFlags, EvutilSocket,
+    ExitReason, LibeventError, Oneshot,
+};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[test]
+fn virtual_event_keeps_loop_alive() {
+    let mut base = Base::new().unwrap();
+    assert_eq!(base.run(), ExitReason::NoPendingEvents);
+
+    let virtual_event = base.add_virtual_event().unwrap();
+    assert_eq!(
+        base.run_timeout(Duration::from_millis(20)),
+        ExitReason::GotExit
+    );
+
+    drop(virtual_event);
+    assert_eq!(base.run(), ExitReason::NoPendingEvents);
+}
+
+#[test]
+fn cancel_from_another_thread() {
+    let token = CancellationToken::new();
+
+    let loop_token = token.clone();
+    let loop_thread = thread::spawn(move || {
+        let mut base = Base::new().unwrap();
+        let _keepalive = base.add_virtual_event().unwrap();
+        base.run_until_cancelled(&loop_token)
+    });
+
+    thread::sleep(Duration::from_millis(50));
+    let cancelled_at = Instant::now();
+    token.cancel();
+
+    assert_eq!(loop_thread.join().unwrap(), ExitReason::Cancelled);
+    assert!(cancelled_at.elapsed() < Duration::from_secs(1));
+}
+
+#[test]
+fn run_until_cancelled_exits_when_empty() {
+    let mut base = Base::new().unwrap();
+    let token = CancellationToken::new();
+
+    assert_eq!(
+        base.run_until_cancelled(&token),
+        ExitReason::NoPendingEvents
+    );
+}
+
+#[test]
+fn num_priorities() {
+    let mut base = Base::new().unwrap();
+    assert_eq!(base.num_priorities(), 1);
+
+    base.priority_init(5).unwrap();
+    assert_eq!(base.num_priorities(), 5);
+}
+
+#[test]
+fn event_add_reports_code() {
+    extern "C" fn noop(_fd: EvutilSocket, _flags: EventCallbackFlags, _ctx: EventCallbackCtx) {}
+
+    let mut base = Base::new().unwrap();
+    // An fd which is not open.
+    let ev = base
+        .event_new(Some(1_000_000), EventFlags::READ, noop, None)
+        .unwrap();
+
+    assert_eq!(base.event_add(ev, None), Err(LibeventError::EventAdd(-1)));
+
+    unsafe { libevent_sys::event_free(ev.as_ptr()) };
+}
+
+#[test]
+fn zero_timeout_fires_on_next_turn() {
+    let mut base = Base::new().unwrap();
+    let fired = Arc::new(AtomicUsize::new(0));
+
+    let on_timeout = fired.clone();
+    base.spawn(Oneshot::new(Duration::ZERO), move |_ev| {
+        on_timeout.fetch_add(1, Ordering::SeqCst);
+    })
+    .unwrap();
+
+    base.turn();
+    assert_eq!(fired.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn timeouts_beyond_timeval_are_rejected() {
+    extern "C" fn noop(_fd: EvutilSocket, _flags: EventCallbackFlags, _ctx: EventCallbackCtx) {}
+
+    let mut base = Base::new().unwrap();
+    let ev = base
+        .event_new(None, EventFlags::empty(), noop, None)
+        .unwrap();
+
+    assert_eq!(
+        base.event_add(ev, Some(Duration::MAX)),
+        Err(LibeventError::EventAdd(-1))
+    );
+    assert_eq!(
+        base.loopexit(Duration::MAX),
+        Err(LibeventError::LoopExit(-1))
+    );
+    assert!(base.spawn(Oneshot::new(Duration::MAX), |_ev| {}).is_err());
+
+    unsafe { libevent_sys::event_free(ev.as_ptr()) };
+}
+
+#[test]
+fn prepare_hook_runs_per_turn() {
+    let mut base = Base::new().unwrap();
+    let _keepalive = base.add_virtual_event().unwrap();
+    let runs = Arc::new(AtomicUsize::new(0));
+
+    let hook_runs = runs.clone();
+    base.on_prepare(move || {
+        hook_runs.fetch_add(1, Ordering::SeqCst);
+    });
+
+    for _ in 0..3 {
+        base.turn();
+    }
+    assert_eq!(runs.load(Ordering::SeqCst), 3);
+}
+
+#[test]
+fn prepare_hook_runs_per_iteration() {
+    let mut base = Base::new().unwrap();
+    let runs = Arc::new(AtomicUsize::new(0));
+
+    let hook_runs = runs.clone();
+    base.on_prepare(move || {
+        hook_runs.fetch_add(1, Ordering::SeqCst);
+    });
+
+    for ms in [0, 20, 40] {
+        base.spawn(Oneshot::new(Duration::from_millis(ms)), |ev| {
+            ev.stop().unwrap();
+        })
+        .unwrap();
+    }
+
+    assert_eq!(base.run(), ExitReason::NoPendingEvents);
+    assert_eq!(runs.load(Ordering::SeqCst), 3);
+}
+
+#[test]
+fn prepare_hook_can_break_the_loop() {
+    use libevent::Interval;
+
+    let mut base = Base::new().unwrap();
+    let runs = Arc::new(AtomicUsize::new(0));
+
+    // Hooks must be `Send`, unlike the raw pointer.
+    let raw = unsafe { base.as_raw() }.as_ptr() as usize;
+    let hook_runs = runs.clone();
+    base.on_prepare(move || {
+        if hook_runs.fetch_add(1, Ordering::SeqCst) == 1 {
+            unsafe { libevent_sys::event_base_loopbreak(raw as *mut _) };
+        }
+    });
+
+    let _interval = base
+        .spawn_local(Interval::new(Duration::from_millis(1)), |_ev| {})
+        .unwrap();
+
+    assert_eq!(base.run(), ExitReason::GotBreak);
+    assert_eq!(runs.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn defer_runs_once_on_next_turn() {
+    let mut base = Base::new().unwrap();
+    let ran = Arc::new(AtomicUsize::new(0));
+
+    let ran_cb = ran.clone();
+    base.defer(move || {
+        ran_cb.fetch_add(1, Ordering::SeqCst);
+    })
+    .unwrap();
+    assert_eq!(ran.load(Ordering::SeqCst), 0);
+
+    base.turn();
+    assert_eq!(ran.load(Ordering::SeqCst), 1);
+
+    base.turn();
+    assert_eq!(ran.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn drain_ready_counts_callbacks() {
+    let mut base = Base::new().unwrap();
+    let mut other = Base::new().unwrap();
+
+    for _ in 0..3 {
+        base.spawn(Oneshot::new(Duration::ZERO), |_ev| {}).unwrap();
+    }
+    other.spawn(Oneshot::new(Duration::ZERO), |_ev| {}).unwrap();
+
+    assert_eq!(base.drain_ready(), 3);
+    assert_eq!(base.drain_ready(), 0);
+    assert_eq!(other.drain_ready(), 1);
+}
+
+#[test]
+fn spawn_blocking_delivers_result_on_loop_thread() {
+    let mut base = Base::new().unwrap();
+    let loop_thread = thread::current().id();
+    let result = std::rc::Rc::new(std::cell::Cell::new(None));
+
+    let on_done = result.clone();
+    base.spawn_blocking(
+        move || {
+            thread::sleep(Duration::from_millis(20));
+            assert_ne!(thread::current().id(), loop_thread);
+            6 * 7
+        },
+        move |n| {
+            assert_eq!(thread::current().id(), loop_thread);
+            on_done.set(Some(n));
+        },
+    )
+    .unwrap();
+
+    assert_eq!(base.run(), ExitReason::NoPendingEvents);
+    assert_eq!(result.get(), Some(42));
+}
+
+#[test]
+fn spawn_blocking_panic_releases_loop() {
+    let mut base = Base::new().unwrap();
+    let done = std::rc::Rc::new(std::cell::Cell::new(false));
+
+    let on_done = done.clone();
+    base.spawn_blocking(|| panic!("blocking work failed"), move |()| on_done.set(true))
+        .unwrap();
+
+    // The event must not keep spinning on the EOF left behind by the panic.
+    let deadline = Instant::now() + Duration::from_secs(5);
+    base.run_until(
+        || base.event_count() == 0 || Instant::now() >= deadline,
+        Duration::from_millis(1),
+    );
+    assert_eq!(base.event_count(), 0);
+    assert!(!done.get());
+}
+
+#[test]
+fn single_blocking_thread_runs_work_in_turn() {
+    let mut base = Base::new().unwrap();
+    base.set_blocking_threads(1);
+    let spans = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+
+    for _ in 0..2 {
+        let on_done = spans.clone();
+        base.spawn_blocking(
+            || {
+                let started = Instant::now();
+                thread::sleep(Duration::from_millis(20));
+                (started, Instant::now())
+            },
+            move |span| on_done.borrow_mut().push(span),
+        )
+        .unwrap();
+    }
+
+    assert_eq!(base.run(), ExitReason::NoPendingEvents);
+    let mut spans = spans.borrow().clone();
+    assert_eq!(spans.len(), 2);
+    spans.sort();
+    assert!(spans[1].0 >= spans[0].1, "{:?}", spans);
+}
+
+#[test]
+fn loopexit_far_future_has_no_deadline() {
+    let base = Base::new().unwrap();
+
+    base.loopexit(Duration::from_secs(i64::MAX as u64)).unwrap();
+    assert_eq!(base.loopexit_remaining(), None);
+
+    base.loopexit(Duration::from_secs(60)).unwrap();
+    let remaining = base.loopexit_remaining().unwrap();
+    assert!(remaining > Duration::from_secs(59) && remaining <= Duration::from_secs(60));
+}
+
+#[test]
+fn dump_events_lists_events() {
+    use libevent::{AsRawEvent, Event, Interval};
+    use std::os::unix::net::UnixStream;
+
+    let mut base = Base::new().unwrap();
+    let (rx, _tx) = UnixStream::pair().unwrap();
+
+    let reader = base
+        .spawn_local(Event::from_fd(&rx, EventFlags::READ, None), |_ev, _fd, _flags| {})
+        .unwrap();
+    let timer = base
+        .spawn_local(Interval::new(Duration::from_secs(1)), |_ev| {})
+        .unwrap();
+
+    let dump = base.dump_events().unwrap();
+    for ev in [&reader as &dyn AsRawEvent, &timer] {
+        let ptr = format!("{:p}", unsafe { ev.as_raw() }.as_ptr());
+        assert!(dump.contains(&ptr), "{} missing from:\n{}", ptr, dump);
+    }
+    assert!(dump.contains(" Read"), "{}", dump);
+}
+
+#[test]
+fn precise_timers_are_not_less_precise() {
+    fn run_oneshots(mut base: Base) -> Duration {
+        let started = Instant::now();
+        for _ in 0..100 {
+            base.spawn(Oneshot::new(Duration::from_millis(1)), |_ev| {})
+                .unwrap();
+            base.run();
+        }
+        started.elapsed()
+    }
+
+    let precise = run_oneshots(Base::new_precise_timers().unwrap());
+    let default = run_oneshots(Base::new().unwrap());
+
+    assert!(precise >= Duration::from_millis(100));
+    // Generous slack, since CI machines are noisy.
+    assert!(
+        precise <= default + Duration::from_millis(50),
+        "precise: {:?}, default: {:?}",
+        precise,
+        default
+    );
+}
+
+#[test]
+fn events_for_fd_finds_read_and_write() {
+    use libevent::{AsRawEvent, Event, Interval};
+    use std::os::unix::io::AsRawFd;
+    use std::os::unix::net::UnixStream;
+
+    let mut base = Base::new().unwrap();
+    let (sock, other) = UnixStream::pair().unwrap();
+
+    let reader = base
+        .spawn_local(Event::from_fd(&sock, EventFlags::READ, None), |_ev, _fd, _flags| {})
+        .unwrap();
+    let writer = base
+        .spawn_local(Event::from_fd(&sock, EventFlags::WRITE, None), |_ev, _fd, _flags| {})
+        .unwrap();
+    let _unrelated = base
+        .spawn_local(Event::from_fd(&other, EventFlags::READ, None), |_ev, _fd, _flags| {})
+        .unwrap();
+    let _timer = base
+        .spawn_local(Interval::new(Duration::from_secs(1)), |_ev| {})
+        .unwrap();
+
+    let mut found = base.events_for_fd(sock.as_raw_fd());
+    let mut expected = unsafe { vec![reader.as_raw(), writer.as_raw()] };
+    found.sort();
+    expected.sort();
+    assert_eq!(found, expected);
+}
+
+#[test]
+fn shutdown_hooks_run_in_reverse_on_drop() {
+    let mut base = Base::new().unwrap();
+    let order = Arc::new(std::sync::Mutex::new(Vec::new()));
+
+    for i in 0..3 {
+        let order = order.clone();
+        base.on_shutdown(move || order.lock().unwrap().push(i));
+    }
+    assert!(order.lock().unwrap().is_empty());
+
+    // The hooks go along when the base is sent to another thread.
+    thread::spawn(move || drop(base)).join().unwrap();
+    assert_eq!(*order.lock().unwrap(), vec![2, 1, 0]);
+}
+
+#[test]
+fn timer_resolution_is_reasonable() {
+    for base in [Base::new().unwrap(), Base::new_precise_timers().unwrap()] {
+        let resolution = base.timer_resolution();
+        assert!(resolution > Duration::ZERO);
+        assert!(resolution <= Duration::from_millis(20), "{:?}", resolution);
+    }
+
+    let default = Base::new().unwrap().timer_resolution();
+    let precise = Base::new_precise_timers().unwrap().timer_resolution();
+    assert!(precise <= default);
+}
+
+#[test]
+fn warmed_base_dispatches_like_a_cold_one() {
+    use libevent::{Event, EventConfig};
+    use std::io::Write;
+    use std::os::unix::net::UnixStream;
+
+    // Past any fd limit, warming is skipped rather than failing.
+    Base::new_with_capacity(usize::MAX).unwrap();
+
+    let config = EventConfig::new().expected_fds(256).precise_timers(true);
+    for mut base in [
+        Base::new_with_capacity(256).unwrap(),
+        Base::with_config(config).unwrap(),
+    ] {
+        let (mut tx, rx) = UnixStream::pair().unwrap();
+        let read = std::rc::Rc::new(std::cell::Cell::new(false));
+
+        let on_read = read.clone();
+        let _ev = base
+            .spawn_local(
+                Event::from_fd(&rx, EventFlags::READ, None),
+                move |_ev, _fd, _flags| on_read.set(true),
+            )
+            .unwrap();
+        tx.write_all(b"x").unwrap();
+        base.run_until(|| read.get(), Duration::from_millis(1));
+        assert!(read.get());
+    }
+}
+
+#[test]
+fn watchdog_reports_stalled_callback() {
+    let mut base = Base::new().unwrap();
+    let stalls = Arc::new(AtomicUsize::new(0));
+
+    let on_stall = stalls.clone();
+    base.set_watchdog(Duration::from_millis(20), move || {
+        on_stall.fetch_add(1, Ordering::SeqCst);
+    });
+    base.defer(|| thread::sleep(Duration::from_millis(200)))
+        .unwrap();
+
+    base.turn();
+    assert_eq!(stalls.load(Ordering::SeqCst), 1);
+
+    // A quick callback stays below the threshold.
+    base.defer(|| {}).unwrap();
+    base.turn();
+    thread::sleep(Duration::from_millis(50));
+    assert_eq!(stalls.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn exit_hooks_see_each_reason() {
+    use std::sync::Mutex;
+
+    let mut base = Base::new().unwrap();
+    let reasons = Arc::new(Mutex::new(Vec::new()));
+
+    let seen = reasons.clone();
+    base.on_loop_exit(move |reason| seen.lock().unwrap().push(reason));
+
+    let keepalive = base.add_virtual_event().unwrap();
+    let raw = unsafe { base.as_raw() }.as_ptr();
+    base.defer(move || unsafe {
+        libevent_sys::event_base_loopbreak(raw);
+    })
+    .unwrap();
+    base.run();
+
+    drop(keepalive);
+    base.run();
+
+    assert_eq!(
+        *reasons.lock().unwrap(),
+        vec![ExitReason::GotBreak, ExitReason::NoPendingEvents]
+    );
+}
+
+#[test]
+fn clear_all_events_removes_user_events() {
+    use libevent::{Event, Interval, LoopFlags};
+    use std::io::Write;
+    use std::os::unix::net::UnixStream;
+
+    let mut base = Base::new().unwrap();
+    let fired = Arc::new(AtomicUsize::new(0));
+    let (mut tx, rx) = UnixStream::pair().unwrap();
+    tx.write_all(b"x").unwrap();
+
+    let on_timeout = fired.clone();
+    base.spawn(Oneshot::new(Duration::from_millis(1)), move |_ev| {
+        on_timeout.fetch_add(1, Ordering::SeqCst);
+    })
+    .unwrap();
+    let on_tick = fired.clone();
+    let _interval = base
+        .spawn_local(Interval::new(Duration::from_millis(1)), move |_ev| {
+            on_tick.fetch_add(1, Ordering::SeqCst);
+        })
+        .unwrap();
+    let on_read = fired.clone();
+    let _reader = base
+        .spawn_local(Event::from_fd(&rx, EventFlags::READ, None), move |_ev, _fd, _flags| {
+            on_read.fetch_add(1, Ordering::SeqCst);
+        })
+        .unwrap();
+    assert_eq!(base.event_count(), 3);
+
+    base.clear_all_events();
+    assert_eq!(base.event_count(), 0);
+
+    thread::sleep(Duration::from_millis(5));
+    base.loop_(LoopFlags::NONBLOCK);
+    assert_eq!(fired.load(Ordering::SeqCst), 0);
+}
+
+#[test]
+fn clear_all_events_keeps_helper_events() {
+    let mut base = Base::new().unwrap();
+    let remote = base.remote().unwrap();
+    base.clear_all_events();
+
+    let ran = Arc::new(AtomicUsize::new(0));
+    let on_run = ran.clone();
+    remote.schedule(move || {
+        on_run.fetch_add(1, Ordering::SeqCst);
+    });
+    base.run_until(|| ran.load(Ordering::SeqCst) == 1, Duration::from_millis(1));
+}
+
+#[test]
+fn clear_all_events_removes_virtual_timers() {
+    let mut base = Base::new().unwrap();
+    base.enable_virtual_clock();
+    let fired = Arc::new(AtomicUsize::new(0));
+
+    let on_timeout = fired.clone();
+    base.spawn(Oneshot::new(Duration::from_millis(100)), move |_ev| {
+        on_timeout.fetch_add(1, Ordering::SeqCst);
+    })
+    .unwrap();
+
+    base.clear_all_events();
+    base.advance_time(Duration::from_secs(1));
+    assert_eq!(fired.load(Ordering::SeqCst), 0);
+}
+
+#[test]
+fn coalesced_timers_fire_together() {
+    use libevent::LoopFlags;
+
+    let mut base = Base::new().unwrap();
+    base.set_timer_coalescing(Some(Duration::from_millis(10)));
+    let fired = Arc::new(AtomicUsize::new(0));
+
+    for ms in [3, 7, 9].iter() {
+        let on_timeout = fired.clone();
+        base.spawn(Oneshot::new(Duration::from_millis(*ms)), move |_ev| {
+            on_timeout.fetch_add(1, Ordering::SeqCst);
+        })
+        .unwrap();
+    }
+
+    // All three were rounded up to the same 10ms deadline.
+    base.loop_(LoopFlags::ONCE);
+    assert_eq!(fired.load(Ordering::SeqCst), 3);
+}
+
+#[test]
+fn iteration_count_counts_turns() {
+    let base = Base::new().unwrap();
+    let before = base.iteration_count();
+
+    for _ in 0..3 {
+        base.turn();
+    }
+    assert_eq!(base.iteration_count(), before + 3);
+}
+
+#[test]
+fn only_error_converts_to_err() {
+    use libevent::LoopFlags;
+
+    let reasons = [
+        ExitReason::GotExit,
+        ExitReason::GotBreak,
+        ExitReason::NoPendingEvents,
+        ExitReason::Cancelled,
+        ExitReason::Unknown {
+            flags: LoopFlags::ONCE,
+            exit_code: 2,
+        },
+    ];
+    for reason in reasons.iter() {
+        assert!(reason.into_result().is_ok(), "{:?}", reason);
+    }
+    assert!(ExitReason::Error.into_result().is_err());
+}
+
+#[test]
+fn advance_time_fires_only_passed_timers() {
+    let mut base = Base::new().unwrap();
+    base.enable_virtual_clock();
+    let fired = Arc::new(std::sync::Mutex::new(Vec::new()));
+
+    for ms in [100, 500].iter() {
+        let on_timeout = fired.clone();
+        base.spawn(Oneshot::new(Duration::from_millis(*ms)), move |_ev| {
+            on_timeout.lock().unwrap().push(*ms);
+        })
+        .unwrap();
+    }
+
+    base.advance_time(Duration::from_millis(200));
+    assert_eq!(*fired.lock().unwrap(), vec![100]);
+}
+
+#[test]
+fn advance_time_saturates() {
+    let mut base = Base::new().unwrap();
+    base.enable_virtual_clock();
+    let fired = Arc::new(AtomicUsize::new(0));
+
+    let on_timeout = fired.clone();
+    base.spawn(Oneshot::new(Duration::from_millis(100)), move |_ev| {
+        on_timeout.fetch_add(1, Ordering::SeqCst);
+    })
+    .unwrap();
+
+    base.advance_time(Duration::MAX);
+    base.advance_time(Duration::MAX);
+    assert_eq!(fired.load(Ordering::SeqCst), 1);
+
+    // Its deadline lies beyond the end of virtual time.
+    let on_timeout = fired.clone();
+    base.spawn(Oneshot::new(Duration::from_millis(100)), move |_ev| {
+        on_timeout.fetch_add(1, Ordering::SeqCst);
+    })
+    .unwrap();
+    base.advance_time(Duration::MAX);
+    assert_eq!(fired.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn virtual_clock_leaves_deferred_work_alone() {
+    let mut base = Base::new().unwrap();
+    base.enable_virtual_clock();
+    let ran = Arc::new(AtomicUsize::new(0));
+
+    let ran_cb = ran.clone();
+    base.defer(move || {
+        ran_cb.fetch_add(1, Ordering::SeqCst);
+    })
+    .unwrap();
+
+    base.turn();
+    assert_eq!(ran.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn advance_time_neither_counts_nor_runs_exit_hooks() {
+    let mut base = Base::new().unwrap();
+    base.enable_virtual_clock();
+    let fired = Arc::new(AtomicUsize::new(0));
+    let exits = Arc::new(AtomicUsize::new(0));
+
+    let on_exit = exits.clone();
+    base.on_loop_exit(move |_reason| {
+        on_exit.fetch_add(1, Ordering::SeqCst);
+    });
+    for ms in [100, 200].iter() {
+        let on_timeout = fired.clone();
+        base.spawn(Oneshot::new(Duration::from_millis(*ms)), move |_ev| {
+            on_timeout.fetch_add(1, Ordering::SeqCst);
+        })
+        .unwrap();
+    }
+
+    let before = base.iteration_count();
+    base.advance_time(Duration::from_secs(1));
+    assert_eq!(fired.load(Ordering::SeqCst), 2);
+    assert_eq!(base.iteration_count(), before);
+    assert_eq!(exits.load(Ordering::SeqCst), 0);
+}
+
+#[test]
+fn deferred_work_inherits_priority() {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    let mut base = Base::new().unwrap();
+    base.priority_init(3).unwrap();
+    base.set_priority_inheritance(true);
+    let order = Rc::new(RefCell::new(Vec::new()));
+
+    // The callback defers work via the base driving it.
+    let base_ptr: *mut Base = &mut base;
+    let high_order = order.clone();
+    base.set_timer_priority(Some(0)).unwrap();
+    base.spawn(Oneshot::new(Duration::ZERO), move |_ev| {
+        high_order.borrow_mut().push("high");
+        let deferred_order = high_order.clone();
+        unsafe { &mut *base_ptr }
+            .defer(move || deferred_order.borrow_mut().push("deferred"))
+            .unwrap();
+    })
+    .unwrap();
+
+    base.set_timer_priority(Some(2)).unwrap();
+    for _ in 0..2 {
+        let low_order = order.clone();
+        base.spawn(Oneshot::new(Duration::ZERO), move |_ev| {
+            low_order.borrow_mut().push("low");
+        })
+        .unwrap();
+    }
+
+    base.run();
+    assert_eq!(*order.borrow(), ["high", "deferred", "low", "low"]);
+}
+
+#[test]
+fn suspend_io_holds_back_spawned_and_raw_events() {
+    use libevent::Event;
+    use std::io::Write;
+    use std::os::unix::io::AsRawFd;
+    use std::os::unix::net::UnixStream;
+
+    extern "C" fn count(_fd: EvutilSocket, _flags: EventCallbackFlags, ctx: EventCallbackCtx) {
+        unsafe { &*(ctx as *const AtomicUsize) }.fetch_add(1, Ordering::SeqCst);
+    }
+
+    let mut base = Base::new().unwrap();
+    let (rx, mut tx) = UnixStream::pair().unwrap();
+
+    let spawned = Arc::new(AtomicUsize::new(0));
+    let on_ready = spawned.clone();
+    let ev = Event::from_fd(&rx, EventFlags::READ, None);
+    let _ev = base
+        .spawn_local(ev, move |_ev, _fd, _flags| {
+            on_ready.fetch_add(1, Ordering::SeqCst);
+        })
+        .unwrap();
+
+    let raw = AtomicUsize::new(0);
+    let raw_ev = base
+        .event_new(
+            Some(rx.as_raw_fd()),
+            EventFlags::READ,
+            count,
+            Some(&raw as *const AtomicUsize as EventCallbackCtx),
+        )
+        .unwrap();
+    base.event_add(raw_ev, None).unwrap();
+
+    let timers = Arc::new(AtomicUsize::new(0));
+    let on_timeout = timers.clone();
+    base.spawn(Oneshot::new(Duration::from_millis(1)), move |_ev| {
+        on_timeout.fetch_add(1, Ordering::SeqCst);
+    })
+    .unwrap();
+
+    base.suspend_io().unwrap();
+    tx.write_all(b"x").unwrap();
+    base.run_timeout(Duration::from_millis(20));
+    assert_eq!(timers.load(Ordering::SeqCst), 1);
+    assert_eq!(spawned.load(Ordering::SeqCst), 0);
+    assert_eq!(raw.load(Ordering::SeqCst), 0);
+
+    base.resume_io().unwrap();
+    base.run_until(
+        || spawned.load(Ordering::SeqCst) > 0 && raw.load(Ordering::SeqCst) > 0,
+        Duration::from_millis(1),
+    );
+    assert_eq!(spawned.load(Ordering::SeqCst), 1);
+    assert_eq!(raw.load(Ordering::SeqCst), 1);
+
+    unsafe { libevent_sys::event_free(raw_ev.as_ptr()) };
+}
+
+#[test]
+fn run_until_returns_once_flag_is_set() {
+    let mut base = Base::new().unwrap();
+    let flag = std::rc::Rc::new(std::cell::Cell::new(false));
+
+    let on_timeout = flag.clone();
+    base.spawn(Oneshot::new(Duration::from_millis(100)), move |_ev| {
+        on_timeout.set(true)
+    })
+    .unwrap();
+
+    let started = Instant::now();
+    base.run_until(|| flag.get(), Duration::from_millis(10));
+    let elapsed = started.elapsed();
+
+    assert!(flag.get());
+    assert!(
+        elapsed >= Duration::from_millis(100) && elapsed < Duration::from_millis(500),
+        "{:?}",
+        elapsed
+    );
+}
+
+#[test]
+fn assert_no_leaked_events_names_leaked_event() {
+    use libevent::Event;
+    use std::os::unix::io::AsRawFd;
+    use std::os::unix::net::UnixStream;
+    use std::panic::{self, AssertUnwindSafe};
+
+    let mut base = Base::new().unwrap();
+    base.assert_no_leaked_events();
+
+    let (rx, _tx) = UnixStream::pair().unwrap();
+    let ev = Event::from_fd(&rx, EventFlags::READ, None);
+    let _ev = base.spawn_local(ev, |_ev, _fd, _flags| {}).unwrap();
+
+    let payload =
+        panic::catch_unwind(AssertUnwindSafe(|| base.assert_no_leaked_events())).unwrap_err();
+    let message = payload.downcast_ref::<String>().unwrap();
+    assert!(message.starts_with("1 event(s)"), "{}", message);
+    assert!(
+        message.contains(&format!("fd {}", rx.as_raw_fd())),
+        "{}",
+        message
+    );
+}
+
+#[test]
+fn loopexit_min_keeps_earliest_deadline() {
+    let mut base = Base::new().unwrap();
+    // Keeps the loop from running out of events.
+    base.spawn(Oneshot::new(Duration::from_secs(5)), |_ev| {})
+        .unwrap();
+
+    base.loopexit(Duration::from_secs(1)).unwrap();
+    base.loopexit_min(Duration::from_millis(200)).unwrap();
+    base.loopexit_min(Duration::from_millis(500)).unwrap();
+    base.loopexit_min(Duration::MAX).unwrap();
+
+    let started = Instant::now();
+    assert_eq!(base.run(), ExitReason::GotExit);
+    let elapsed = started.elapsed();
+    assert!(
+        elapsed >= Duration::from_millis(200) && elapsed < Duration::from_millis(800),
+        "{:?}",
+        elapsed
+    );
+}
+
+#[test]
+fn run_timeout_without_representable_deadline_runs_until_done() {
+    let mut base = Base::new().unwrap();
+    let fired = Arc::new(AtomicUsize::new(0));
+
+    let on_timeout = fired.clone();
+    base.spawn(Oneshot::new(Duration::from_millis(10)), move |_ev| {
+        on_timeout.fetch_add(1, Ordering::SeqCst);
+    })
+    .unwrap();
+
+    assert_eq!(base.run_timeout(Duration::MAX), ExitReason::NoPendingEvents);
+    assert_eq!(fired.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn available_methods_include_platform_backend() {
+    let methods = libevent::available_methods();
+    assert!(!methods.is_empty());
+
+    #[cfg(target_os = "linux")]
+    assert!(methods.contains(&"epoll".to_string()), "{:?}", methods);
+    #[cfg(any(target_os = "macos", target_os = "freebsd"))]
+    assert!(methods.contains(&"kqueue".to_string()), "{:?}", methods);
+
+    let base = Base::new().unwrap();
+    assert!(methods.contains(&base.method()), "{:?}", methods);
+}
+
+#[test]
+fn stop_from_callback_breaks_run() {
+    use libevent::Interval;
+
+    let mut base = Base::new().unwrap();
+    // Keeps the loop from running out of events.
+    let _ev = base
+        .spawn_local(Interval::new(Duration::from_secs(1)), |_ev| {})
+        .unwrap();
+
+    let base_ptr: *const Base = &base;
+    base.spawn(Oneshot::new(Duration::ZERO), move |_ev| {
+        unsafe { &*base_ptr }.stop().unwrap();
+    })
+    .unwrap();
+    assert_eq!(base.run(), ExitReason::GotBreak);
+
+    base.stop_after(Duration::from_millis(10)).unwrap();
+    assert_eq!(base.run(), ExitReason::GotExit);
+}
+
+#[test]
+fn from_raw_base_leaves_unowned_base_alone() {
+    use libevent::Interval;
+    use std::ptr::NonNull;
+
+    let raw = NonNull::new(unsafe { libevent_sys::event_base_new() }).unwrap();
+    {
+        let mut base = unsafe { Base::from_raw_base(raw, false) };
+        let fired = Arc::new(AtomicUsize::new(0));
+
+        let on_fire = fired.clone();
+        let ev = base
+            .spawn_local(Interval::new(Duration::from_millis(1)), move |_ev| {
+                on_fire.fetch_add(1, Ordering::SeqCst);
+            })
+            .unwrap();
+        base.run_until(
+            || fired.load(Ordering::SeqCst) >= 3,
+            Duration::from_millis(1),
+        );
+        drop(ev);
+    }
+
+    // Still alive, and ours to free.
+    let added = libevent_sys::EVENT_BASE_COUNT_ADDED as std::os::raw::c_int;
+    let events = unsafe { libevent_sys::event_base_get_num_events(raw.as_ptr(), added) };
+    assert_eq!(events, 0);
+    unsafe { libevent_sys::event_base_free(raw.as_ptr()) };
+}
+
+#[test]
+fn high_priority_timer_fires_despite_busy_socket() {
+    use libevent::Event;
+    use std::cell::Cell;
+    use std::io::Write;
+    use std::os::unix::net::UnixStream;
+    use std::rc::Rc;
+
+    let mut base = Base::new().unwrap();
+    base.priority_init(3).unwrap();
+    let (rx, mut tx) = UnixStream::pair().unwrap();
+    tx.write_all(b"never drained").unwrap();
+
+    // The socket stays readable, so its callback runs on every turn, at the
+    // default, middle priority.
+    let busy_turns = Arc::new(AtomicUsize::new(0));
+    let on_busy = busy_turns.clone();
+    let busy = Event::from_fd(&rx, EventFlags::READ | EventFlags::PERSIST, None);
+    base.spawn(busy, move |_ev, _fd, _flags| {
+        on_busy.fetch_add(1, Ordering::SeqCst);
+        thread::sleep(Duration::from_millis(1));
+    })
+    .unwrap();
+
+    base.set_timer_priority(Some(0)).unwrap();
+    let fired_at = Rc::new(Cell::new(None));
+    let on_fired = fired_at.clone();
+    let start = Instant::now();
+    let _timer = base
+        .spawn_local(Oneshot::new(Duration::from_millis(20)), move |_ev| {
+            on_fired.set(Some(start.elapsed()));
+        })
+        .unwrap();
+
+    assert_eq!(
+        base.run_timeout(Duration::from_millis(100)),
+        ExitReason::GotExit
+    );
+
+    // The deadline counts from the base's cached time, so allow for it being
+    // a little stale.
+    let fired_at = fired_at.get().expect("timer never fired");
+    assert!(fired_at >= Duration::from_millis(10), "{:?}", fired_at);
+    assert!(fired_at < Duration::from_millis(60), "{:?}", fired_at);
+    assert!(busy_turns.load(Ordering::SeqCst) > 10);
+}
+
+#[test]
+fn busy_fraction_reflects_time_in_callbacks() {
+    use libevent::Interval;
+
+    let mut base = Base::new().unwrap();
+    assert_eq!(base.busy_fraction(), 0.0);
+    base.enable_busy_tracking();
+
+    // Busy for half of each period.
+    base.spawn(Interval::new(Duration::from_millis(20)), |_ev| {
+        thread::sleep(Duration::from_millis(10))
+    })
+    .unwrap();
+
+    base.busy_fraction();
+    base.run_timeout(Duration::from_millis(200));
+    let fraction = base.busy_fraction();
+    assert!((0.3..0.7).contains(&fraction), "{}", fraction);
+}
+
+#[test]
+fn panicking_callback_ends_run_with_payload() {
+    let mut base = Base::new().unwrap();
+    base.spawn(Oneshot::new(Duration::ZERO), |_ev| {
+        panic!("callback failed")
+    })
+    .unwrap();
+
+    assert_eq!(base.run(), ExitReason::Panicked);
+    let payload = base.take_panic().unwrap();
+    assert_eq!(payload.downcast_ref::<&str>(), Some(&"callback failed"));
+    assert!(base.take_panic().is_none());
+
+    let attempt = 2;
+    base.spawn(Oneshot::new(Duration::ZERO), move |_ev| {
+        panic!("failed again: {}", attempt)
+    })
+    .unwrap();
+    let payload = base.run_catching().unwrap_err();
+    assert_eq!(
+        payload.downcast_ref::<String>().map(String::as_str),
+        Some("failed again: 2")
+    );
+    assert_eq!(base.run_catching().unwrap(), ExitReason::NoPendingEvents);
+}
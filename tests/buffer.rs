@@ -0,0 +1,440 @@
+use libevent::{EolStyle, EvBuffer};
+use std::sync::Arc;
+
+#[test]
+fn readln_splits_lines() {
+    let mut buf = EvBuffer::new().unwrap();
+    buf.add(b"GET /\r\nHost: x\r\n").unwrap();
+
+    assert_eq!(buf.readln(EolStyle::CrlfStrict).as_deref(), Some("GET /"));
+    assert_eq!(buf.readln(EolStyle::CrlfStrict).as_deref(), Some("Host: x"));
+    assert_eq!(buf.readln(EolStyle::CrlfStrict), None);
+    assert!(buf.is_empty());
+}
+
+#[test]
+fn readln_waits_for_complete_line() {
+    let mut buf = EvBuffer::new().unwrap();
+    buf.add(b"partial").unwrap();
+
+    assert_eq!(buf.readln(EolStyle::Lf), None);
+    assert_eq!(buf.len(), 7);
+}
+
+#[test]
+fn search_and_peek() {
+    let mut buf = EvBuffer::new().unwrap();
+    buf.add(b"abc\r\n\r\nxyz").unwrap();
+
+    assert_eq!(buf.search(b"\r\n\r\n"), Some(3));
+    assert_eq!(buf.search(b"nope"), None);
+    assert_eq!(buf.peek(3), b"abc");
+    assert_eq!(buf.len(), 10);
+}
+
+#[test]
+fn add_reference_releases_data_once_drained() {
+    let mut buf = EvBuffer::new().unwrap();
+    let data: Arc<[u8]> = Arc::from(&b"static response"[..]);
+
+    buf.add_reference(data.clone()).unwrap();
+    assert_eq!(Arc::strong_count(&data), 2);
+    assert_eq!(buf.peek(6), b"static");
+
+    unsafe { libevent_sys::evbuffer_drain(buf.as_raw().as_ptr(), buf.len()) };
+    assert_eq!(Arc::strong_count(&data), 1);
+}
+
+#[test]
+fn add_file_sends_contents() {
+    use libevent::{Base, BufferEvent};
+    use std::fs::{self, File};
+    use std::io::{Read, Write};
+    use std::os::unix::net::UnixStream;
+    use std::time::Duration;
+
+    let contents: Vec<u8> = (0..64 * 1024).map(|i| i as u8).collect();
+    let path = std::env::temp_dir().join(format!("libevent-add-file-{}", std::process::id()));
+    File::create(&path).unwrap().write_all(&contents).unwrap();
+    let file = File::open(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    let mut base = Base::new().unwrap();
+    let (tx, mut rx) = UnixStream::pair().unwrap();
+    rx.set_nonblocking(true).unwrap();
+
+    let mut bev = BufferEvent::new(&mut base, tx).unwrap();
+    bev.output()
+        .add_file(file, 0, contents.len() as u64)
+        .unwrap();
+
+    let mut received = Vec::new();
+    base.run_until(
+        || {
+            let mut chunk = [0; 4096];
+            loop {
+                match rx.read(&mut chunk) {
+                    Ok(n) if n > 0 => received.extend_from_slice(&chunk[..n]),
+                    _ => break,
+                }
+            }
+            received.len() >= contents.len()
+        },
+        Duration::from_millis(1),
+    );
+    assert!(received == contents);
+}
+
+#[test]
+fn bufferevent_makes_socket_nonblocking() {
+    use libevent::{Base, BufferEvent};
+    use std::os::unix::net::UnixStream;
+
+    let mut base = Base::new().unwrap();
+    let (tx, _rx) = UnixStream::pair().unwrap();
+
+    let bev = BufferEvent::new(&mut base, tx).unwrap();
+    let flags = unsafe { libc::fcntl(bev.fd(), libc::F_GETFL) };
+    assert!(flags >= 0);
+    assert_ne!(flags & libc::O_NONBLOCK, 0);
+}
+
+#[test]
+fn add_printf_formats_into_buffer() {
+    let mut buf = EvBuffer::new().unwrap();
+    libevent::evbuffer_add_printf!(buf, "HTTP/1.1 {} {}\r\n", 200, "OK").unwrap();
+    libevent::evbuffer_add_printf!(buf, "Content-Length: {}\r\n\r\n", 5).unwrap();
+
+    let expected = b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\n";
+    assert_eq!(buf.peek(buf.len()), &expected[..]);
+}
+
+#[test]
+fn peek_iovecs_covers_all_chunks() {
+    let mut buf = EvBuffer::new().unwrap();
+    let data: Vec<u8> = (0..256 * 1024).map(|i| (i % 251) as u8).collect();
+    for chunk in data.chunks(64 * 1024) {
+        buf.add(chunk).unwrap();
+    }
+
+    // A generous limit only allocates for the chunks there are.
+    let vecs = buf.peek_iovecs(usize::MAX);
+    assert!(vecs.len() > 1);
+    assert_eq!(vecs.concat(), data);
+
+    let first = buf.peek_iovecs(1);
+    assert_eq!(first, &vecs[..1]);
+    assert!(buf.peek_iovecs(0).is_empty());
+}
+
+#[test]
+fn flush_pushes_data_through_filter() {
+    use libevent::{Base, BufferEvent, EventFlags, FlushMode};
+    use std::io::Read;
+    use std::os::raw::c_void;
+    use std::os::unix::net::UnixStream;
+    use std::ptr::NonNull;
+
+    /// Holds back all output until flushed.
+    unsafe extern "C" fn hold_until_flush(
+        src: *mut libevent_sys::evbuffer,
+        dst: *mut libevent_sys::evbuffer,
+        _dst_limit: isize,
+        mode: libevent_sys::bufferevent_flush_mode,
+        _ctx: *mut c_void,
+    ) -> libevent_sys::bufferevent_filter_result {
+        if mode == libevent_sys::bufferevent_flush_mode_BEV_NORMAL {
+            return libevent_sys::bufferevent_filter_result_BEV_NEED_MORE;
+        }
+        libevent_sys::evbuffer_add_buffer(dst, src);
+        libevent_sys::bufferevent_filter_result_BEV_OK
+    }
+
+    let mut base = Base::new().unwrap();
+    let (tx, mut rx) = UnixStream::pair().unwrap();
+    rx.set_nonblocking(true).unwrap();
+
+    let socket = BufferEvent::new(&mut base, tx).unwrap();
+    let mut filter = unsafe {
+        let raw = libevent_sys::bufferevent_filter_new(
+            socket.as_raw().as_ptr(),
+            None,
+            Some(hold_until_flush),
+            0,
+            None,
+            std::ptr::null_mut(),
+        );
+        BufferEvent::from_raw(NonNull::new(raw).unwrap())
+    };
+
+    filter.output().add(b"partial").unwrap();
+    for _ in 0..3 {
+        base.turn();
+    }
+    let mut chunk = [0; 64];
+    assert!(rx.read(&mut chunk).is_err());
+
+    assert_eq!(filter.flush(EventFlags::WRITE, FlushMode::Flush), 1);
+    let mut received = Vec::new();
+    base.run_until(
+        || {
+            if let Ok(n) = rx.read(&mut chunk) {
+                received.extend_from_slice(&chunk[..n]);
+            }
+            received.len() >= 7
+        },
+        std::time::Duration::from_millis(1),
+    );
+    assert_eq!(received, b"partial");
+}
+
+#[test]
+fn close_after_write_delivers_everything() {
+    use libevent::{Base, BufferEvent};
+    use std::io::Read;
+    use std::os::unix::net::UnixStream;
+    use std::thread;
+
+    let mut base = Base::new().unwrap();
+    let (tx, mut rx) = UnixStream::pair().unwrap();
+    let payload: Vec<u8> = (0..4 * 1024 * 1024).map(|i| (i % 253) as u8).collect();
+
+    // Read until EOF, i.e. until the bufferevent closed the socket.
+    let reader = thread::spawn(move || {
+        let mut received = Vec::new();
+        rx.read_to_end(&mut received).unwrap();
+        received
+    });
+
+    let mut bev = BufferEvent::new(&mut base, tx).unwrap();
+    bev.output().add(&payload).unwrap();
+    bev.close_after_write();
+    base.run();
+
+    assert!(reader.join().unwrap() == payload);
+}
+
+#[test]
+fn output_len_drains_as_written() {
+    use libevent::{Base, BufferEvent};
+    use std::os::unix::net::UnixStream;
+    use std::time::Duration;
+
+    let mut base = Base::new().unwrap();
+    let (tx, _rx) = UnixStream::pair().unwrap();
+
+    let mut bev = BufferEvent::new(&mut base, tx).unwrap();
+    bev.output().add(&[0; 100]).unwrap();
+    assert_eq!(bev.output_len(), 100);
+    assert_eq!(bev.input_len(), 0);
+
+    base.run_until(|| bev.output_len() == 0, Duration::from_millis(1));
+}
+
+#[test]
+fn disabled_reading_holds_back_input() {
+    use libevent::{Base, BufferEvent, EventFlags};
+    use std::io::Write;
+    use std::os::unix::net::UnixStream;
+    use std::time::Duration;
+
+    let mut base = Base::new().unwrap();
+    let (mut tx, rx) = UnixStream::pair().unwrap();
+
+    let bev = BufferEvent::new(&mut base, rx).unwrap();
+    bev.disable(EventFlags::READ).unwrap();
+    tx.write_all(b"held back").unwrap();
+
+    for _ in 0..3 {
+        base.turn();
+    }
+    assert_eq!(bev.input_len(), 0);
+
+    bev.enable(EventFlags::READ).unwrap();
+    base.run_until(|| bev.input_len() == 9, Duration::from_millis(1));
+}
+
+#[test]
+fn buffer_reference_shares_content() {
+    let mut src = EvBuffer::new().unwrap();
+    src.add(b"broadcast payload").unwrap();
+
+    let dsts: Vec<EvBuffer> = (0..3)
+        .map(|_| {
+            let mut dst = EvBuffer::new().unwrap();
+            dst.add_buffer_reference(&src).unwrap();
+            dst
+        })
+        .collect();
+
+    for dst in &dsts {
+        assert_eq!(dst.peek(dst.len()), b"broadcast payload");
+    }
+    assert_eq!(src.peek(src.len()), b"broadcast payload");
+}
+
+#[test]
+fn socket_error_reports_refused_connect() {
+    use libevent::{Base, BufferEvent};
+    use std::cell::RefCell;
+    use std::io::{self, ErrorKind};
+    use std::net::{Ipv4Addr, TcpListener, TcpStream};
+    use std::os::raw::{c_int, c_short, c_void};
+    use std::os::unix::io::FromRawFd;
+    use std::time::Duration;
+
+    struct Outcome {
+        bev: *const BufferEvent,
+        error: RefCell<Option<io::Error>>,
+    }
+
+    unsafe extern "C" fn on_event(
+        _bev: *mut libevent_sys::bufferevent,
+        what: c_short,
+        ctx: *mut c_void,
+    ) {
+        let outcome = &*(ctx as *const Outcome);
+        if what as u32 & libevent_sys::BEV_EVENT_ERROR != 0 {
+            *outcome.error.borrow_mut() = Some((*outcome.bev).socket_error());
+        }
+    }
+
+    // A port nothing listens on anymore.
+    let port = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+
+    let mut base = Base::new().unwrap();
+    let fd = unsafe { libc::socket(libc::AF_INET, libc::SOCK_STREAM, 0) };
+    assert!(fd >= 0);
+    let bev = BufferEvent::new(&mut base, unsafe { TcpStream::from_raw_fd(fd) }).unwrap();
+    let outcome = Outcome {
+        bev: &bev,
+        error: RefCell::new(None),
+    };
+
+    let mut addr: libc::sockaddr_in = unsafe { std::mem::zeroed() };
+    addr.sin_family = libc::AF_INET as _;
+    addr.sin_port = port.to_be();
+    addr.sin_addr.s_addr = u32::from(Ipv4Addr::LOCALHOST).to_be();
+    unsafe {
+        let raw = bev.as_raw().as_ptr();
+        let ctx = &outcome as *const Outcome as *mut c_void;
+        libevent_sys::bufferevent_setcb(raw, None, None, Some(on_event), ctx);
+
+        let len = std::mem::size_of::<libc::sockaddr_in>() as c_int;
+        let addr = &addr as *const libc::sockaddr_in as *const libevent_sys::sockaddr;
+        assert_eq!(libevent_sys::bufferevent_socket_connect(raw, addr, len), 0);
+    }
+
+    base.run_until(
+        || outcome.error.borrow().is_some(),
+        Duration::from_millis(1),
+    );
+    let error = outcome.error.borrow_mut().take().unwrap();
+    assert_eq!(error.kind(), ErrorKind::ConnectionRefused, "{}", error);
+}
+
+#[test]
+fn take_fd_keeps_socket_open() {
+    use libevent::{Base, BufferEvent, EventFlags};
+    use std::io::{Read, Write};
+    use std::os::raw::c_void;
+    use std::os::unix::net::UnixStream;
+    use std::time::Duration;
+
+    let mut base = Base::new().unwrap();
+    let (ours, mut peer) = UnixStream::pair().unwrap();
+
+    let mut bev = BufferEvent::new(&mut base, ours).unwrap();
+    bev.output().add(b"hello").unwrap();
+    base.run_until(|| bev.output_len() == 0, Duration::from_millis(1));
+    let mut buf = [0; 5];
+    peer.read_exact(&mut buf).unwrap();
+    assert_eq!(&buf, b"hello");
+
+    peer.write_all(b"world").unwrap();
+    bev.enable(EventFlags::READ).unwrap();
+    base.run_until(|| bev.input_len() == 5, Duration::from_millis(1));
+    assert_eq!(bev.input().peek(5), b"world");
+
+    let fd = bev.take_fd();
+    assert!(fd >= 0);
+
+    let sent = unsafe { libc::write(fd, b"bye".as_ptr() as *const c_void, 3) };
+    assert_eq!(sent, 3);
+    let mut buf = [0; 3];
+    peer.read_exact(&mut buf).unwrap();
+    assert_eq!(&buf, b"bye");
+
+    peer.write_all(b"again").unwrap();
+    let mut buf = [0; 5];
+    let received = unsafe { libc::read(fd, buf.as_mut_ptr() as *mut c_void, 5) };
+    assert_eq!(received, 5);
+    assert_eq!(&buf, b"again");
+
+    assert_eq!(unsafe { libc::close(fd) }, 0);
+}
+
+#[test]
+fn write_vectored_delivers_slices_in_order() {
+    use libevent::{Base, BufferEvent};
+    use std::io::Read;
+    use std::os::unix::net::UnixStream;
+    use std::time::Duration;
+
+    let mut base = Base::new().unwrap();
+    let (tx, mut rx) = UnixStream::pair().unwrap();
+
+    let bev = BufferEvent::new(&mut base, tx).unwrap();
+    let header = 4u32.to_be_bytes();
+    bev.write_vectored(&[&header, b"body"]).unwrap();
+    assert_eq!(bev.output_len(), 8);
+
+    base.run_until(|| bev.output_len() == 0, Duration::from_millis(1));
+
+    let mut received = [0; 8];
+    rx.read_exact(&mut received).unwrap();
+    assert_eq!(&received, b"\0\0\0\x04body");
+}
+
+#[test]
+fn paused_read_callback_waits_for_resume() {
+    use libevent::{Base, BufferEvent, EventFlags, ReadAction};
+    use std::cell::RefCell;
+    use std::io::Write;
+    use std::os::unix::net::UnixStream;
+    use std::rc::Rc;
+    use std::time::Duration;
+
+    let mut base = Base::new().unwrap();
+    let (rx, mut tx) = UnixStream::pair().unwrap();
+    let lines = Rc::new(RefCell::new(Vec::new()));
+
+    let mut bev = BufferEvent::new(&mut base, rx).unwrap();
+    let on_read = lines.clone();
+    bev.set_read_callback(move |input| {
+        if let Some(line) = input.readln(EolStyle::Lf) {
+            on_read.borrow_mut().push(line);
+        }
+        ReadAction::Pause
+    });
+    bev.enable(EventFlags::READ).unwrap();
+
+    tx.write_all(b"first\n").unwrap();
+    base.run_until(|| !lines.borrow().is_empty(), Duration::from_millis(1));
+
+    // The socket is readable again, but reading stays paused.
+    tx.write_all(b"second\n").unwrap();
+    for _ in 0..3 {
+        base.turn();
+    }
+    assert_eq!(*lines.borrow(), ["first"]);
+    assert_eq!(bev.input_len(), 0);
+
+    bev.enable(EventFlags::READ).unwrap();
+    base.run_until(|| lines.borrow().len() == 2, Duration::from_millis(1));
+    assert_eq!(*lines.borrow(), ["first", "second"]);
+}
@@ -0,0 +1,45 @@
+// Relies on Linux dropping SYNs once a listener's accept queue is full, which
+// makes further connects hang without any outside network.
+#[cfg(target_os = "linux")]
+#[test]
+fn connect_times_out() {
+    use libevent::{Base, ConnectError};
+    use std::cell::RefCell;
+    use std::net::{TcpListener, TcpStream};
+    use std::os::unix::io::AsRawFd;
+    use std::rc::Rc;
+    use std::time::{Duration, Instant};
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    assert_eq!(unsafe { libc::listen(listener.as_raw_fd(), 0) }, 0);
+
+    // Fill the accept queue, which is never drained.
+    let mut queued = Vec::new();
+    while let Ok(stream) = TcpStream::connect_timeout(&addr, Duration::from_millis(50)) {
+        queued.push(stream);
+    }
+
+    let mut base = Base::new().unwrap();
+    let fd = unsafe { libc::socket(libc::AF_INET, libc::SOCK_STREAM, 0) };
+    assert!(fd >= 0);
+
+    let outcome = Rc::new(RefCell::new(None));
+    let on_outcome = outcome.clone();
+    let started = Instant::now();
+    base.connect_with_timeout(fd, addr, Duration::from_millis(100), move |res| {
+        *on_outcome.borrow_mut() = Some(res)
+    })
+    .unwrap();
+
+    base.run();
+    let elapsed = started.elapsed();
+    unsafe { libc::close(fd) };
+
+    assert!(
+        matches!(*outcome.borrow(), Some(Err(ConnectError::Timeout))),
+        "{:?}",
+        outcome
+    );
+    assert!(elapsed >= Duration::from_millis(100) && elapsed < Duration::from_secs(5));
+}
@@ -0,0 +1,298 @@
+use libevent::{Base, Event, EventFlags};
+use std::cell::RefCell;
+use std::io::Write;
+use std::os::unix::io::AsRawFd;
+use std::os::unix::net::UnixStream;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+#[test]
+fn callback_receives_fd_and_flags() {
+    let mut base = Base::new().unwrap();
+    let (mut tx, rx) = UnixStream::pair().unwrap();
+    let seen = Rc::new(RefCell::new(Vec::new()));
+
+    let ev = Event::from_fd(&rx, EventFlags::READ, None);
+    let seen_cb = seen.clone();
+    base.spawn(ev, move |ev, fd, flags| {
+        seen_cb.borrow_mut().push((fd, flags));
+        ev.stop().unwrap();
+    })
+    .unwrap();
+
+    tx.write_all(b"x").unwrap();
+    base.run();
+
+    assert_eq!(*seen.borrow(), vec![(rx.as_raw_fd(), EventFlags::READ)]);
+}
+
+#[test]
+fn callback_receives_timeout_flag() {
+    let mut base = Base::new().unwrap();
+    let (_tx, rx) = UnixStream::pair().unwrap();
+    let seen = Rc::new(RefCell::new(Vec::new()));
+
+    let ev = Event::from_fd(&rx, EventFlags::READ, Some(Duration::from_millis(10)));
+    let seen_cb = seen.clone();
+    base.spawn(ev, move |ev, _fd, flags| {
+        seen_cb.borrow_mut().push(flags);
+        ev.stop().unwrap();
+    })
+    .unwrap();
+
+    base.run();
+
+    assert_eq!(*seen.borrow(), vec![EventFlags::TIMEOUT]);
+}
+
+#[test]
+fn event_channel_forwards_activations() {
+    use libevent::Backpressure;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+
+    let mut base = Base::new().unwrap();
+    let (rx, mut tx) = UnixStream::pair().unwrap();
+
+    let (_handle, activations) = base
+        .event_channel(rx.as_raw_fd(), EventFlags::READ, 4, Backpressure::Drop)
+        .unwrap();
+    let got = Arc::new(AtomicBool::new(false));
+
+    let consumer_got = got.clone();
+    let consumer = thread::spawn(move || {
+        let flags = activations.recv_timeout(Duration::from_secs(5)).unwrap();
+        consumer_got.store(true, Ordering::SeqCst);
+        flags
+    });
+
+    tx.write_all(b"x").unwrap();
+    base.run_until(|| got.load(Ordering::SeqCst), Duration::from_millis(1));
+    assert_eq!(consumer.join().unwrap(), EventFlags::READ);
+}
+
+#[test]
+fn equal_priority_events_take_turns() {
+    use libevent::LoopFlags;
+    use std::cell::Cell;
+
+    let mut base = Base::new().unwrap();
+    let counts: Vec<Rc<Cell<usize>>> = (0..4).map(|_| Rc::new(Cell::new(0))).collect();
+
+    let mut pairs = Vec::new();
+    let mut handles = Vec::new();
+    for count in &counts {
+        // The data is never read, so the event stays ready.
+        let (mut tx, rx) = UnixStream::pair().unwrap();
+        tx.write_all(b"x").unwrap();
+
+        let count = count.clone();
+        let ev = Event::from_fd(&rx, EventFlags::READ | EventFlags::PERSIST, None);
+        handles.push(
+            base.spawn_local(ev, move |_ev, _fd, _flags| count.set(count.get() + 1))
+                .unwrap(),
+        );
+        pairs.push((tx, rx));
+    }
+
+    for _ in 0..100 {
+        base.loop_(LoopFlags::ONCE);
+    }
+
+    let total: usize = counts.iter().map(|count| count.get()).sum();
+    assert!(total >= 100);
+    for count in &counts {
+        assert!(count.get() * 5 >= total, "{} of {}", count.get(), total);
+    }
+}
+
+#[test]
+fn observers_run_after_callback_in_order() {
+    let mut base = Base::new().unwrap();
+    let (mut tx, rx) = UnixStream::pair().unwrap();
+    let calls = Arc::new(Mutex::new(Vec::new()));
+
+    let primary = calls.clone();
+    let ev = base
+        .spawn_local(
+            Event::from_fd(&rx, EventFlags::READ, None),
+            move |_ev, _fd, _flags| primary.lock().unwrap().push("primary"),
+        )
+        .unwrap();
+    for name in ["first", "second"] {
+        let calls = calls.clone();
+        ev.add_observer(move |flags| {
+            assert_eq!(flags, EventFlags::READ);
+            calls.lock().unwrap().push(name);
+        });
+    }
+
+    tx.write_all(b"x").unwrap();
+    base.run_until(
+        || !calls.lock().unwrap().is_empty(),
+        Duration::from_millis(1),
+    );
+    assert_eq!(*calls.lock().unwrap(), vec!["primary", "first", "second"]);
+}
+
+#[test]
+fn panicking_observer_ends_run_and_is_kept() {
+    use libevent::ExitReason;
+
+    let mut base = Base::new().unwrap();
+    let (mut tx, rx) = UnixStream::pair().unwrap();
+    let ev = base
+        .spawn_local(
+            Event::from_fd(&rx, EventFlags::READ | EventFlags::PERSIST, None),
+            |_ev, _fd, _flags| {},
+        )
+        .unwrap();
+    ev.add_observer(|_flags| panic!("observer failed"));
+
+    tx.write_all(b"x").unwrap();
+    for _ in 0..2 {
+        assert_eq!(base.run(), ExitReason::Panicked);
+        let payload = base.take_panic().unwrap();
+        assert_eq!(payload.downcast_ref::<&str>(), Some(&"observer failed"));
+    }
+}
+
+#[test]
+fn latency_stats_cover_ready_events() {
+    use libevent::LoopFlags;
+
+    let mut base = Base::new().unwrap();
+    assert_eq!(base.latency_stats().count(), 0);
+    base.enable_latency_stats();
+
+    // The data is never read, so both events stay ready.
+    let (mut tx, rx) = UnixStream::pair().unwrap();
+    tx.write_all(b"x").unwrap();
+    let _handles: Vec<_> = (0..2)
+        .map(|_| {
+            let ev = Event::from_fd(&rx, EventFlags::READ | EventFlags::PERSIST, None);
+            base.spawn_local(ev, |_ev, _fd, _flags| {}).unwrap()
+        })
+        .collect();
+
+    for _ in 0..10 {
+        base.loop_(LoopFlags::ONCE);
+    }
+
+    let stats = base.latency_stats();
+    assert_eq!(stats.count(), 20);
+    assert_eq!(stats.buckets().iter().sum::<u64>(), 20);
+    assert!(stats.max() < Duration::from_millis(100), "{:?}", stats);
+    assert!(stats.mean().unwrap() <= stats.max());
+}
+
+#[test]
+fn max_events_per_turn_caps_callbacks() {
+    use libevent::{ExitReason, Oneshot};
+    use std::cell::Cell;
+
+    let mut base = Base::new().unwrap();
+    let ran = Rc::new(Cell::new(0));
+    for _ in 0..10 {
+        let on_timeout = ran.clone();
+        base.spawn(Oneshot::new(Duration::ZERO), move |_ev| {
+            on_timeout.set(on_timeout.get() + 1)
+        })
+        .unwrap();
+    }
+
+    base.set_max_events_per_turn(Some(3));
+    assert_eq!(base.turn(), ExitReason::GotBreak);
+    assert_eq!(ran.get(), 3);
+    base.turn();
+    assert_eq!(ran.get(), 6);
+
+    // The loop only breaks once a callback ran.
+    base.set_max_events_per_turn(Some(0));
+    base.turn();
+    assert_eq!(ran.get(), 7);
+
+    base.set_max_events_per_turn(None);
+    base.turn();
+    assert_eq!(ran.get(), 10);
+}
+
+#[test]
+fn more_ready_fds_than_a_backend_batch_all_dispatch() {
+    let mut base = Base::new().unwrap();
+    let seen = Rc::new(RefCell::new(Vec::new()));
+
+    // `epoll` starts out fetching 32 ready fds per call, which this exceeds.
+    let mut pairs = Vec::new();
+    for i in 0..100 {
+        let (mut tx, rx) = UnixStream::pair().unwrap();
+        tx.write_all(b"x").unwrap();
+
+        let seen = seen.clone();
+        let ev = Event::from_fd(&rx, EventFlags::READ, None);
+        base.spawn(ev, move |_ev, _fd, _flags| seen.borrow_mut().push(i))
+            .unwrap();
+        pairs.push((tx, rx));
+    }
+
+    base.run();
+
+    let mut seen = seen.borrow().clone();
+    seen.sort_unstable();
+    assert_eq!(seen, (0..100).collect::<Vec<_>>());
+}
+
+#[test]
+fn register_source_delivers_token() {
+    use libevent::{Interest, LoopFlags};
+
+    let mut base = Base::new().unwrap();
+    let (rx, mut tx) = UnixStream::pair().unwrap();
+    let seen = Rc::new(RefCell::new(Vec::new()));
+
+    let seen_cb = seen.clone();
+    let _registration = base
+        .register_source(&rx, Interest::READABLE, 42, move |token, flags| {
+            seen_cb.borrow_mut().push((token, flags))
+        })
+        .unwrap();
+
+    // The data is never read, so the registration stays ready.
+    tx.write_all(b"x").unwrap();
+    base.loop_(LoopFlags::ONCE);
+    assert_eq!(*seen.borrow(), vec![(42, EventFlags::READ)]);
+}
+
+#[test]
+fn add_fd_and_add_fd_timeout_deliver_their_trigger() {
+    let mut base = Base::new().unwrap();
+    let (ready_rx, mut ready_tx) = UnixStream::pair().unwrap();
+    let (idle_rx, _idle_tx) = UnixStream::pair().unwrap();
+    let seen = Rc::new(RefCell::new(Vec::new()));
+
+    let on_ready = seen.clone();
+    let _ready = base
+        .add_fd(&ready_rx, EventFlags::READ, move |flags| {
+            on_ready.borrow_mut().push(("ready", flags))
+        })
+        .unwrap();
+    let on_idle = seen.clone();
+    let _idle = base
+        .add_fd_timeout(
+            &idle_rx,
+            EventFlags::READ,
+            Duration::from_millis(10),
+            move |flags| on_idle.borrow_mut().push(("idle", flags)),
+        )
+        .unwrap();
+
+    ready_tx.write_all(b"x").unwrap();
+    base.run();
+
+    assert_eq!(
+        *seen.borrow(),
+        vec![("ready", EventFlags::READ), ("idle", EventFlags::TIMEOUT)]
+    );
+}
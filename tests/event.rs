@@ -0,0 +1,297 @@
+use libevent::{AsRawEvent, Base, EventFlags, Interval};
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+#[test]
+fn set_callback_replaces_closure() {
+    let mut base = Base::new().unwrap();
+    let fired = Rc::new(RefCell::new(Vec::new()));
+
+    let fired_a = fired.clone();
+    let mut ev = base
+        .spawn_local(Interval::new(Duration::from_millis(1)), move |_ev| {
+            fired_a.borrow_mut().push('a')
+        })
+        .unwrap();
+
+    base.run_until(|| !fired.borrow().is_empty(), Duration::from_millis(1));
+    assert_eq!(*fired.borrow(), vec!['a']);
+
+    let fired_b = fired.clone();
+    ev.set_callback(move |_ev| fired_b.borrow_mut().push('b'))
+        .unwrap();
+
+    base.run_until(|| fired.borrow().len() >= 3, Duration::from_millis(1));
+    assert_eq!(*fired.borrow(), vec!['a', 'b', 'b']);
+}
+
+#[test]
+fn flag_helpers() {
+    let flags = EventFlags::READ | EventFlags::TIMEOUT;
+
+    assert!(flags.is_read());
+    assert!(flags.is_timeout());
+    assert!(!flags.is_write());
+    assert!(!flags.is_signal());
+}
+
+#[test]
+fn stats_count_fires() {
+    let mut base = Base::new().unwrap();
+    let fired = Rc::new(Cell::new(0));
+
+    let fired_cb = fired.clone();
+    let ev = base
+        .spawn_local(Interval::new(Duration::from_millis(1)), move |_ev| {
+            fired_cb.set(fired_cb.get() + 1)
+        })
+        .unwrap();
+    assert_eq!(ev.stats().fire_count, 0);
+    ev.enable_stats();
+
+    base.run_until(|| fired.get() >= 5, Duration::from_millis(1));
+
+    let stats = ev.stats();
+    assert_eq!(stats.fire_count, fired.get());
+    assert!((5..=6).contains(&stats.fire_count));
+    assert!(stats.last_fired.unwrap().elapsed() < Duration::from_secs(1));
+}
+
+#[test]
+fn handles_expose_raw_event() {
+    fn raw_of(ev: impl AsRawEvent) -> *mut libevent_sys::event {
+        unsafe { ev.as_raw().as_ptr() }
+    }
+
+    let mut base = Base::new().unwrap();
+    let seen = Rc::new(Cell::new(std::ptr::null_mut()));
+
+    let seen_cb = seen.clone();
+    let ev = base
+        .spawn_local(Interval::new(Duration::from_millis(1)), move |ev| {
+            seen_cb.set(raw_of(&*ev))
+        })
+        .unwrap();
+    let raw = raw_of(&ev);
+    assert!(!raw.is_null());
+
+    base.run_until(|| !seen.get().is_null(), Duration::from_millis(1));
+    assert_eq!(seen.get(), raw);
+}
+
+// Handles hash by the identity of the event, which never changes.
+#[allow(clippy::mutable_key_type)]
+#[test]
+fn handles_hash_by_identity() {
+    use std::collections::HashSet;
+
+    let mut base = Base::new().unwrap();
+    let a = base
+        .spawn_local(Interval::new(Duration::from_secs(1)), |_ev| {})
+        .unwrap();
+    let b = base
+        .spawn_local(Interval::new(Duration::from_secs(1)), |_ev| {})
+        .unwrap();
+
+    let mut set = HashSet::new();
+    set.insert(a.clone());
+
+    assert!(set.contains(&a));
+    assert!(!set.contains(&b));
+    assert!(a != b);
+}
+
+#[test]
+fn label_shows_in_debug() {
+    let mut base = Base::new().unwrap();
+    let ev = base
+        .spawn_local(Interval::new(Duration::from_secs(1)), |_ev| {})
+        .unwrap();
+    assert_eq!(ev.label(), None);
+
+    ev.set_label("heartbeat");
+    assert_eq!(ev.label().as_deref(), Some("heartbeat"));
+    assert!(format!("{:?}", ev).contains("heartbeat"));
+}
+
+#[test]
+fn from_fd_accepts_tcp_stream() {
+    use libevent::Event;
+    use std::io::Write;
+    use std::net::{TcpListener, TcpStream};
+
+    let mut base = Base::new().unwrap();
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+    let (mut server, _) = listener.accept().unwrap();
+
+    let readable = Rc::new(Cell::new(false));
+    let on_read = readable.clone();
+    let _ev = base
+        .spawn_local(
+            Event::from_fd(&client, EventFlags::READ, None),
+            move |_ev, _fd, flags| on_read.set(flags.contains(EventFlags::READ)),
+        )
+        .unwrap();
+
+    server.write_all(b"ping").unwrap();
+    base.run_until(|| readable.get(), Duration::from_millis(1));
+    assert!(readable.get());
+}
+
+#[test]
+fn interval_is_persistent_and_oneshot_is_not() {
+    use libevent::Oneshot;
+
+    let mut base = Base::new().unwrap();
+    let interval = base
+        .spawn_local(Interval::new(Duration::from_secs(1)), |_ev| {})
+        .unwrap();
+    let oneshot = base
+        .spawn_local(Oneshot::new(Duration::from_secs(1)), |_ev| {})
+        .unwrap();
+
+    assert!(interval.is_persistent());
+    assert!(!oneshot.is_persistent());
+}
+
+#[test]
+fn priority_boost_is_restored() {
+    let mut base = Base::new().unwrap();
+    base.priority_init(4).unwrap();
+    let ev = base
+        .spawn_local(Interval::new(Duration::from_secs(1)), |_ev| {})
+        .unwrap();
+    let original = ev.priority();
+
+    let boosted = ev.with_priority_boost(0, || ev.priority());
+    assert_eq!(boosted, 0);
+    assert_eq!(ev.priority(), original);
+}
+
+#[test]
+fn priority_reports_assigned_priority() {
+    let mut base = Base::new().unwrap();
+    base.priority_init(4).unwrap();
+    base.set_timer_priority(Some(2)).unwrap();
+
+    let ev = base
+        .spawn_local(Interval::new(Duration::from_secs(1)), |_ev| {})
+        .unwrap();
+    assert_eq!(ev.priority(), 2);
+}
+
+#[test]
+fn interval_flags_reject_fd_flags() {
+    let mut base = Base::new().unwrap();
+    let ev = Interval::with_flags(Duration::from_secs(1), EventFlags::empty()).unwrap();
+    let ev = base.spawn_local(ev, |_ev| {}).unwrap();
+    assert!(ev.is_persistent());
+
+    for flags in [EventFlags::READ, EventFlags::WRITE | EventFlags::ET].iter() {
+        let err = Interval::with_flags(Duration::from_secs(1), *flags).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    }
+}
+
+#[test]
+fn stateful_interval_shares_state_with_owner() {
+    let mut base = Base::new().unwrap();
+    let seen = Rc::new(Cell::new(0));
+
+    let on_fire = seen.clone();
+    let (_ev, state) = base
+        .add_stateful_interval(Duration::from_millis(1), 0u32, move |count| {
+            *count += 1;
+            on_fire.set(*count);
+        })
+        .unwrap();
+
+    base.run_until(|| *state.borrow() >= 3, Duration::from_millis(1));
+    assert_eq!(*state.borrow(), seen.get());
+    assert!(seen.get() >= 3);
+}
+
+#[test]
+fn time_until_fire_reports_idle_timeout() {
+    use std::os::unix::net::UnixStream;
+
+    let mut base = Base::new().unwrap();
+    let (rx, _tx) = UnixStream::pair().unwrap();
+
+    let idle = base
+        .add_fd_timeout(&rx, EventFlags::READ, Duration::from_millis(500), |_| {})
+        .unwrap();
+    let left = idle.time_until_fire().unwrap();
+    assert!(left <= Duration::from_millis(500), "{:?}", left);
+    assert!(left >= Duration::from_millis(400), "{:?}", left);
+
+    let ready = base.add_fd(&rx, EventFlags::READ, |_| {}).unwrap();
+    assert_eq!(ready.time_until_fire(), None);
+}
+
+#[test]
+fn exec_time_limit_reports_and_stops_slow_callback() {
+    use libevent::ExitReason;
+    use std::thread;
+
+    let mut base = Base::new().unwrap();
+    let fired = Rc::new(Cell::new(0));
+    let exceeded = Arc::new(Mutex::new(Vec::new()));
+
+    let on_fire = fired.clone();
+    let ev = base
+        .spawn_local(Interval::new(Duration::from_millis(1)), move |_ev| {
+            on_fire.set(on_fire.get() + 1);
+            thread::sleep(Duration::from_millis(20));
+        })
+        .unwrap();
+    ev.set_exec_time_limit(Some(Duration::from_millis(5)));
+    let on_exceeded = exceeded.clone();
+    ev.on_exec_time_exceeded(move |took| {
+        on_exceeded.lock().unwrap().push(took);
+        true
+    });
+
+    assert_eq!(base.run(), ExitReason::NoPendingEvents);
+    assert_eq!(fired.get(), 1);
+    let exceeded = exceeded.lock().unwrap();
+    assert_eq!(exceeded.len(), 1);
+    assert!(exceeded[0] >= Duration::from_millis(20), "{:?}", exceeded);
+}
+
+#[test]
+fn panicking_exec_time_callback_ends_run_and_is_kept() {
+    use libevent::ExitReason;
+    use std::thread;
+
+    let mut base = Base::new().unwrap();
+    let ev = base
+        .spawn_local(Interval::new(Duration::from_millis(1)), |_ev| {
+            thread::sleep(Duration::from_millis(5));
+        })
+        .unwrap();
+    ev.set_exec_time_limit(Some(Duration::from_millis(1)));
+    ev.on_exec_time_exceeded(|_took| panic!("too slow"));
+
+    for _ in 0..2 {
+        assert_eq!(base.run(), ExitReason::Panicked);
+        let payload = base.take_panic().unwrap();
+        assert_eq!(payload.downcast_ref::<&str>(), Some(&"too slow"));
+    }
+}
+
+#[test]
+fn inactive_event_can_move_to_another_thread() {
+    use libevent::Event;
+    use std::os::unix::net::UnixStream;
+    use std::thread;
+
+    let (_tx, rx) = UnixStream::pair().unwrap();
+    let ev = Event::from_fd(&rx, EventFlags::READ, None);
+    ev.set_label("moved");
+    let label = thread::spawn(move || ev.label()).join().unwrap();
+    assert_eq!(label.as_deref(), Some("moved"));
+}
@@ -0,0 +1,37 @@
+use libevent::{Base, EventFlags};
+use std::io::{Read, Write};
+use std::os::unix::net::UnixStream;
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[test]
+fn block_on_awaits_timer_then_socket() {
+    let mut base = Base::new().unwrap();
+    let (mut rx, mut tx) = UnixStream::pair().unwrap();
+
+    let sleep = base.sleep(Duration::from_millis(20)).unwrap();
+    let ready = base.ready(&rx, EventFlags::READ).unwrap();
+
+    let writer = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(50));
+        tx.write_all(b"ping").unwrap();
+    });
+
+    let start = Instant::now();
+    let (slept, flags) = base
+        .block_on(async {
+            sleep.await;
+            let slept = start.elapsed();
+            (slept, ready.await)
+        })
+        .unwrap();
+
+    assert!(slept >= Duration::from_millis(20));
+    assert!(flags.contains(EventFlags::READ));
+
+    let mut buf = [0; 4];
+    rx.read_exact(&mut buf).unwrap();
+    assert_eq!(&buf, b"ping");
+
+    writer.join().unwrap();
+}
@@ -0,0 +1,117 @@
+#![cfg(feature = "http")]
+
+use libevent::{Base, HttpServer};
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+fn localhost() -> SocketAddr {
+    "127.0.0.1:0".parse().unwrap()
+}
+
+/// Sends a raw GET request for `path` from another thread, and returns a
+/// receiver for the full response.
+fn get(addr: SocketAddr, path: &str) -> mpsc::Receiver<String> {
+    let (tx, rx) = mpsc::channel();
+    let request = format!("GET {} HTTP/1.1\r\nHost: x\r\nConnection: close\r\n\r\n", path);
+
+    thread::spawn(move || {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        stream.write_all(request.as_bytes()).unwrap();
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        tx.send(response).unwrap();
+    });
+
+    rx
+}
+
+#[test]
+fn server_responds() {
+    let mut base = Base::new().unwrap();
+    let mut server = HttpServer::bind(&mut base, localhost()).unwrap();
+    server
+        .set_handler("/hello", |req| req.respond(200, b"hi there").unwrap())
+        .unwrap();
+
+    let rx = get(server.local_addr().unwrap(), "/hello");
+    let mut response = None;
+    base.run_until(
+        || {
+            response = rx.try_recv().ok();
+            response.is_some()
+        },
+        Duration::from_millis(1),
+    );
+
+    let response = response.unwrap();
+    assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+    assert!(response.ends_with("\r\n\r\nhi there"), "{}", response);
+}
+
+#[test]
+fn handler_reads_query_params() {
+    let mut base = Base::new().unwrap();
+    let mut server = HttpServer::bind(&mut base, localhost()).unwrap();
+    server
+        .set_handler("/search", |req| {
+            let body = format!(
+                "{:?} {:?} {:?}",
+                req.query_param("q"),
+                req.query_param("n"),
+                req.query_param("missing")
+            );
+            req.respond(200, body.as_bytes()).unwrap()
+        })
+        .unwrap();
+
+    let rx = get(server.local_addr().unwrap(), "/search?q=rust&n=10");
+    let mut response = None;
+    base.run_until(
+        || {
+            response = rx.try_recv().ok();
+            response.is_some()
+        },
+        Duration::from_millis(1),
+    );
+
+    let response = response.unwrap();
+    assert!(
+        response.ends_with(r#"Some("rust") Some("10") None"#),
+        "{}",
+        response
+    );
+}
+
+#[test]
+fn client_receives_server_body() {
+    use libevent::{HttpClient, Response};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    let mut base = Base::new().unwrap();
+    let mut server = HttpServer::bind(&mut base, localhost()).unwrap();
+    server
+        .set_handler("/hook", |req| req.respond(200, b"delivered").unwrap())
+        .unwrap();
+
+    let url = format!("http://{}/hook", server.local_addr().unwrap());
+    let response = Rc::new(RefCell::new(None));
+    let on_response = response.clone();
+    HttpClient::get(&mut base, &url, move |res| {
+        *on_response.borrow_mut() = Some(res.unwrap())
+    })
+    .unwrap();
+
+    base.run_until(|| response.borrow().is_some(), Duration::from_millis(1));
+    assert_eq!(
+        response.borrow_mut().take(),
+        Some(Response {
+            status: 200,
+            body: b"delivered".to_vec(),
+        })
+    );
+}
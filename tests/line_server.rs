@@ -0,0 +1,36 @@
+use libevent::{Base, LineServer};
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpStream;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+#[test]
+fn echoes_uppercased_lines() {
+    let mut base = Base::new().unwrap();
+    let server = LineServer::bind(&mut base, "127.0.0.1:0".parse().unwrap(), |line| {
+        Some(line.to_uppercase())
+    })
+    .unwrap();
+    let addr = server.local_addr().unwrap();
+
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let mut client = TcpStream::connect(addr).unwrap();
+        client.write_all(b"hello\n").unwrap();
+
+        let mut response = String::new();
+        BufReader::new(client).read_line(&mut response).unwrap();
+        tx.send(response).unwrap();
+    });
+
+    let mut response = None;
+    base.run_until(
+        || {
+            response = rx.try_recv().ok();
+            response.is_some()
+        },
+        Duration::from_millis(1),
+    );
+    assert_eq!(response.as_deref(), Some("HELLO\n"));
+}
@@ -0,0 +1,343 @@
+use libevent::{Base, Listener};
+use std::net::{SocketAddr, TcpStream};
+
+fn localhost() -> SocketAddr {
+    "127.0.0.1:0".parse().unwrap()
+}
+
+#[cfg(feature = "stream")]
+mod incoming {
+    use super::*;
+    use futures_core::Stream;
+    use std::pin::Pin;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{mpsc, Arc};
+    use std::task::{Context, Poll, Wake, Waker};
+    use std::thread;
+    use std::time::Duration;
+
+    struct Unpark(thread::Thread);
+
+    impl Wake for Unpark {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    /// Blocks the current thread until `stream` yields its next item.
+    fn block_on_next<S: Stream + Unpin>(stream: &mut S) -> Option<S::Item> {
+        let waker = Waker::from(Arc::new(Unpark(thread::current())));
+        let mut cx = Context::from_waker(&waker);
+
+        loop {
+            match Pin::new(&mut *stream).poll_next(&mut cx) {
+                Poll::Ready(item) => return item,
+                Poll::Pending => thread::park(),
+            }
+        }
+    }
+
+    #[test]
+    fn yields_connections_across_threads() {
+        let (tx, rx) = mpsc::channel();
+        let done = Arc::new(AtomicBool::new(false));
+
+        let loop_done = done.clone();
+        let loop_thread = thread::spawn(move || {
+            let mut base = Base::new().unwrap();
+            let listener = Listener::bind(&mut base, localhost(), |_, _| {}).unwrap();
+            tx.send((listener.local_addr().unwrap(), listener.incoming()))
+                .unwrap();
+
+            base.run_until(
+                || loop_done.load(Ordering::SeqCst),
+                Duration::from_millis(1),
+            );
+        });
+
+        let (addr, mut incoming) = rx.recv().unwrap();
+        let clients = [
+            TcpStream::connect(addr).unwrap(),
+            TcpStream::connect(addr).unwrap(),
+        ];
+
+        let mut accepted: Vec<SocketAddr> = (0..2)
+            .map(|_| block_on_next(&mut incoming).unwrap().unwrap().1)
+            .collect();
+        let mut expected: Vec<SocketAddr> = clients
+            .iter()
+            .map(|client| client.local_addr().unwrap())
+            .collect();
+        accepted.sort();
+        expected.sort();
+        assert_eq!(accepted, expected);
+
+        done.store(true, Ordering::SeqCst);
+        loop_thread.join().unwrap();
+    }
+
+    #[test]
+    fn dropping_on_loop_thread_disables_listener() {
+        let mut base = Base::new().unwrap();
+        let listener = Listener::bind(&mut base, localhost(), |_, _| {}).unwrap();
+        drop(listener.incoming());
+
+        let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        for _ in 0..10 {
+            base.turn();
+        }
+
+        // The connection waited in the backlog rather than being accepted.
+        let mut incoming = listener.incoming();
+        listener.enable().unwrap();
+        base.run_until(
+            || {
+                let waker = Waker::from(Arc::new(Unpark(thread::current())));
+                let mut cx = Context::from_waker(&waker);
+                match Pin::new(&mut incoming).poll_next(&mut cx) {
+                    Poll::Ready(conn) => {
+                        let addr = conn.unwrap().unwrap().1;
+                        assert_eq!(addr, client.local_addr().unwrap());
+                        true
+                    }
+                    Poll::Pending => false,
+                }
+            },
+            Duration::from_millis(1),
+        );
+    }
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn defer_accept_waits_for_data() {
+    use std::cell::Cell;
+    use std::io::Write;
+    use std::rc::Rc;
+    use std::time::{Duration, Instant};
+
+    let mut base = Base::new().unwrap();
+    let accepted = Rc::new(Cell::new(0));
+    let on_accept = accepted.clone();
+    let listener = Listener::bind(&mut base, localhost(), move |_, _| {
+        on_accept.set(on_accept.get() + 1)
+    })
+    .unwrap();
+    listener.set_defer_accept(true).unwrap();
+
+    let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+    let idle_until = Instant::now() + Duration::from_millis(100);
+    base.run_until(|| Instant::now() >= idle_until, Duration::from_millis(5));
+    assert_eq!(accepted.get(), 0);
+
+    client.write_all(b"hello").unwrap();
+    base.run_until(|| accepted.get() == 1, Duration::from_millis(1));
+}
+
+#[test]
+fn from_fd_accepts_without_taking_ownership() {
+    use std::cell::Cell;
+    use std::net::TcpListener;
+    use std::os::unix::io::AsRawFd;
+    use std::rc::Rc;
+    use std::time::Duration;
+
+    let mut base = Base::new().unwrap();
+    let socket = TcpListener::bind(localhost()).unwrap();
+    let accepted = Rc::new(Cell::new(0));
+
+    let on_accept = accepted.clone();
+    let listener = unsafe {
+        Listener::from_fd(&mut base, socket.as_raw_fd(), move |_, _| {
+            on_accept.set(on_accept.get() + 1)
+        })
+    }
+    .unwrap();
+
+    let _client = TcpStream::connect(socket.local_addr().unwrap()).unwrap();
+    base.run_until(|| accepted.get() == 1, Duration::from_millis(1));
+
+    // The socket is still open after the listener is gone.
+    drop(listener);
+    let _client = TcpStream::connect(socket.local_addr().unwrap()).unwrap();
+    assert!(socket.accept().is_ok());
+}
+
+// Lowering the fd limit affects the whole process, so this has to run alone
+// via `cargo test -- --ignored --test-threads=1`.
+#[test]
+#[ignore]
+fn error_callback_reports_fd_exhaustion() {
+    use std::cell::RefCell;
+    use std::io;
+    use std::rc::Rc;
+    use std::time::Duration;
+
+    let mut base = Base::new().unwrap();
+    let errors = Rc::new(RefCell::new(Vec::new()));
+    let listener = Listener::bind(&mut base, localhost(), |_, _| {}).unwrap();
+
+    // The failing connection stays in the backlog, so back off right away.
+    let on_error = errors.clone();
+    let raw = unsafe { listener.as_raw() };
+    listener.set_error_callback(move |err| {
+        on_error.borrow_mut().push(err.raw_os_error());
+        unsafe { libevent_sys::evconnlistener_disable(raw.as_ptr()) };
+    });
+
+    // Connect first, so the connections wait in the backlog.
+    let addr = listener.local_addr().unwrap();
+    let _clients: Vec<_> = (0..4).map(|_| TcpStream::connect(addr).unwrap()).collect();
+
+    let mut limit = libc::rlimit {
+        rlim_cur: 0,
+        rlim_max: 0,
+    };
+    assert_eq!(unsafe { libc::getrlimit(libc::RLIMIT_NOFILE, &mut limit) }, 0);
+    let lowered = libc::rlimit {
+        rlim_cur: 256,
+        ..limit
+    };
+    assert_eq!(unsafe { libc::setrlimit(libc::RLIMIT_NOFILE, &lowered) }, 0);
+
+    // Use up all remaining fds, so that accepting fails.
+    let mut fds = Vec::new();
+    loop {
+        let fd = unsafe { libc::dup(0) };
+        if fd < 0 {
+            assert_eq!(io::Error::last_os_error().raw_os_error(), Some(libc::EMFILE));
+            break;
+        }
+        fds.push(fd);
+    }
+
+    base.run_until(|| !errors.borrow().is_empty(), Duration::from_millis(1));
+
+    for fd in fds {
+        unsafe { libc::close(fd) };
+    }
+    assert_eq!(unsafe { libc::setrlimit(libc::RLIMIT_NOFILE, &limit) }, 0);
+    assert_eq!(*errors.borrow(), vec![Some(libc::EMFILE)]);
+}
+
+#[test]
+fn disabled_listener_leaves_connections_queued() {
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use std::time::Duration;
+
+    let mut base = Base::new().unwrap();
+    let accepted = Rc::new(Cell::new(0));
+
+    let on_accept = accepted.clone();
+    let listener = Listener::bind(&mut base, localhost(), move |_, _| {
+        on_accept.set(on_accept.get() + 1)
+    })
+    .unwrap();
+    listener.disable().unwrap();
+
+    let _client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+    for _ in 0..10 {
+        base.turn();
+    }
+    assert_eq!(accepted.get(), 0);
+
+    listener.enable().unwrap();
+    base.run_until(|| accepted.get() == 1, Duration::from_millis(1));
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn reuseport_spreads_connections() {
+    use libevent::ListenerFlags;
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use std::time::Duration;
+
+    let mut bases = [Base::new().unwrap(), Base::new().unwrap()];
+    let counts = [Rc::new(Cell::new(0)), Rc::new(Cell::new(0))];
+
+    let mut addr = localhost();
+    let mut listeners = Vec::new();
+    for (base, count) in bases.iter_mut().zip(counts.iter()) {
+        let count = count.clone();
+        let listener = Listener::bind_with_flags(
+            base,
+            addr,
+            ListenerFlags::REUSEABLE_PORT,
+            move |_, _| count.set(count.get() + 1),
+        )
+        .unwrap();
+        addr = listener.local_addr().unwrap();
+        listeners.push(listener);
+    }
+
+    let clients: Vec<_> = (0..32).map(|_| TcpStream::connect(addr).unwrap()).collect();
+    let total = || counts.iter().map(|count| count.get()).sum::<usize>();
+    while total() < clients.len() {
+        for base in &bases {
+            base.turn();
+        }
+        std::thread::sleep(Duration::from_millis(1));
+    }
+
+    assert!(counts[0].get() > 0, "first listener accepted nothing");
+    assert!(counts[1].get() > 0, "second listener accepted nothing");
+}
+
+#[test]
+fn accept_reports_peer_address() {
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use std::time::Duration;
+
+    // IPv6 may be unavailable, e.g. in containers, in which case only IPv4
+    // is covered.
+    let addrs = ["127.0.0.1:0", "[::1]:0"];
+
+    for addr in addrs.iter().map(|addr| addr.parse::<SocketAddr>().unwrap()) {
+        let mut base = Base::new().unwrap();
+        let peer = Rc::new(Cell::new(None));
+
+        let on_accept = peer.clone();
+        let listener = match Listener::bind(&mut base, addr, move |_, addr| {
+            on_accept.set(Some(addr))
+        }) {
+            Ok(listener) => listener,
+            Err(_) if addr.is_ipv6() => continue,
+            Err(err) => panic!("{}", err),
+        };
+
+        let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        base.run_until(|| peer.get().is_some(), Duration::from_millis(1));
+        assert_eq!(peer.get(), Some(client.local_addr().unwrap()));
+    }
+}
+
+#[test]
+fn connection_cap_holds_back_accepts() {
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use std::time::{Duration, Instant};
+
+    let mut base = Base::new().unwrap();
+    let accepted = Rc::new(Cell::new(0));
+
+    let on_accept = accepted.clone();
+    let listener = Listener::bind(&mut base, localhost(), move |_, _| {
+        on_accept.set(on_accept.get() + 1)
+    })
+    .unwrap();
+    listener.set_max_connections(2);
+
+    let addr = listener.local_addr().unwrap();
+    let _clients: Vec<_> = (0..3).map(|_| TcpStream::connect(addr).unwrap()).collect();
+
+    base.run_until(|| accepted.get() == 2, Duration::from_millis(1));
+    let idle_until = Instant::now() + Duration::from_millis(50);
+    base.run_until(|| Instant::now() >= idle_until, Duration::from_millis(5));
+    assert_eq!(accepted.get(), 2);
+
+    listener.release_connection();
+    base.run_until(|| accepted.get() == 3, Duration::from_millis(1));
+}
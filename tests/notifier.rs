@@ -0,0 +1,102 @@
+use libevent::{Base, Notifier};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::Duration;
+
+/// Runs a loop with a notifier on another thread until the notifier's
+/// callback ran `until` times, returning the notifier, the callback count and
+/// the loop thread.
+fn spawn_loop(until: usize) -> (Notifier, Arc<AtomicUsize>, thread::JoinHandle<()>) {
+    let (tx, rx) = mpsc::channel();
+    let calls = Arc::new(AtomicUsize::new(0));
+
+    let loop_calls = calls.clone();
+    let loop_thread = thread::spawn(move || {
+        let mut base = Base::new().unwrap();
+        let on_notify = loop_calls.clone();
+        let (notifier, _event) = base
+            .notifier(move || {
+                on_notify.fetch_add(1, Ordering::SeqCst);
+            })
+            .unwrap();
+        tx.send(notifier).unwrap();
+
+        base.run_until(
+            || loop_calls.load(Ordering::SeqCst) >= until,
+            Duration::from_millis(1),
+        );
+    });
+
+    (rx.recv().unwrap(), calls, loop_thread)
+}
+
+#[test]
+fn notify_wakes_loop_from_another_thread() {
+    let (notifier, calls, loop_thread) = spawn_loop(1);
+
+    for _ in 0..5 {
+        notifier.notify();
+    }
+
+    loop_thread.join().unwrap();
+    // Notifications may be coalesced.
+    let calls = calls.load(Ordering::SeqCst);
+    assert!((1..=5).contains(&calls), "{}", calls);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn linux_notifier_uses_eventfd() {
+    use libevent::NotifyMechanism;
+
+    let (notifier, calls, loop_thread) = spawn_loop(1);
+    assert_eq!(notifier.mechanism(), NotifyMechanism::EventFd);
+
+    notifier.notify();
+    loop_thread.join().unwrap();
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn remote_runs_closures_on_loop_thread() {
+    let mut base = Base::new().unwrap();
+    let remote = base.remote().unwrap();
+    let loop_thread = thread::current().id();
+    let (tx, rx) = mpsc::channel();
+
+    let cloned = remote.clone();
+    thread::spawn(move || cloned.schedule(move || tx.send(thread::current().id()).unwrap()))
+        .join()
+        .unwrap();
+
+    let mut ran_on = None;
+    base.run_until(
+        || {
+            ran_on = rx.try_recv().ok();
+            ran_on.is_some()
+        },
+        Duration::from_millis(1),
+    );
+    assert_eq!(ran_on, Some(loop_thread));
+}
+
+#[test]
+fn remote_keeps_working_while_io_is_suspended() {
+    let mut base = Base::new().unwrap();
+    let remote = base.remote().unwrap();
+    base.suspend_io().unwrap();
+
+    let ran = Arc::new(AtomicUsize::new(0));
+    let on_run = ran.clone();
+    thread::spawn(move || {
+        remote.schedule(move || {
+            on_run.fetch_add(1, Ordering::SeqCst);
+        })
+    })
+    .join()
+    .unwrap();
+
+    base.run_until(|| ran.load(Ordering::SeqCst) == 1, Duration::from_millis(1));
+    base.resume_io().unwrap();
+}
@@ -0,0 +1,19 @@
+use libevent::{BasePool, Interval};
+use std::time::Duration;
+
+#[test]
+fn spreads_events_across_bases() {
+    let mut pool = BasePool::new(2).unwrap();
+
+    let events: Vec<_> = (0..10)
+        .map(|_| {
+            pool.spawn_local_least_loaded(Interval::new(Duration::from_secs(1)), |_ev| {})
+                .unwrap()
+        })
+        .collect();
+
+    let counts: Vec<usize> = pool.bases().iter().map(|base| base.event_count()).collect();
+    assert_eq!(counts, vec![5, 5]);
+
+    drop(events);
+}
@@ -0,0 +1,44 @@
+use libevent::{Interval, Oneshot, RecordingBase};
+use std::cell::Cell;
+use std::rc::Rc;
+use std::time::Duration;
+
+#[test]
+fn records_interleaved_timers_in_order() {
+    let mut rec = RecordingBase::new().unwrap();
+    let ticks = Rc::new(Cell::new(0));
+
+    let ticks_cb = ticks.clone();
+    let interval = rec
+        .base_mut()
+        .spawn_local(Interval::new(Duration::from_millis(20)), move |_ev| {
+            ticks_cb.set(ticks_cb.get() + 1)
+        })
+        .unwrap();
+    let oneshot = rec
+        .base_mut()
+        .spawn_local(Oneshot::new(Duration::from_millis(30)), |_ev| {})
+        .unwrap();
+
+    rec.base()
+        .run_until(|| ticks.get() == 2, Duration::from_millis(1));
+
+    rec.assert_order(&[&interval, &oneshot, &interval]);
+    let activations = rec.activations();
+    assert!(activations.iter().all(|a| a.fd == -1));
+    assert!(activations.windows(2).all(|w| w[0].time <= w[1].time));
+
+    rec.clear();
+    assert!(rec.activations().is_empty());
+}
+
+#[test]
+fn other_bases_are_not_recorded() {
+    let rec = RecordingBase::new().unwrap();
+    let mut base = libevent::Base::new().unwrap();
+
+    base.spawn(Oneshot::new(Duration::ZERO), |_ev| {}).unwrap();
+    base.turn();
+
+    assert!(rec.activations().is_empty());
+}
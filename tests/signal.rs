@@ -0,0 +1,143 @@
+#![cfg(unix)]
+
+use libevent::Base;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+/// Signal dispositions are process-wide, and libevent only lets one base at
+/// a time handle signals, so tests touching them take turns.
+static SIGNALS_TAKEN: AtomicBool = AtomicBool::new(false);
+
+/// A test's turn at handling signals, which ends once dropped.
+struct Turn;
+
+impl Turn {
+    fn take() -> Self {
+        while SIGNALS_TAKEN
+            .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            thread::yield_now();
+        }
+        Turn
+    }
+}
+
+impl Drop for Turn {
+    fn drop(&mut self) {
+        SIGNALS_TAKEN.store(false, Ordering::Release);
+    }
+}
+
+#[test]
+fn every_handler_runs_once_per_signal() {
+    let _turn = Turn::take();
+
+    let mut base = Base::new().unwrap();
+    let calls = Arc::new(AtomicUsize::new(0));
+    for _ in 0..2 {
+        let on_signal = calls.clone();
+        base.add_signal(libc::SIGUSR1, move || {
+            on_signal.fetch_add(1, Ordering::SeqCst);
+        })
+        .unwrap();
+    }
+
+    unsafe { libc::raise(libc::SIGUSR1) };
+    base.run_until(
+        || calls.load(Ordering::SeqCst) >= 2,
+        Duration::from_millis(1),
+    );
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn signal_method_is_reported_with_signal_registered() {
+    let _turn = Turn::take();
+
+    let mut base = Base::new().unwrap();
+    base.add_signal(libc::SIGUSR2, || {}).unwrap();
+
+    let method = base.signal_method().expect("unknown backend");
+    assert!(!method.is_empty());
+}
+
+#[test]
+fn dropping_base_restores_previous_disposition() {
+    static RAISED: AtomicUsize = AtomicUsize::new(0);
+
+    extern "C" fn on_raised(_signum: libc::c_int) {
+        RAISED.fetch_add(1, Ordering::SeqCst);
+    }
+
+    let _turn = Turn::take();
+    let handler = on_raised as extern "C" fn(libc::c_int) as libc::sighandler_t;
+    let previous = unsafe { libc::signal(libc::SIGUSR2, handler) };
+    assert_ne!(previous, libc::SIG_ERR);
+
+    let mut base = Base::new().unwrap();
+    let calls = Arc::new(AtomicUsize::new(0));
+    let on_signal = calls.clone();
+    base.add_signal(libc::SIGUSR2, move || {
+        on_signal.fetch_add(1, Ordering::SeqCst);
+    })
+    .unwrap();
+    drop(base);
+
+    unsafe { libc::raise(libc::SIGUSR2) };
+    assert_eq!(RAISED.load(Ordering::SeqCst), 1);
+    assert_eq!(calls.load(Ordering::SeqCst), 0);
+
+    let installed = unsafe { libc::signal(libc::SIGUSR2, previous) };
+    assert_eq!(installed, handler);
+}
+
+#[test]
+fn signal_handlers_are_left_out_of_event_count() {
+    let _turn = Turn::take();
+
+    let mut base = Base::new().unwrap();
+    base.add_signal(libc::SIGUSR1, || {}).unwrap();
+
+    assert_eq!(base.event_count(), 0);
+}
+
+#[test]
+fn handlers_survive_a_panicking_handler() {
+    let _turn = Turn::take();
+
+    let mut base = Base::new().unwrap();
+    let calls = Arc::new(AtomicUsize::new(0));
+    let on_signal = calls.clone();
+    base.add_signal(libc::SIGUSR1, move || {
+        if on_signal.fetch_add(1, Ordering::SeqCst) == 0 {
+            panic!("first signal");
+        }
+    })
+    .unwrap();
+
+    unsafe { libc::raise(libc::SIGUSR1) };
+    base.run_until(
+        || calls.load(Ordering::SeqCst) >= 1,
+        Duration::from_millis(1),
+    );
+    assert!(base.take_panic().is_some());
+
+    // Another handler can still be added, and the first one is kept.
+    let late_calls = Arc::new(AtomicUsize::new(0));
+    let on_signal = late_calls.clone();
+    base.add_signal(libc::SIGUSR1, move || {
+        on_signal.fetch_add(1, Ordering::SeqCst);
+    })
+    .unwrap();
+
+    unsafe { libc::raise(libc::SIGUSR1) };
+    base.run_until(
+        || late_calls.load(Ordering::SeqCst) >= 1,
+        Duration::from_millis(1),
+    );
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+    assert_eq!(late_calls.load(Ordering::SeqCst), 1);
+}
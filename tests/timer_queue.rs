@@ -0,0 +1,67 @@
+use libevent::{Base, TimerQueue};
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use std::time::Duration;
+
+#[test]
+fn callback_may_drop_the_queue() {
+    let mut base = Base::new().unwrap();
+    let queue = Rc::new(RefCell::new(Some(TimerQueue::new(&mut base).unwrap())));
+    let fired = Rc::new(Cell::new(0));
+
+    for _ in 0..3 {
+        let on_timeout = fired.clone();
+        let owner = queue.clone();
+        queue
+            .borrow()
+            .as_ref()
+            .unwrap()
+            .add(Duration::ZERO, move || {
+                on_timeout.set(on_timeout.get() + 1);
+                drop(owner.borrow_mut().take());
+            })
+            .unwrap();
+    }
+
+    base.run();
+    assert_eq!(fired.get(), 1);
+    assert!(queue.borrow().is_none());
+}
+
+#[test]
+fn add_rejects_unrepresentable_deadline() {
+    let mut base = Base::new().unwrap();
+    let queue = TimerQueue::new(&mut base).unwrap();
+
+    let err = queue
+        .add(Duration::from_secs(i64::MAX as u64), || {})
+        .unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    assert!(queue.add(Duration::MAX, || {}).is_err());
+    assert!(queue.is_empty());
+}
+
+#[test]
+fn panicking_callback_ends_run() {
+    use libevent::ExitReason;
+
+    let mut base = Base::new().unwrap();
+    let queue = TimerQueue::new(&mut base).unwrap();
+    let fired = Rc::new(Cell::new(false));
+
+    queue
+        .add(Duration::ZERO, || panic!("timer failed"))
+        .unwrap();
+    let on_timeout = fired.clone();
+    queue
+        .add(Duration::from_millis(1), move || on_timeout.set(true))
+        .unwrap();
+
+    assert_eq!(base.run(), ExitReason::Panicked);
+    let payload = base.take_panic().unwrap();
+    assert_eq!(payload.downcast_ref::<&str>(), Some(&"timer failed"));
+
+    // The queue carries on with the timers still pending.
+    assert_eq!(base.run(), ExitReason::NoPendingEvents);
+    assert!(fired.get());
+}
@@ -0,0 +1,60 @@
+use libevent::{Base, TimerSet};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::Duration;
+
+/// Wraps the system allocator to count allocations. This is the only test
+/// in this binary, so that no other test allocates while it counts.
+struct CountingAlloc;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+#[test]
+fn firing_timers_does_not_allocate() {
+    const NUM_TIMERS: usize = 100;
+
+    let mut base = Base::new().unwrap();
+    let fired = Rc::new(Cell::new(0usize));
+
+    // Every timer is due again after sleeping for longer than this, even
+    // with libevent's coarse clock.
+    let mut timers = TimerSet::with_capacity(&mut base, NUM_TIMERS);
+    for _ in 0..NUM_TIMERS {
+        let fired = fired.clone();
+        timers
+            .add(Duration::from_millis(1), move || fired.set(fired.get() + 1))
+            .unwrap();
+    }
+
+    // Warm up, so that libevent has sized its internal queues.
+    thread::sleep(Duration::from_millis(20));
+    base.turn();
+
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    for _ in 0..10 {
+        thread::sleep(Duration::from_millis(20));
+        base.turn();
+    }
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - before;
+
+    drop(timers);
+    assert_eq!(allocations, 0);
+    assert_eq!(fired.get(), 11 * NUM_TIMERS);
+}
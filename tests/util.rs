@@ -0,0 +1,18 @@
+use libevent::util::parse_sockaddr;
+use std::net::SocketAddr;
+
+#[test]
+fn parses_socket_addresses() {
+    assert_eq!(
+        parse_sockaddr("127.0.0.1:8080").unwrap(),
+        "127.0.0.1:8080".parse::<SocketAddr>().unwrap()
+    );
+    assert_eq!(
+        parse_sockaddr("[::1]:443").unwrap(),
+        "[::1]:443".parse::<SocketAddr>().unwrap()
+    );
+    assert_eq!(parse_sockaddr("::1").unwrap().port(), 0);
+
+    let err = parse_sockaddr("not an address").unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+}